@@ -0,0 +1,122 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{db::Database, markdown};
+
+/// Writes an mdBook with one page per symbol in `db` to `dir`, grouped by Doxygen group or else
+/// by the header the symbol is declared in. Fields are shown on the page of their record.
+pub fn emit(dir: &Path, db: &Database, options: &markdown::Options) -> std::io::Result<()> {
+    let src = dir.join("src");
+    std::fs::create_dir_all(&src)?;
+    let book_toml = dir.join("book.toml");
+    if !book_toml.exists() {
+        std::fs::write(
+            &book_toml,
+            "[book]\ntitle = \"API reference\"\nsrc = \"src\"\n",
+        )?;
+    }
+    // Rust items aren't part of the book, so there is nothing to link to
+    let paths = HashMap::new();
+    let mut groups = BTreeMap::<String, Vec<&str>>::new();
+    for (name, symbol) in &db.symbols {
+        if name.contains('.') {
+            continue;
+        }
+        let group = symbol.doc.groups.first().cloned().or_else(|| {
+            let file = symbol.location.as_ref()?.file.file_name()?;
+            Some(file.to_string_lossy().into_owned())
+        });
+        groups
+            .entry(group.unwrap_or_else(|| "Other".to_owned()))
+            .or_default()
+            .push(name);
+    }
+    let mut summary = String::from("# Summary\n\n");
+    for (group, names) in &mut groups {
+        names.sort_unstable();
+        let slug = slug(group);
+        std::fs::create_dir_all(src.join(&slug))?;
+        let mut index = format!("# {}\n\n", group);
+        summary.push_str(&format!("- [{}]({}/README.md)\n", group, slug));
+        for name in names.iter() {
+            let symbol = &db.symbols[*name];
+            markdown::copy_assets(symbol, options)?;
+            let mut page = format!("# `{}`\n\n", name);
+            let docs = markdown::render_markdown(name, symbol, db, &paths, None, options);
+            page.push_str(&demote_headings(&docs, 1));
+            page.push('\n');
+            // fields have their docs stored under names like `Foo.bar`
+            let prefix = format!("{}.", name);
+            let mut fields = db
+                .symbols
+                .iter()
+                .filter(|(n, _)| n.starts_with(&prefix))
+                .collect::<Vec<_>>();
+            fields.sort_unstable_by_key(|(n, _)| *n);
+            if !fields.is_empty() {
+                page.push_str("\n## Fields\n");
+            }
+            for (field, symbol) in fields {
+                let docs = markdown::render_markdown(field, symbol, db, &paths, None, options);
+                page.push_str(&format!("\n### `{}`\n\n", &field[prefix.len()..]));
+                page.push_str(&demote_headings(&docs, 3));
+                page.push('\n');
+            }
+            let file = format!("{}.md", slug_name(name));
+            std::fs::write(src.join(&slug).join(&file), page)?;
+            index.push_str(&format!("- [`{}`]({})\n", name, file));
+            summary.push_str(&format!("  - [`{}`]({}/{})\n", name, slug, file));
+        }
+        std::fs::write(src.join(&slug).join("README.md"), index)?;
+    }
+    std::fs::write(src.join("SUMMARY.md"), summary)
+}
+
+/// Moves the headings of rendered docs `levels` levels down, below the headings of the page.
+fn demote_headings(text: &str, levels: usize) -> String {
+    let mut fence: Option<&str> = None;
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let run = trimmed.len() - trimmed.trim_start_matches(['`', '~']).len();
+            match fence {
+                Some(open) if trimmed.starts_with(open) => fence = None,
+                Some(_) => {}
+                None if run >= 3 => fence = Some(&trimmed[..run]),
+                None if line.starts_with('#') => return format!("{}{}", "#".repeat(levels), line),
+                None => {}
+            }
+            line.to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Turns a group or header name into a directory name.
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Turns a symbol name into a file name, keeping its case so that names differing only in case
+/// stay apart on case-sensitive file systems.
+fn slug_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
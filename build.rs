@@ -0,0 +1,87 @@
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::sync::{self, Warning};
+
+/// Copies docs into the Rust sources `rust_srcs` from the C sources `c_srcs` in a build script,
+/// with the options of the sync command otherwise left at their defaults. See `sync_with`.
+pub fn sync(
+    rust_srcs: &[impl AsRef<Path>],
+    c_srcs: &[impl AsRef<Path>],
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let options = sync::Options {
+        rust_srcs: rust_srcs.iter().map(|p| p.as_ref().to_owned()).collect(),
+        c: crate::c::Options {
+            c_srcs: c_srcs.iter().map(|p| p.as_ref().to_owned()).collect(),
+            ..Default::default()
+        },
+        markdown: crate::default_options()?,
+        ..Default::default()
+    };
+    sync_with(options)
+}
+
+/// Copies docs into Rust sources in a build script. Instead of changing the sources, the ones
+/// with docs are written to the same path relative to the crate in `OUT_DIR`, for the crate to
+/// include with `include!(concat!(env!("OUT_DIR"), "/src/ffi.rs"))`. The include directories
+/// that `links` dependencies export as `DEP_*_INCLUDE` are passed to clang, and cargo is told to
+/// run the build script again when any of the files the docs were made from changes. Warnings
+/// are passed on to cargo. Returns the paths written. `options.state` can't be used, since
+/// sources it skips would be missing from a new `OUT_DIR`.
+pub fn sync_with(mut options: sync::Options) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if options.state.is_some() {
+        return Err("--state can't be used in a build script".into());
+    }
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").ok_or("OUT_DIR isn't set")?);
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    let mut includes = env::vars_os()
+        .filter(|(name, _)| {
+            name.to_str()
+                .is_some_and(|name| name.starts_with("DEP_") && name.ends_with("_INCLUDE"))
+        })
+        .collect::<Vec<_>>();
+    includes.sort();
+    for (name, dirs) in includes {
+        println!("cargo:rerun-if-env-changed={}", name.to_string_lossy());
+        for dir in env::split_paths(&dirs) {
+            options
+                .c
+                .clang_args
+                .push(format!("-I{}", dir.to_string_lossy()));
+        }
+    }
+    let outcome = sync::run(&options)?;
+    for warning in &outcome.warnings {
+        if !matches!(
+            warning,
+            Warning::Unresolved { .. } | Warning::Ambiguous { .. }
+        ) {
+            println!("cargo:warning={}", warning);
+        }
+    }
+    for input in &outcome.inputs {
+        println!("cargo:rerun-if-changed={}", input.display());
+    }
+    let mut written = Vec::new();
+    for file in outcome.files {
+        // paths are taken relative to the crate, and only their normal components are kept, so
+        // that nothing is written outside OUT_DIR
+        let path = manifest_dir
+            .as_deref()
+            .and_then(|dir| file.path.strip_prefix(dir).ok())
+            .unwrap_or(&file.path);
+        let relative = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect::<PathBuf>();
+        let path = out_dir.join(relative);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, file.source)?;
+        written.push(path);
+    }
+    Ok(written)
+}
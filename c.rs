@@ -0,0 +1,1904 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    db::{Attribute, Database, Kind, Location, Symbol},
+    doc::{self, Annotations, Direction, Doc},
+    doxygen, gtkdoc, headerdoc, html, man, structured,
+};
+
+#[derive(clap::Args, Debug, Default)]
+pub struct Options {
+    /// List of C sources to pull doc comments from.
+    #[clap(short, long)]
+    pub c_srcs: Vec<PathBuf>,
+    /// JSON manifest of the API headers to pull doc comments from, for projects without a
+    /// compile_commands.json, like `{"clang_args": ["-Iinclude"], "headers": [{"path":
+    /// "include/foo/*.h", "clang_args": ["-DFOO_API="], "prefixes": ["foo_", "Foo"]}]}`. Only
+    /// symbols starting with one of the prefixes of a header are taken from it, if it has any.
+    /// Paths, including those in clang arguments, are relative to the manifest.
+    #[clap(long, value_name = "MANIFEST")]
+    pub headers: Option<PathBuf>,
+    /// Extra argument to pass to clang when parsing C sources. Can be used multiple times, for
+    /// example to define macros like `--clang-arg=-DG_INLINE_FUNC=static inline`.
+    #[clap(long = "clang-arg", allow_hyphen_values = true)]
+    pub clang_args: Vec<String>,
+    /// Directory or file to load libclang from. Overrides LIBCLANG_PATH.
+    #[cfg(feature = "runtime")]
+    #[clap(long)]
+    pub libclang_path: Option<PathBuf>,
+    /// Target triple to parse C sources for, e.g. `thumbv7em-none-eabihf`.
+    #[clap(long)]
+    pub target: Option<String>,
+    /// Root directory for headers and libraries of the target.
+    #[clap(long)]
+    pub sysroot: Option<PathBuf>,
+    /// Machine-specific flag to pass to clang without the leading `-m`, for example
+    /// `--machine cpu=cortex-m4` or `--machine thumb`. Can be used multiple times.
+    #[clap(long = "machine")]
+    pub machine_flags: Vec<String>,
+    /// Parse C sources like MSVC does, for the Windows SDK headers and others written for it.
+    /// Enables Microsoft extensions and compatibility and, unless --target is used, targets
+    /// `x86_64-pc-windows-msvc`. The SDK and MSVC include directories are taken from the
+    /// `INCLUDE` environment variable, like the one set by `vcvarsall.bat`, or can be given
+    /// with --isystem. SAL annotations like `_In_` and `_Out_opt_` on functions fill in the
+    /// direction and nullability of parameters and return values.
+    #[clap(long)]
+    pub msvc: bool,
+    /// Language to parse C sources as, instead of choosing it by their extension. `cuda` only
+    /// parses host code and doesn't need a CUDA installation.
+    #[clap(long, value_enum)]
+    pub language: Option<Language>,
+    /// Remove the qualifiers of CUDA and OpenCL device code, like `__device__`, `__global__` and
+    /// `__kernel`, so that headers of GPU runtimes and libraries that use them parse as C.
+    #[clap(long)]
+    pub ignore_device_qualifiers: bool,
+    /// Add a directory to the system include search path. Symbols declared in system headers are
+    /// ignored unless --include-system-headers is used.
+    #[clap(long = "isystem")]
+    pub system_include_dirs: Vec<PathBuf>,
+    /// Parse C sources for a bare-metal target, like the headers of CMSIS, ESP-IDF or another
+    /// vendor SDK. Headers of the host are not searched, only clang's own ones, `include` in the
+    /// --sysroot, like the newlib headers of an `arm-none-eabi` toolchain, and the directories
+    /// given with --isystem or -I. Unknown pragmas and attributes are ignored and parsing goes
+    /// on after any number of errors.
+    #[clap(long)]
+    pub bare_metal: bool,
+    /// Don't search any standard include directory, not even clang's own one with headers like
+    /// `stddef.h`, for SDKs that come with the headers of their compiler. Include directories
+    /// then all have to be given with --isystem or -I.
+    #[clap(long)]
+    pub nostdinc: bool,
+    /// Installed library to pass the compiler flags of to clang, like its include directories,
+    /// as given by `pkg-config --cflags`. Can be used multiple times. The `PKG_CONFIG`
+    /// environment variable overrides the pkg-config program.
+    #[clap(long = "pkg-config", value_name = "NAME")]
+    pub pkg_config: Vec<String>,
+    /// Command that prints compiler flags to pass to clang, like the include directories of
+    /// libraries installed with vcpkg or conan where there is no pkg-config. Its output is split
+    /// at whitespace outside double quotes. The command and its arguments are split at
+    /// whitespace. Can be used multiple times.
+    #[clap(long = "cflags-cmd", value_name = "COMMAND")]
+    pub cflags_commands: Vec<String>,
+    /// Also take docs from symbols declared in system headers.
+    #[clap(long)]
+    pub include_system_headers: bool,
+    /// Precompiled header to include in every C source. Declarations from it are not searched
+    /// for docs. If --prelude is used, the generated header is written here instead, and only
+    /// rebuilt when the prelude is newer. With more than one --define-set, each set gets its own
+    /// header next to it, named like `foo.<set>.pch`.
+    #[clap(long)]
+    pub pch: Option<PathBuf>,
+    /// Header to generate a precompiled header from, which is then included in every C source.
+    #[clap(long)]
+    pub prelude: Option<PathBuf>,
+    /// Named set of extra clang arguments, written as `name:-DFOO,-DBAR`. Can be used multiple
+    /// times, each C source is then parsed once for every set and the docs are merged.
+    #[clap(long = "define-set", allow_hyphen_values = true)]
+    pub define_sets: Vec<DefineSet>,
+    /// Skip parsing function bodies in C sources. Speeds up parsing of headers with many
+    /// inline functions.
+    #[clap(long)]
+    pub skip_function_bodies: bool,
+    /// Parse every C source, even ones that don't mention any symbol that still needs docs.
+    #[clap(long)]
+    pub parse_all: bool,
+    /// Directory to search for files included with `\example` or `\snippet`. Can be used
+    /// multiple times.
+    #[clap(long = "example-dir")]
+    pub example_dirs: Vec<PathBuf>,
+    /// Directory to search for images referenced by `\image` or `<img>`, besides the directory
+    /// of the file with the comment. Can be used multiple times.
+    #[clap(long = "image-dir")]
+    pub image_dirs: Vec<PathBuf>,
+    /// Directory of HTML docs generated by gtk-doc or Doxygen to take docs from, for libraries
+    /// that only publish those. Docs in C sources take precedence. With `dir=url`, links to
+    /// other pages are made absolute with the URL the docs are published at. Experimental, can
+    /// be used multiple times.
+    #[clap(long = "html-docs", value_name = "DIR[=URL]")]
+    pub html_docs: Vec<html::HtmlDocs>,
+    /// Man page in section 3, or directory to search for them, to take the docs of the
+    /// functions they are named after from. Docs in C sources take precedence. Can be used
+    /// multiple times.
+    #[clap(long = "man-pages", value_name = "PATH")]
+    pub man_pages: Vec<PathBuf>,
+    /// Also take docs from the comment above a declaration when up to this many blank lines
+    /// separate the two, and from plain `/* */` and `//` comments. libclang only takes doc
+    /// comments right above a declaration.
+    #[clap(long, value_name = "LINES")]
+    pub comment_distance: Option<usize>,
+    /// Header to prefer docs from when a symbol is declared with docs in more than one, like a
+    /// public and an internal header. Can be a glob, matched against the end of the path, and can
+    /// be used multiple times in order of preference. Other headers come last.
+    #[clap(long = "prefer-header", value_name = "GLOB")]
+    pub preferred_headers: Vec<glob::Pattern>,
+    /// Print where the docs for each symbol were found.
+    #[clap(short, long)]
+    pub verbose: bool,
+}
+
+/// The contents of a manifest given with `--headers`.
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct Manifest {
+    /// Arguments to pass to clang for every header.
+    pub clang_args: Vec<String>,
+    pub headers: Vec<ManifestHeader>,
+}
+
+/// A header, or a glob matching several, in a `Manifest`.
+#[derive(serde::Deserialize)]
+pub struct ManifestHeader {
+    pub path: PathBuf,
+    /// Arguments to pass to clang for this header, after those of the manifest.
+    #[serde(default)]
+    pub clang_args: Vec<String>,
+    /// Prefixes of the names of the symbols to take from this header and the ones it includes.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+/// A glob of C sources to parse, given with `--c-srcs` or in the `--headers` manifest.
+pub(crate) struct Source {
+    pub pattern: PathBuf,
+    /// Arguments to pass to clang for these sources, after the ones from the options.
+    pub clang_args: Vec<String>,
+    /// Prefixes of the symbols to take from these sources, or empty to take all.
+    pub prefixes: Vec<String>,
+}
+
+impl Source {
+    fn wants(&self, name: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| name.starts_with(p.as_str()))
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    C,
+    /// CUDA, for headers with `__host__` and `__device__` functions.
+    Cuda,
+    /// OpenCL C, for headers shared with kernels.
+    Opencl,
+}
+
+/// Qualifiers removed by `--ignore-device-qualifiers`.
+const DEVICE_QUALIFIERS: &[&str] = &[
+    "__host__",
+    "__device__",
+    "__global__",
+    "__shared__",
+    "__constant__",
+    "__managed__",
+    "__forceinline__",
+    "__noinline__",
+    "__launch_bounds__(...)",
+    "__kernel",
+    "__global",
+    "__local",
+    "__constant",
+    "__private",
+    "__generic",
+    "__read_only",
+    "__write_only",
+    "__read_write",
+];
+
+impl Options {
+    /// Checks if there are C sources to parse, given with `--c-srcs` or `--headers`.
+    pub fn has_sources(&self) -> bool {
+        !self.c_srcs.is_empty() || self.headers.is_some()
+    }
+
+    /// Returns the sources given with `--c-srcs` followed by the headers in the manifest.
+    pub(crate) fn sources(&self) -> Result<Vec<Source>, Box<dyn std::error::Error>> {
+        let mut sources = self
+            .c_srcs
+            .iter()
+            .map(|src| Source {
+                pattern: src.clone(),
+                clang_args: Vec::new(),
+                prefixes: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        if let Some(path) = &self.headers {
+            let manifest: Manifest = crate::json::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            // relative include directories are resolved against the working directory
+            let working_dir = format!("-working-directory={}", dir.display());
+            for header in manifest.headers {
+                let clang_args = std::iter::once(working_dir.clone())
+                    .chain(manifest.clang_args.iter().cloned())
+                    .chain(header.clang_args)
+                    .collect();
+                sources.push(Source {
+                    pattern: dir.join(header.path),
+                    clang_args,
+                    prefixes: header.prefixes,
+                });
+            }
+        }
+        Ok(sources)
+    }
+
+    fn clang_args(
+        &self,
+        config: Option<&DefineSet>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut args = Vec::new();
+        match &self.target {
+            Some(target) => args.push(format!("--target={}", target)),
+            None if self.msvc => args.push("--target=x86_64-pc-windows-msvc".into()),
+            None => {}
+        }
+        if self.msvc {
+            args.extend(
+                [
+                    "-fms-extensions",
+                    "-fms-compatibility",
+                    "-fms-compatibility-version=19.30",
+                ]
+                .map(String::from),
+            );
+        }
+        if let Some(sysroot) = &self.sysroot {
+            args.push(format!("--sysroot={}", sysroot.display()));
+        }
+        args.extend(self.machine_flags.iter().map(|f| format!("-m{}", f)));
+        let language: &[&str] = match self.language {
+            Some(Language::C) => &["-x", "c"],
+            Some(Language::Cuda) => &["-x", "cuda", "--cuda-host-only", "-nocudainc", "-nocudalib"],
+            Some(Language::Opencl) => &["-x", "cl", "-cl-std=CL3.0"],
+            None => &[],
+        };
+        args.extend(language.iter().map(|&arg| arg.to_owned()));
+        if self.ignore_device_qualifiers {
+            args.extend(DEVICE_QUALIFIERS.iter().map(|q| format!("-D{}=", q)));
+        }
+        if self.bare_metal {
+            args.extend(
+                [
+                    "-ffreestanding",
+                    "-nostdlibinc",
+                    "-ferror-limit=0",
+                    "-Wno-unknown-pragmas",
+                    "-Wno-ignored-pragmas",
+                    "-Wno-unknown-attributes",
+                    "-Wno-ignored-attributes",
+                ]
+                .map(String::from),
+            );
+        }
+        if self.nostdinc {
+            args.push("-nostdinc".into());
+        }
+        for dir in &self.system_include_dirs {
+            args.push("-isystem".into());
+            args.push(dir.to_string_lossy().into_owned());
+        }
+        // -nostdlibinc also drops the standard directories in the sysroot
+        if let (true, Some(sysroot)) = (self.bare_metal, &self.sysroot) {
+            args.push("-isystem".into());
+            args.push(sysroot.join("include").to_string_lossy().into_owned());
+        }
+        if !self.pkg_config.is_empty() {
+            args.extend(pkg_config_cflags(&self.pkg_config)?);
+        }
+        for command in &self.cflags_commands {
+            args.extend(command_cflags(command)?);
+        }
+        args.extend(self.clang_args.iter().cloned());
+        if let Some(config) = config {
+            args.extend(config.args.iter().cloned());
+        }
+        Ok(args)
+    }
+}
+
+/// Runs `pkg-config --cflags` for `libraries` and returns the flags it prints.
+fn pkg_config_cflags(libraries: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let program = std::env::var("PKG_CONFIG").unwrap_or_else(|_| "pkg-config".into());
+    let args = std::iter::once("--cflags").chain(libraries.iter().map(String::as_str));
+    // pkg-config escapes whitespace inside a flag with a backslash
+    Ok(split_flags(&command_output(&program, args)?, true))
+}
+
+/// Runs a --cflags-cmd command, split at whitespace, and returns the flags it prints.
+fn command_cflags(command: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut args = command.split_whitespace();
+    let program = args.next().ok_or("--cflags-cmd is empty")?;
+    // backslashes are left alone, since they separate Windows paths
+    Ok(split_flags(&command_output(program, args)?, false))
+}
+
+/// Runs `program` with `args` and returns what it writes to stdout.
+fn command_output<'a>(
+    program: &str,
+    args: impl IntoIterator<Item = &'a str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let args = args.into_iter().collect::<Vec<_>>();
+    let output = std::process::Command::new(program)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("couldn't run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Splits compiler flags at whitespace, except inside double quotes or, if `escapes` is set,
+/// after a backslash.
+fn split_flags(text: &str, escapes: bool) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut flag = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if escapes => flag.extend(chars.next()),
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !flag.is_empty() {
+                    flags.push(std::mem::take(&mut flag));
+                }
+            }
+            c => flag.push(c),
+        }
+    }
+    if !flag.is_empty() {
+        flags.push(flag);
+    }
+    flags
+}
+
+#[derive(Clone, Debug)]
+pub struct DefineSet {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl std::str::FromStr for DefineSet {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = s
+            .split_once(':')
+            .filter(|(name, _)| !name.is_empty())
+            .ok_or_else(|| format!("expected `name:-DFOO,-DBAR`, got `{}`", s))?;
+        Ok(Self {
+            name: name.to_owned(),
+            args: args
+                .split(',')
+                .filter(|a| !a.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+        })
+    }
+}
+
+/// Whether `path` exists and was modified after `than`.
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    matches!((modified(path), modified(than)), (Some(a), Some(b)) if a > b)
+}
+
+/// What `extract` is doing, reported to its progress callback.
+pub enum Event<'a> {
+    /// A C source is about to be parsed.
+    Parsing(&'a Path),
+    /// The docs of a symbol were found in a source, parsed with the define set `config` if there
+    /// are several. `merged` is set if they complete docs found in an earlier declaration.
+    Found {
+        symbol: &'a str,
+        path: &'a Path,
+        config: Option<&'a str>,
+        merged: bool,
+    },
+    /// A file named by `\example` or `\snippet` in the docs of a symbol wasn't found.
+    MissingExample { symbol: &'a str, file: &'a str },
+}
+
+/// Parses the C sources and adds the docs of their symbols to `db`, keeping any docs already in
+/// it. If `wanted` is given, only those symbols are looked up and sources that don't mention any
+/// of them are skipped. `progress` is called with every source before it is parsed and with the
+/// docs found, and stops the extraction with a `sync::Cancelled` error if it returns
+/// `ControlFlow::Break`. Returns the sources looked at and the headers they include, for
+/// dependency tracking.
+pub fn extract(
+    options: &Options,
+    wanted: Option<&HashSet<String>>,
+    db: &mut Database,
+    progress: &mut dyn FnMut(Event) -> ControlFlow<()>,
+) -> Result<BTreeSet<PathBuf>, Box<dyn std::error::Error>> {
+    let clang = load_clang(options)?;
+    let index = clang::Index::new(&clang, true, false);
+    let configs = if options.define_sets.is_empty() {
+        vec![None]
+    } else {
+        options.define_sets.iter().map(Some).collect::<Vec<_>>()
+    };
+    let mut temp_pchs = TempFiles(Vec::new());
+    let mut config_args = Vec::new();
+    for (i, config) in configs.iter().enumerate() {
+        let mut clang_args = options.clang_args(*config)?;
+        let pch = if let Some(prelude) = &options.prelude {
+            // a PCH is only valid for the defines it was built with, so each define set gets one
+            let pch = match (&options.pch, config) {
+                (Some(pch), Some(config)) if configs.len() > 1 => {
+                    let mut name = pch.file_stem().unwrap_or_default().to_owned();
+                    name.push(format!(".{}.", config.name));
+                    name.push(pch.extension().unwrap_or_else(|| "pch".as_ref()));
+                    pch.with_file_name(name)
+                }
+                (Some(pch), _) => pch.clone(),
+                _ => {
+                    let pch = std::env::temp_dir().join(format!(
+                        "rust-clang-doc-generator-{}-{}.pch",
+                        std::process::id(),
+                        i
+                    ));
+                    temp_pchs.0.push(pch.clone());
+                    pch
+                }
+            };
+            if options.pch.is_none() || !is_newer(&pch, prelude) {
+                let mut parser = index.parser(prelude);
+                parser
+                    .arguments(&clang_args)
+                    .incomplete(true)
+                    .skip_function_bodies(options.skip_function_bodies);
+                parser.parse()?.save(&pch)?;
+            }
+            Some(pch)
+        } else {
+            options.pch.clone()
+        };
+        if let Some(pch) = pch {
+            clang_args.push("-include-pch".into());
+            clang_args.push(pch.to_string_lossy().into_owned());
+        }
+        config_args.push(clang_args);
+    }
+    let mut found = HashMap::<String, Found>::new();
+    let mut contents = HashMap::new();
+    // tags of records and enums mapped to the name of their typedef
+    let mut tags = HashMap::new();
+    let mut cancelled = false;
+    let mut inputs = BTreeSet::new();
+    inputs.extend(options.headers.clone());
+    'srcs: for source in options.sources()? {
+        for path in glob::glob(source.pattern.to_string_lossy().as_ref())? {
+            let path = path?;
+            if !path.is_file() {
+                continue;
+            }
+            inputs.insert(path.clone());
+            if let Some(wanted) = wanted {
+                let mut missing = wanted
+                    .iter()
+                    .map(|name| db.resolve(name))
+                    .filter(|name| {
+                        // symbols found outside the most preferred header may be found again
+                        !db.symbols.contains_key(*name)
+                            || found.get(*name).is_some_and(|symbol| symbol.rank > 0)
+                    })
+                    .map(|name| name.split('.').next().unwrap_or(name))
+                    .peekable();
+                if missing.peek().is_none() {
+                    break 'srcs;
+                }
+                if !options.parse_all {
+                    // skip running clang on files that never mention any of the missing symbols
+                    let source = std::fs::read(&path)?;
+                    let source = String::from_utf8_lossy(&source);
+                    let idents = source
+                        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .collect::<HashSet<_>>();
+                    if !missing.any(|name| idents.contains(name)) {
+                        continue;
+                    }
+                }
+            }
+            if progress(Event::Parsing(&path)).is_break() {
+                cancelled = true;
+                break 'srcs;
+            }
+            for (config, clang_args) in configs.iter().zip(&config_args) {
+                let clang_args = [clang_args.as_slice(), &source.clang_args].concat();
+                let mut parser = index.parser(&path);
+                parser
+                    .arguments(&clang_args)
+                    .detailed_preprocessing_record(true)
+                    .include_attributed_types(true)
+                    .skip_function_bodies(options.skip_function_bodies);
+                let tu = parser.parse()?;
+                // compatibility names can be declared before or after the symbol they forward
+                // to, so collect all of them before looking for docs
+                for e in tu.get_entity().get_children() {
+                    if e.get_kind() == clang::EntityKind::InclusionDirective {
+                        inputs.extend(e.get_file().map(|file| file.get_path()));
+                    }
+                    if let (Some(name), Some(target)) = (entity_name(&e), alias_target(&e)) {
+                        db.aliases.entry(name).or_insert(target);
+                    }
+                    for (name, target) in enum_constant_aliases(&e) {
+                        db.aliases.entry(name).or_insert(target);
+                    }
+                    if let Some((tag, typedef)) = tag_typedef(&e) {
+                        db.aliases.entry(tag.clone()).or_insert(typedef.clone());
+                        tags.entry(tag).or_insert(typedef);
+                    }
+                }
+                // a type-generic macro forwards to a function, but its own docs come first
+                let wanted = wanted.map(|wanted| {
+                    wanted
+                        .iter()
+                        .flat_map(|name| [name.clone(), db.resolve(name).to_owned()])
+                        .collect::<HashSet<_>>()
+                });
+                let mut res = Ok(());
+                let mut functions = HashSet::new();
+                tu.get_entity().visit_children(|e, _| {
+                    if !options.include_system_headers && e.is_in_system_header() {
+                        return clang::EntityVisitResult::Continue;
+                    }
+                    if e.get_kind() == clang::EntityKind::FunctionDecl {
+                        functions.extend(entity_name(&e));
+                    }
+                    for (name, carrier) in documented_entities(e) {
+                        // the typedef, forward declaration and definition of an opaque type like
+                        // `typedef struct _Foo Foo` are all documented as `Foo`
+                        let name = match name.split_once('.') {
+                            Some((tag, member)) if tags.contains_key(tag) => {
+                                format!("{}.{}", tags[tag], member)
+                            }
+                            _ => tags.get(&name).cloned().unwrap_or(name),
+                        };
+                        if !source.wants(&name) {
+                            continue;
+                        }
+                        if let Some(wanted) = &wanted {
+                            if !wanted.contains(&name) {
+                                continue;
+                            }
+                        }
+                        let rank = header_rank(&carrier, &options.preferred_headers);
+                        let location = comment_location(&carrier, options);
+                        let comment = location.as_ref().map(|l| (l.file.clone(), l.line));
+                        let usr = symbol_usr(&carrier);
+                        // docs already in the database are kept, ones found earlier in this run
+                        // give way to those in a preferred header, or are completed by other
+                        // declarations of the same symbol, like a documented definition
+                        let merge = match (db.symbols.contains_key(&name), found.get(&name)) {
+                            (false, _) => false,
+                            (true, Some(earlier)) if rank < earlier.rank => false,
+                            (true, Some(earlier))
+                                if earlier.usr == usr
+                                    && comment
+                                        .as_ref()
+                                        .is_some_and(|c| !earlier.comments.contains(c)) =>
+                            {
+                                true
+                            }
+                            _ => continue,
+                        };
+                        let mut missing_examples = Vec::new();
+                        let doc = match parse_comment(
+                            &carrier,
+                            &index,
+                            options,
+                            &mut missing_examples,
+                            &mut contents,
+                        ) {
+                            Ok(Some(doc)) => doc,
+                            Ok(None) => continue,
+                            Err(e) => {
+                                res = Err(e);
+                                return clang::EntityVisitResult::Break;
+                            }
+                        };
+                        for file in &missing_examples {
+                            let event = Event::MissingExample {
+                                symbol: &name,
+                                file,
+                            };
+                            if progress(event).is_break() {
+                                cancelled = true;
+                                return clang::EntityVisitResult::Break;
+                            }
+                        }
+                        let event = Event::Found {
+                            symbol: &name,
+                            path: &path,
+                            config: config.map(|c| c.name.as_str()),
+                            merged: merge,
+                        };
+                        if progress(event).is_break() {
+                            cancelled = true;
+                            return clang::EntityVisitResult::Break;
+                        }
+                        if merge {
+                            found.get_mut(&name).unwrap().comments.extend(comment);
+                            db.symbols.get_mut(&name).unwrap().doc.merge(doc);
+                            continue;
+                        }
+                        let mut attributes = attributes(&carrier);
+                        if doc.flags && !attributes.contains(&Attribute::Flags) {
+                            attributes.push(Attribute::Flags);
+                        }
+                        let symbol = Symbol {
+                            doc,
+                            config: config.map(|c| c.name.clone()),
+                            attributes,
+                            location,
+                            kind: symbol_kind(&e, &name),
+                            signature: signature(if name.contains('.') { &carrier } else { &e }),
+                        };
+                        let comments = comment.into_iter().collect();
+                        found.insert(
+                            name.clone(),
+                            Found {
+                                rank,
+                                usr,
+                                comments,
+                            },
+                        );
+                        db.symbols.insert(name, symbol);
+                    }
+                    // nothing that can be documented is declared inside a function body
+                    if e.get_kind() == clang::EntityKind::FunctionDecl {
+                        clang::EntityVisitResult::Continue
+                    } else {
+                        clang::EntityVisitResult::Recurse
+                    }
+                });
+                res?;
+                if cancelled {
+                    break 'srcs;
+                }
+                for name in found.keys() {
+                    let symbol = db.symbols.get_mut(name).unwrap();
+                    let is_type =
+                        matches!(symbol.kind, Some(Kind::Struct | Kind::Union | Kind::Type));
+                    let counted = symbol
+                        .attributes
+                        .iter()
+                        .any(|a| matches!(a, Attribute::RefCounted { .. }));
+                    if is_type && !counted {
+                        symbol.attributes.extend(ref_functions(name, &functions));
+                    }
+                }
+            }
+        }
+    }
+    drop(temp_pchs);
+    if cancelled {
+        return Err(crate::sync::Cancelled.into());
+    }
+    Ok(inputs)
+}
+
+/// Returns the docs of every documented symbol in the C sources, HTML docs and man pages given
+/// in `options`, like the extract command does. The C sources are parsed unless there are only
+/// HTML docs or man pages. `progress` is called like in `extract`, also with the docs found in
+/// HTML docs and man pages, which can't be stopped. The C sources and headers read are returned
+/// too.
+pub fn extract_all(
+    options: &Options,
+    progress: &mut dyn FnMut(Event) -> ControlFlow<()>,
+) -> Result<(Database, BTreeSet<PathBuf>), Box<dyn std::error::Error>> {
+    let mut db = Database::default();
+    let mut inputs = BTreeSet::new();
+    if options.has_sources() || (options.html_docs.is_empty() && options.man_pages.is_empty()) {
+        inputs = extract(options, None, &mut db, progress)?;
+    }
+    let mut found = |symbol: &str, path: &Path| {
+        let _ = progress(Event::Found {
+            symbol,
+            path,
+            config: None,
+            merged: false,
+        });
+    };
+    html::extract(&options.html_docs, None, &mut db, &mut found)?;
+    man::extract(&options.man_pages, None, &mut db, &mut found)?;
+    Ok((db, inputs))
+}
+
+/// Files deleted when dropped, like the precompiled headers made for define sets, so that they are
+/// also cleaned up when parsing fails.
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            // the file may not have been written yet
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// A symbol whose docs were found by `extract`.
+struct Found {
+    /// Rank of the header it was found in, see `header_rank`.
+    rank: usize,
+    usr: Option<clang::Usr>,
+    /// File and line of each comment its docs were taken from.
+    comments: HashSet<(PathBuf, u32)>,
+}
+
+/// Where the doc comment of a C declaration is, or would go.
+pub struct Declaration {
+    pub file: PathBuf,
+    /// Byte offset of the start of the declaration.
+    pub start: usize,
+    /// Line of the start of the declaration.
+    pub line: u32,
+    /// Byte offset of the start of its existing comment.
+    pub comment: Option<usize>,
+}
+
+/// Finds the declarations of `names` in the C sources and the headers they include, keeping the
+/// first one found for each name unless a later one is in a preferred header. Define sets aren't
+/// used, every source is parsed once.
+pub fn declarations(
+    options: &Options,
+    names: &HashSet<String>,
+) -> Result<BTreeMap<String, Declaration>, Box<dyn std::error::Error>> {
+    let clang = load_clang(options)?;
+    let index = clang::Index::new(&clang, true, false);
+    let mut clang_args = options.clang_args(None)?;
+    if let (Some(pch), None) = (&options.pch, &options.prelude) {
+        clang_args.push("-include-pch".into());
+        clang_args.push(pch.to_string_lossy().into_owned());
+    }
+    let mut decls = BTreeMap::new();
+    let mut ranks = HashMap::new();
+    for source in options.sources()? {
+        for path in glob::glob(source.pattern.to_string_lossy().as_ref())? {
+            let path = path?;
+            if !path.is_file() {
+                continue;
+            }
+            let mut parser = index.parser(&path);
+            parser
+                .arguments(&[clang_args.as_slice(), &source.clang_args].concat())
+                .skip_function_bodies(options.skip_function_bodies);
+            let tu = parser.parse()?;
+            tu.get_entity().visit_children(|e, _| {
+                if !options.include_system_headers && e.is_in_system_header() {
+                    return clang::EntityVisitResult::Continue;
+                }
+                let constants =
+                    enum_constant_aliases(&e)
+                        .into_iter()
+                        .filter_map(|(alias, name)| {
+                            let decl = enum_decl(&e)?;
+                            let constant = decl
+                                .get_children()
+                                .into_iter()
+                                .find(|c| entity_name(c).as_deref() == Some(&name))?;
+                            Some((alias, constant))
+                        });
+                for (name, carrier) in documented_entities(e).into_iter().chain(constants) {
+                    let rank = header_rank(&carrier, &options.preferred_headers);
+                    if !names.contains(&name)
+                        || !source.wants(&name)
+                        || ranks.get(&name).is_some_and(|&found| rank >= found)
+                    {
+                        continue;
+                    }
+                    // a comment on the record of a typedef is kept there
+                    let commented = carrier.get_comment().is_some();
+                    let decl = if commented { carrier } else { e };
+                    let offset = |l: Option<clang::source::SourceLocation>| {
+                        let l = l?.get_file_location();
+                        Some((l.file?.get_path(), l.offset as usize, l.line))
+                    };
+                    let comment = decl.get_comment_range().map(|r| r.get_start());
+                    if let Some((file, start, line)) =
+                        offset(decl.get_range().map(|r| r.get_start()))
+                    {
+                        let comment = offset(comment)
+                            .map(|(_, offset, _)| offset)
+                            .or_else(|| detached_comment(&decl, options).map(|d| d.offset));
+                        ranks.insert(name.clone(), rank);
+                        decls.insert(
+                            name,
+                            Declaration {
+                                file,
+                                start,
+                                line,
+                                comment,
+                            },
+                        );
+                    }
+                }
+                if e.get_kind() == clang::EntityKind::FunctionDecl {
+                    clang::EntityVisitResult::Continue
+                } else {
+                    clang::EntityVisitResult::Recurse
+                }
+            });
+        }
+    }
+    Ok(decls)
+}
+
+/// Returns the position of the first of `preferred` that matches the file `e` is declared in, or
+/// the length of `preferred` if none do. A pattern matches a path if it matches any of its ends,
+/// so `gtk/gtk.h` matches `/usr/include/gtk-4.0/gtk/gtk.h`.
+fn header_rank(e: &clang::Entity, preferred: &[glob::Pattern]) -> usize {
+    let path = match e.get_location().and_then(|l| l.get_file_location().file) {
+        Some(file) if !preferred.is_empty() => file.get_path(),
+        _ => return preferred.len(),
+    };
+    let components = path.components().collect::<Vec<_>>();
+    preferred
+        .iter()
+        .position(|pattern| {
+            (0..components.len())
+                .any(|i| pattern.matches_path(&components[i..].iter().collect::<PathBuf>()))
+        })
+        .unwrap_or(preferred.len())
+}
+
+fn load_clang(options: &Options) -> Result<clang::Clang, String> {
+    #[cfg(feature = "runtime")]
+    if let Some(path) = &options.libclang_path {
+        std::env::set_var("LIBCLANG_PATH", path);
+    }
+    #[cfg(not(feature = "runtime"))]
+    let _ = options;
+    let clang = clang::Clang::new().map_err(|e| format!("failed to load libclang: {}", e))?;
+    check_clang_version(&clang::get_version())?;
+    Ok(clang)
+}
+
+/// Minimum libclang major version, matching the `clang_10_0` feature of the clang crate.
+const MIN_CLANG_VERSION: u32 = 10;
+
+fn check_clang_version(version: &str) -> Result<(), String> {
+    // formatted like "clang version 14.0.6" with an optional vendor prefix and suffix
+    let major = version
+        .split_once("version ")
+        .and_then(|(_, v)| v.split('.').next())
+        .and_then(|v| v.parse::<u32>().ok());
+    match major {
+        Some(major) if major < MIN_CLANG_VERSION => Err(format!(
+            "{} is too old, at least libclang {}.0 is required",
+            version, MIN_CLANG_VERSION
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the name of an entity, or `None` for anonymous records and enums.
+fn entity_name(e: &clang::Entity) -> Option<String> {
+    // depending on the libclang version, anonymous declarations are either unnamed or are
+    // spelled like "struct (unnamed at foo.h:3:9)"
+    e.get_name()
+        .filter(|n| !n.is_empty() && !n.contains(char::is_whitespace))
+}
+
+/// Returns the names an entity can be looked up by, paired with the entity holding the comment
+/// for that name.
+fn documented_entities(e: clang::Entity) -> Vec<(String, clang::Entity)> {
+    use clang::EntityKind;
+    let mut entities = Vec::new();
+    match e.get_kind() {
+        EntityKind::FunctionDecl | EntityKind::EnumDecl => {
+            if let Some(name) = entity_name(&e) {
+                entities.push((name, e));
+            }
+        }
+        EntityKind::EnumConstantDecl => {
+            if let Some(name) = entity_name(&e) {
+                // anonymous enums like `enum { FOO_MAX = 16 };` are a way to declare a single
+                // constant, and the comment of the enum is then about it
+                let carrier = match e.get_semantic_parent() {
+                    Some(parent)
+                        if e.get_comment().is_none()
+                            && entity_name(&parent).is_none()
+                            && parent
+                                .get_children()
+                                .iter()
+                                .filter(|c| c.get_kind() == EntityKind::EnumConstantDecl)
+                                .count()
+                                == 1 =>
+                    {
+                        parent
+                    }
+                    _ => e,
+                };
+                entities.push((name, carrier));
+            }
+        }
+        // type-generic macros are documented instead of the functions they dispatch to
+        EntityKind::MacroDefinition if generic_target(&e).is_some() => {
+            if let Some(name) = entity_name(&e) {
+                entities.push((name, e));
+            }
+        }
+        EntityKind::StructDecl | EntityKind::UnionDecl => {
+            if let Some(name) = entity_name(&e) {
+                push_fields(&mut entities, &name, e);
+                entities.push((name, e));
+            }
+        }
+        EntityKind::TypedefDecl => {
+            if let Some(name) = entity_name(&e) {
+                // `typedef struct { ... } Foo;` can have the comment on either the typedef or
+                // the record, and the members are only reachable by the typedef name
+                let target = typedef_target(&e);
+                if let Some((decl, true)) = target {
+                    push_fields(&mut entities, &name, decl);
+                }
+                // callbacks are often declared as `typedef FooFunc *Foo;` with the comment and
+                // the parameter docs on the function type
+                let mut carrier = e;
+                let mut next = target.map(|t| t.0);
+                while carrier.get_comment().is_none() {
+                    match next {
+                        Some(decl) => {
+                            carrier = decl;
+                            next = (decl.get_kind() == EntityKind::TypedefDecl)
+                                .then(|| typedef_target(&decl).map(|t| t.0))
+                                .flatten();
+                        }
+                        None => {
+                            carrier = e;
+                            break;
+                        }
+                    }
+                }
+                entities.push((name, carrier));
+            }
+        }
+        _ => {}
+    }
+    entities
+}
+
+/// Returns the enum declared by `e`, or named by it if it is a typedef.
+fn enum_decl<'tu>(e: &clang::Entity<'tu>) -> Option<clang::Entity<'tu>> {
+    use clang::EntityKind;
+    match e.get_kind() {
+        EntityKind::EnumDecl => Some(*e),
+        EntityKind::TypedefDecl => match typedef_target(e)? {
+            (decl, true) if decl.get_kind() == EntityKind::EnumDecl => Some(decl),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Guesses whether an enum is a set of bit flags from the values of its constants. Besides single
+/// bits, flags enums often have constants for no flags and for combinations of other flags.
+fn is_flags_enum(e: &clang::Entity) -> bool {
+    let mut values = e
+        .get_children()
+        .iter()
+        .filter_map(|c| c.get_enum_constant_value())
+        .map(|(_, value)| value)
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    values.dedup();
+    let bits = values.iter().filter(|v| v.is_power_of_two()).count();
+    let all_bits = values
+        .iter()
+        .filter(|v| v.is_power_of_two())
+        .fold(0, |acc, v| acc | v);
+    // 0, 1, 2, 3, 4 is more likely a plain enumeration than flags
+    let sequential = values.windows(2).all(|w| w[1] == w[0] + 1);
+    bits >= 3 && !sequential && values.iter().all(|v| v & !all_bits == 0)
+}
+
+/// Returns names like `FooFlags.BAR` for the constants of an enum, mapped to the full name of the
+/// constant like `FOO_FLAGS_BAR`. This is how the constants of a Rust type generated by
+/// `bitflags!` are looked up.
+fn enum_constant_aliases(e: &clang::Entity) -> Vec<(String, String)> {
+    let (name, decl) = match (entity_name(e), enum_decl(e)) {
+        (Some(name), Some(decl)) => (name, decl),
+        _ => return Vec::new(),
+    };
+    let constants = decl
+        .get_children()
+        .iter()
+        .filter(|c| c.get_kind() == clang::EntityKind::EnumConstantDecl)
+        .filter_map(entity_name)
+        .collect::<Vec<_>>();
+    let prefix = match constants.split_first() {
+        Some((first, rest)) => {
+            let mut len = first.len();
+            for constant in rest {
+                len = first
+                    .bytes()
+                    .zip(constant.bytes())
+                    .take(len)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            }
+            // only strip whole words
+            first[..len].rfind('_').map_or(0, |i| i + 1)
+        }
+        None => 0,
+    };
+    constants
+        .iter()
+        .filter(|c| c.len() > prefix)
+        .map(|c| (format!("{}.{}", name, &c[prefix..]), c.clone()))
+        .collect()
+}
+
+/// Returns the name a declaration forwards to, for compatibility shims like
+/// `#define old_name new_name` and `__attribute__((alias("new_name")))`. The docs of type-generic
+/// macros fall back to the function they dispatch to by default.
+fn alias_target(e: &clang::Entity) -> Option<String> {
+    use clang::{token::TokenKind, EntityKind};
+    match e.get_kind() {
+        EntityKind::MacroDefinition if !e.is_function_like_macro() => {
+            match e.get_range()?.tokenize().as_slice() {
+                [_, target] if target.get_kind() == TokenKind::Identifier => {
+                    Some(target.get_spelling())
+                }
+                _ => None,
+            }
+        }
+        EntityKind::MacroDefinition => generic_target(e),
+        EntityKind::FunctionDecl | EntityKind::VarDecl if e.has_attributes() => {
+            let tokens = e.get_range()?.tokenize();
+            tokens.windows(3).find_map(|t| {
+                (matches!(t[0].get_spelling().as_str(), "alias" | "__alias__")
+                    && t[1].get_spelling() == "("
+                    && t[2].get_kind() == TokenKind::Literal)
+                    .then(|| t[2].get_spelling().trim_matches('"').to_owned())
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns the function a type-generic macro like
+/// `#define cbrt(x) _Generic((x), float: cbrtf, default: cbrt)(x)` dispatches to for the
+/// `default` association, or for the first one if there is no default.
+fn generic_target(e: &clang::Entity) -> Option<String> {
+    let tokens = e.get_range()?.tokenize();
+    let spellings = tokens.iter().map(|t| t.get_spelling()).collect::<Vec<_>>();
+    let start = spellings.iter().position(|s| s == "_Generic")?;
+    // the tokens of the controlling expression and of each association
+    let mut associations = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for s in &spellings[start + 1..] {
+        match s.as_str() {
+            "(" | "[" | "{" => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    associations.push(std::mem::take(&mut current));
+                    break;
+                }
+            }
+            "," if depth == 1 => {
+                associations.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(s.as_str());
+    }
+    let targets = associations
+        .iter()
+        .skip(1)
+        .filter_map(|association| {
+            let colon = association.iter().position(|s| *s == ":")?;
+            match &association[colon + 1..] {
+                [name] if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                    Some((association[..colon] == ["default"], *name))
+                }
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    let (_, target) = targets
+        .iter()
+        .find(|(default, _)| *default)
+        .or_else(|| targets.first())?;
+    Some((*target).to_owned())
+}
+
+/// Parses the doc comment of an entity, if it has one.
+/// Files named by `\example` or `\snippet` that aren't found are added to `missing_examples`.
+/// `contents` keeps the sources read for annotations that expand to nothing, by path.
+fn parse_comment(
+    e: &clang::Entity,
+    index: &clang::Index,
+    options: &Options,
+    missing_examples: &mut Vec<String>,
+    contents: &mut HashMap<PathBuf, String>,
+) -> Result<Option<Doc>, roxmltree::Error> {
+    let mut raw = e.get_comment();
+    let detached = match raw {
+        Some(_) => None,
+        None => detached_comment(e, options),
+    };
+    if let Some(detached) = &detached {
+        raw = Some(detached.text.clone());
+    }
+    let args = e
+        .get_arguments()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|arg| arg.get_name())
+        .collect::<Vec<_>>();
+    let parsed = raw.as_deref().and_then(|raw| {
+        gtkdoc::parse(raw)
+            .or_else(|| headerdoc::parse(raw))
+            .or_else(|| structured::parse(raw, &args))
+    });
+    let xml = || match &detached {
+        Some(detached) => parse_detached(index, &detached.text),
+        None => e.get_parsed_comment().map(|comment| comment.as_xml()),
+    };
+    let mut doc = match parsed {
+        Some(doc) => doc,
+        None => match xml() {
+            Some(xml) => Doc::from_xml(&xml, raw.as_deref().unwrap_or_default())?,
+            // HeaderDoc documents constants and fields in the comment of their enum or struct
+            None => match headerdoc_member(e) {
+                Some(doc) => doc,
+                None => return Ok(None),
+            },
+        },
+    };
+    doc.find_languages();
+    if let Some(raw) = &raw {
+        doc.find_copies(raw);
+        doc.find_groups(raw);
+        missing_examples.extend(doc.find_examples(raw, &options.example_dirs));
+        doc.find_images(raw);
+        doc.find_sections(raw);
+    }
+    let comment_dir = e
+        .get_location()
+        .and_then(|l| l.get_file_location().file)
+        .and_then(|f| f.get_path().parent().map(ToOwned::to_owned));
+    let image_dirs = options.image_dirs.iter().cloned().chain(comment_dir);
+    doc.resolve_images(&image_dirs.collect::<Vec<_>>());
+    let explicit = raw
+        .map(|raw| doc::command_texts(&raw, "threadsafety"))
+        .unwrap_or_default();
+    doc.extract_thread_safety(&explicit);
+    // `_Nullable` and `_Nonnull` on the declaration fill in what the comment doesn't say
+    let nullability = |ty: Option<clang::Type>| match ty?.get_nullability()? {
+        clang::Nullability::NonNull => Some(false),
+        clang::Nullability::Nullable => Some(true),
+        clang::Nullability::Unspecified => None,
+    };
+    for arg in e.get_arguments().unwrap_or_default() {
+        let param = match arg.get_name().and_then(|name| doc.param_mut(&name)) {
+            Some(param) => param,
+            None => continue,
+        };
+        if let Some(nullable) = nullability(arg.get_type()) {
+            param.annotations.nullable.get_or_insert(nullable);
+        }
+        param.callback = arg.get_type().and_then(callback_typedef);
+    }
+    infer_array_lengths(&mut doc, &e.get_arguments().unwrap_or_default());
+    if e.get_kind() == clang::EntityKind::FunctionDecl {
+        if let Some(nullable) = nullability(e.get_result_type()) {
+            doc.returns.annotations.nullable.get_or_insert(nullable);
+        }
+    }
+    if options.msvc {
+        add_sal_annotations(&mut doc, e, contents);
+    }
+    Ok((!doc.is_empty()).then_some(doc))
+}
+
+/// Fills in what the comment doesn't say about the parameters and return value of a function
+/// from their SAL annotations, which Windows headers use. They usually expand to nothing, so
+/// they are read from the source, kept in `contents` by path so that each file is read once.
+fn add_sal_annotations(doc: &mut Doc, e: &clang::Entity, contents: &mut HashMap<PathBuf, String>) {
+    let location = match e.get_location() {
+        Some(location) => location.get_file_location(),
+        None => return,
+    };
+    let file = match location.file {
+        Some(file) => file,
+        None => return,
+    };
+    let source = match contents.entry(file.get_path()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => match file.get_contents() {
+            Some(source) => entry.insert(source),
+            None => return,
+        },
+    };
+    for arg in e.get_arguments().unwrap_or_default() {
+        let (name, location) = match (arg.get_name(), arg.get_location()) {
+            (Some(name), Some(location)) => (name, location.get_file_location()),
+            _ => continue,
+        };
+        // parameters declared by a macro aren't in this file
+        if location.file != Some(file) {
+            continue;
+        }
+        let param = match doc.param_mut(&name) {
+            Some(param) => param,
+            None => continue,
+        };
+        if let Some(text) = param_annotation_text(source, location.offset as usize, &name) {
+            let pointer = arg.get_type().is_some_and(is_pointer);
+            param.annotations.merge(sal_annotations(&text, pointer));
+        }
+    }
+    if e.get_kind() == clang::EntityKind::FunctionDecl {
+        if let Some(text) = return_annotation_text(source, location.offset as usize) {
+            let pointer = e.get_result_type().is_some_and(is_pointer);
+            doc.returns
+                .annotations
+                .merge(sal_annotations(text, pointer));
+        }
+    }
+}
+
+/// A comment above a declaration that libclang didn't attach to it, see `--comment-distance`.
+struct Detached {
+    /// The comment rewritten as a `/** */` doc comment.
+    text: String,
+    /// Byte offset of the start of the comment.
+    offset: usize,
+    line: u32,
+}
+
+/// Finds the block comment or run of line comments that ends at most `--comment-distance` blank
+/// lines above a declaration, if there is nothing else in between. libclang never attaches
+/// comments to macros, so theirs are always looked up like this.
+fn detached_comment(e: &clang::Entity, options: &Options) -> Option<Detached> {
+    let distance = match e.get_kind() {
+        clang::EntityKind::MacroDefinition => options.comment_distance.unwrap_or(0),
+        _ => options.comment_distance?,
+    };
+    let start = e.get_range()?.get_start().get_file_location();
+    let source = start.file?.get_contents()?;
+    find_detached(&source, start.offset as usize, distance)
+}
+
+/// Finds the comment for `detached_comment` in `source`, given the offset of the declaration.
+fn find_detached(source: &str, start: usize, distance: usize) -> Option<Detached> {
+    let decl_line = source.get(..start)?.rfind('\n').map_or(0, |i| i + 1);
+    // the range of a macro starts at its name
+    let prefix = source[decl_line..start].trim();
+    if !prefix.is_empty() && prefix.trim_start_matches('#').trim() != "define" {
+        return None;
+    }
+    // walk up the lines above the declaration, with `end` at the start of the last one seen
+    let mut end = decl_line;
+    let mut blank = 0;
+    let mut comment = None;
+    while end > 0 {
+        let line_start = source[..end - 1].rfind('\n').map_or(0, |i| i + 1);
+        let line = source[line_start..end - 1].trim();
+        if comment.is_none() && line.is_empty() {
+            blank += 1;
+            if blank > distance {
+                return None;
+            }
+        } else if comment.is_none() && line.ends_with("*/") {
+            let start = source[..end].rfind("/*")?;
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            // a comment after code on the same line belongs to that code
+            if !source[line_start..start].trim().is_empty() {
+                return None;
+            }
+            comment = Some(start);
+            break;
+        } else if line.starts_with("//") {
+            comment = Some(line_start + source[line_start..].find("//")?);
+        } else {
+            break;
+        }
+        end = line_start;
+    }
+    let offset = comment?;
+    let raw = &source[offset..source[..decl_line].trim_end().len()];
+    let lines = raw
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = match line.strip_prefix("//") {
+                Some(line) => line.trim_start_matches(['/', '!']),
+                None => line
+                    .strip_prefix("/*")
+                    .map_or(line, |l| l.trim_start_matches(['*', '!'])),
+            };
+            let line = line
+                .strip_suffix("*/")
+                .unwrap_or(line)
+                .trim_end_matches('*');
+            line.strip_prefix('*')
+                .map_or(line, |l| l.trim_start_matches('*'))
+                .trim_end()
+        })
+        .collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text = lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(Detached {
+        text: doxygen::comment(text.trim_matches('\n'), ""),
+        offset,
+        line: source[..offset].matches('\n').count() as u32 + 1,
+    })
+}
+
+/// Parses a detached comment like libclang parses the ones it attaches, by attaching it to a
+/// dummy declaration. Returns it in the format of `clang_FullComment_getAsXML`.
+fn parse_detached(index: &clang::Index, comment: &str) -> Option<String> {
+    let path = "detached-comment.c";
+    let source = format!("{}\nvoid detached(void);\n", comment);
+    let tu = index
+        .parser(path)
+        .unsaved(&[clang::Unsaved::new(path, source)])
+        .parse()
+        .ok()?;
+    let decl = tu
+        .get_entity()
+        .get_children()
+        .into_iter()
+        .find(|e| e.get_kind() == clang::EntityKind::FunctionDecl)?;
+    Some(decl.get_parsed_comment()?.as_xml())
+}
+
+/// Marks a pointer parameter followed by a length as an array with that many elements, for
+/// comments without gtk-doc's `(array length=...)`. The length has to be named like one, such as
+/// `len` or `n_items`, or be described as the length, size or number of elements of the pointer.
+fn infer_array_lengths(doc: &mut Doc, args: &[clang::Entity]) {
+    use clang::TypeKind;
+    for pair in args.windows(2) {
+        let (pointer, length) = match (pair[0].get_name(), pair[1].get_name()) {
+            (Some(pointer), Some(length)) => (pointer, length),
+            _ => continue,
+        };
+        let is_pointer = pair[0].get_type().is_some_and(|ty| {
+            let ty = ty.get_canonical_type();
+            ty.get_kind() == TypeKind::Pointer
+                && ty.get_pointee_type().is_some_and(|p| {
+                    !matches!(
+                        p.get_canonical_type().get_kind(),
+                        TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype
+                    )
+                })
+        });
+        let is_integer = pair[1]
+            .get_type()
+            .is_some_and(|ty| ty.get_canonical_type().is_integer());
+        if !is_pointer || !is_integer {
+            continue;
+        }
+        let named = matches!(length.as_str(), "len" | "length" | "size" | "count")
+            || [
+                format!("{}_len", pointer),
+                format!("{}_length", pointer),
+                format!("{}_size", pointer),
+                format!("{}_count", pointer),
+                format!("n_{}", pointer),
+                format!("num_{}", pointer),
+            ]
+            .contains(&length);
+        let described = doc
+            .params
+            .iter()
+            .find(|p| p.name == length)
+            .is_some_and(|p| {
+                let text = p
+                    .description
+                    .first()
+                    .map(|para| doc::plain_text(para).to_lowercase())
+                    .unwrap_or_default();
+                let mentions = text
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .any(|word| word == pointer.to_lowercase());
+                mentions
+                    && ["length", "size", "number of", "count"]
+                        .iter()
+                        .any(|word| text.contains(word))
+            });
+        if !named && !described {
+            continue;
+        }
+        if let Some(param) = doc.param_mut(&pointer) {
+            param.annotations.array_length.get_or_insert(length);
+        }
+    }
+}
+
+/// Returns the source text of a parameter without its `name`, which starts at `name_start`, from
+/// the `(` or `,` before it to the one after it, which has any SAL annotations of the parameter.
+fn param_annotation_text(source: &str, name_start: usize, name: &str) -> Option<String> {
+    let name_end = name_start + name.len();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in source.get(..name_start)?.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth > 0 => depth -= 1,
+            '(' | ',' | ';' | '{' | '}' => {
+                start = i + 1;
+                break;
+            }
+            _ => {}
+        }
+    }
+    let mut depth = 0;
+    let mut end = source.len();
+    for (i, c) in source.get(name_end..)?.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' | ',' | ';' | '{' | '}' => {
+                end = name_end + i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    Some(format!(
+        "{} {}",
+        &source[start..name_start],
+        &source[name_end..end]
+    ))
+}
+
+/// Returns the source text in front of the name of a function, which starts at `name_start`, back
+/// to the end of whatever is before the declaration, which has any SAL annotations of its return
+/// value.
+fn return_annotation_text(source: &str, name_start: usize) -> Option<&str> {
+    let before = source.get(..name_start)?;
+    // walk up the lines, with `start` at the start of the last one taken
+    let mut start = before.len();
+    while start > 0 {
+        let end = before[..start].strip_suffix('\n').map_or(start, str::len);
+        let line_start = before[..end].rfind('\n').map_or(0, |i| i + 1);
+        let line = &before[line_start..end];
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || trimmed.starts_with("//") || trimmed.ends_with("*/") {
+            break;
+        }
+        if let Some(i) = line.rfind([';', '{', '}']) {
+            start = line_start + i + 1;
+            break;
+        }
+        start = line_start;
+    }
+    Some(&before[start..])
+}
+
+/// Converts SAL annotations of a parameter, like `_In_`, `_Out_opt_`, `_Inout_updates_(count)`
+/// and the older `__in_ecount(count)`, or the `IN`, `OUT` and `OPTIONAL` markers of Windows
+/// headers, into its direction, whether it may be `NULL` and the parameter with its length.
+/// Annotations of return values like `_Ret_maybenull_` are converted the same way, without a
+/// direction. A `pointer` annotated without `opt` or `maybenull` must not be `NULL`.
+fn sal_annotations(text: &str, pointer: bool) -> Annotations {
+    let mut annotations = Annotations::default();
+    let mut rest = text;
+    let (mut input, mut output) = (false, false);
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        rest = after;
+        match word {
+            "IN" => input = true,
+            "OUT" => output = true,
+            "OPTIONAL" => annotations.nullable = Some(true),
+            _ if word.starts_with('_') => {
+                let lower = word.trim_matches('_').to_ascii_lowercase();
+                let parts = lower.split('_').collect::<Vec<_>>();
+                match parts[0] {
+                    "in" => input = true,
+                    "out" | "outptr" | "outref" => output = true,
+                    "inout" => (input, output) = (true, true),
+                    "ret" => {}
+                    _ => continue,
+                }
+                if parts.iter().any(|p| matches!(*p, "opt" | "maybenull")) {
+                    annotations.nullable = Some(true);
+                } else if pointer {
+                    annotations.nullable.get_or_insert(false);
+                }
+                // the size of `_In_reads_bytes_(size)` isn't a number of elements
+                let counted = parts
+                    .iter()
+                    .any(|p| matches!(*p, "reads" | "writes" | "updates" | "ecount"));
+                let bytes = parts.iter().any(|p| matches!(*p, "bytes" | "bcount"));
+                if let Some(inner) = after.trim_start().strip_prefix('(') {
+                    let length = inner.split(')').next().unwrap_or_default().trim();
+                    let identifier = !length.is_empty()
+                        && !length.starts_with(|c: char| c.is_ascii_digit())
+                        && length
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if counted && !bytes && identifier {
+                        annotations.array_length = Some(length.to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    annotations.direction = match (input, output) {
+        (true, true) => Some(Direction::InOut),
+        (true, false) => Some(Direction::In),
+        (false, true) => Some(Direction::Out),
+        (false, false) => None,
+    };
+    annotations
+}
+
+/// Checks if a type is a pointer, after resolving typedefs like `LPCSTR`.
+fn is_pointer(ty: clang::Type) -> bool {
+    ty.get_canonical_type().get_kind() == clang::TypeKind::Pointer
+}
+
+/// Returns the name of the callback typedef a parameter is declared with, like `GDestroyNotify`,
+/// or `FooFunc` for `FooFunc *func`.
+fn callback_typedef(ty: clang::Type) -> Option<String> {
+    let decl = ty
+        .get_declaration()
+        .or_else(|| ty.get_pointee_type()?.get_declaration())?;
+    let name = entity_name(&decl)?;
+    (decl.get_kind() == clang::EntityKind::TypedefDecl
+        && symbol_kind(&decl, &name) == Some(Kind::Callback))
+    .then_some(name)
+}
+
+/// Returns the docs of an enum constant or field given by a `@constant` or `@field` tag in the
+/// HeaderDoc comment of its enum or struct.
+fn headerdoc_member(e: &clang::Entity) -> Option<Doc> {
+    let comment = e.get_semantic_parent()?.get_comment()?;
+    headerdoc::member(&comment, &e.get_name()?)
+}
+
+fn symbol_kind(e: &clang::Entity, name: &str) -> Option<Kind> {
+    use clang::EntityKind;
+    if name.contains('.') {
+        return Some(Kind::Field);
+    }
+    Some(match e.get_kind() {
+        EntityKind::FunctionDecl | EntityKind::MacroDefinition => Kind::Function,
+        EntityKind::StructDecl => Kind::Struct,
+        EntityKind::UnionDecl => Kind::Union,
+        EntityKind::EnumDecl => Kind::Enum,
+        EntityKind::EnumConstantDecl => Kind::Constant,
+        EntityKind::TypedefDecl => match typedef_target(e) {
+            Some((decl, true)) => match decl.get_kind() {
+                EntityKind::StructDecl => Kind::Struct,
+                EntityKind::UnionDecl => Kind::Union,
+                EntityKind::EnumDecl => Kind::Enum,
+                _ => Kind::Type,
+            },
+            _ => {
+                let mut ty = e.get_typedef_underlying_type()?;
+                while let Some(pointee) = ty.get_pointee_type() {
+                    ty = pointee;
+                }
+                match ty.get_kind() {
+                    clang::TypeKind::FunctionPrototype | clang::TypeKind::FunctionNoPrototype => {
+                        Kind::Callback
+                    }
+                    _ => Kind::Type,
+                }
+            }
+        },
+        _ => return None,
+    })
+}
+
+/// Pretty-prints a declaration without its body, like `int foo(int x)`.
+fn signature(e: &clang::Entity) -> Option<String> {
+    use clang::EntityKind;
+    if !matches!(
+        e.get_kind(),
+        EntityKind::FunctionDecl
+            | EntityKind::TypedefDecl
+            | EntityKind::FieldDecl
+            | EntityKind::StructDecl
+            | EntityKind::UnionDecl
+            | EntityKind::EnumDecl
+            | EntityKind::EnumConstantDecl
+    ) {
+        return None;
+    }
+    let printer = e.get_pretty_printer();
+    printer.set_flag(clang::PrintingPolicyFlag::UseTerseOutput, true);
+    Some(printer.print()).filter(|s| !s.is_empty())
+}
+
+fn comment_location(e: &clang::Entity, options: &Options) -> Option<Location> {
+    let comment = match e.get_comment() {
+        Some(comment) => comment,
+        None => {
+            let detached = detached_comment(e, options)?;
+            return Some(Location {
+                file: e.get_location()?.get_file_location().file?.get_path(),
+                line: detached.line,
+                hash: Location::hash_comment(&detached.text),
+            });
+        }
+    };
+    let start = e
+        .get_comment_range()
+        .map(|r| r.get_start())
+        .or_else(|| e.get_location())?
+        .get_file_location();
+    Some(Location {
+        file: start.file?.get_path(),
+        line: start.line,
+        hash: Location::hash_comment(&comment),
+    })
+}
+
+/// Returns the functions that take and release a reference to a type, named like `gtk_widget_ref`
+/// and `gtk_widget_unref` for `GtkWidget` or `cairo_reference` and `cairo_destroy` for `cairo_t`.
+fn ref_functions(name: &str, functions: &HashSet<String>) -> Option<Attribute> {
+    let name = name.trim_start_matches('_');
+    let name = name.strip_suffix("_t").unwrap_or(name);
+    let prefix = if name.contains('_') {
+        name.to_ascii_lowercase()
+    } else {
+        // `GtkWidget` becomes `gtk_widget` and `GDBusProxy` becomes `gd_bus_proxy`
+        let chars = name.chars().collect::<Vec<_>>();
+        let mut prefix = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                let prev = chars[i - 1];
+                let next_lower = chars.get(i + 1).is_some_and(char::is_ascii_lowercase);
+                if !prev.is_ascii_uppercase() || next_lower {
+                    prefix.push('_');
+                }
+            }
+            prefix.push(c.to_ascii_lowercase());
+        }
+        prefix
+    };
+    [
+        ("ref", "unref"),
+        ("reference", "destroy"),
+        ("retain", "release"),
+        ("incref", "decref"),
+        ("acquire", "release"),
+    ]
+    .iter()
+    .map(|(take, release)| {
+        (
+            format!("{}_{}", prefix, take),
+            format!("{}_{}", prefix, release),
+        )
+    })
+    .find(|(take, release)| functions.contains(take) && functions.contains(release))
+    .map(|(take, release)| Attribute::RefCounted { take, release })
+}
+
+/// Returns the attributes of a declaration that are worth mentioning in its docs.
+fn attributes(e: &clang::Entity) -> Vec<Attribute> {
+    if let Some(decl) = enum_decl(e) {
+        return is_flags_enum(&decl)
+            .then_some(Attribute::Flags)
+            .into_iter()
+            .collect();
+    }
+    if e.get_kind() != clang::EntityKind::FunctionDecl || !e.has_attributes() {
+        return Vec::new();
+    }
+    // attributes are often hidden behind macros like `G_GNUC_MALLOC`, the pretty printer shows
+    // them expanded
+    let printer = e.get_pretty_printer();
+    printer.set_flag(clang::PrintingPolicyFlag::UseTerseOutput, true);
+    let decl = printer.print();
+    let params = e
+        .get_arguments()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| p.get_name())
+        .collect::<Vec<_>>();
+    let param = |index: &str| {
+        let index = index.trim().parse::<usize>().ok()?;
+        params.get(index.checked_sub(1)?).cloned().flatten()
+    };
+    let mut attrs = Vec::new();
+    for (name, args) in parse_attributes(&decl) {
+        let attr = match name.as_str() {
+            "warn_unused_result" | "nodiscard" => Attribute::WarnUnusedResult,
+            "noreturn" | "_Noreturn" => Attribute::NoReturn,
+            "malloc" => Attribute::Malloc,
+            "format" if !args.is_empty() => Attribute::Format {
+                archetype: args[0].trim_matches('_').to_owned(),
+                param: args.get(1).and_then(|i| param(i)),
+            },
+            "nonnull" => Attribute::NonNull(args.iter().filter_map(|i| param(i)).collect()),
+            _ => continue,
+        };
+        if !attrs.contains(&attr) {
+            attrs.push(attr);
+        }
+    }
+    attrs
+}
+
+/// Finds the attributes in a pretty-printed declaration, written as `__attribute__((...))`,
+/// `[[...]]` or `_Noreturn`. Returns their names without namespaces or surrounding underscores,
+/// and their arguments.
+fn parse_attributes(decl: &str) -> Vec<(String, Vec<String>)> {
+    let mut attrs = Vec::new();
+    if decl
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|w| w == "_Noreturn")
+    {
+        attrs.push(("_Noreturn".to_owned(), Vec::new()));
+    }
+    for (open, close) in [("__attribute__((", "))"), ("[[", "]]")] {
+        let mut rest = decl;
+        while let Some(start) = rest.find(open) {
+            rest = &rest[start + open.len()..];
+            let end = rest.find(close).unwrap_or(rest.len());
+            for spec in split_top_level(&rest[..end]) {
+                let (name, args) = match spec.split_once('(') {
+                    Some((name, args)) => (name, split_top_level(args.trim_end_matches(')'))),
+                    None => (spec, Vec::new()),
+                };
+                let name = name.rsplit("::").next().unwrap_or(name);
+                let name = name.trim().trim_matches('_');
+                if !name.is_empty() {
+                    attrs.push((
+                        name.to_owned(),
+                        args.into_iter().map(|a| a.trim().to_owned()).collect(),
+                    ));
+                }
+            }
+            rest = &rest[end..];
+        }
+    }
+    attrs
+}
+
+/// Splits on commas that aren't nested inside parentheses.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+/// Returns the tag and the name of a typedef of a record or enum with a different tag, like `_Foo`
+/// and `Foo` for `typedef struct _Foo Foo`.
+fn tag_typedef(e: &clang::Entity) -> Option<(String, String)> {
+    use clang::EntityKind;
+    if e.get_kind() != EntityKind::TypedefDecl {
+        return None;
+    }
+    let name = entity_name(e)?;
+    match typedef_target(e)? {
+        (decl, true)
+            if matches!(
+                decl.get_kind(),
+                EntityKind::StructDecl | EntityKind::UnionDecl | EntityKind::EnumDecl
+            ) =>
+        {
+            let tag = entity_name(&decl)?;
+            (tag != name).then_some((tag, name))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the USR of the symbol documented by `e`, which for a typedef of a record or enum is the
+/// one of the record or enum, so that docs of both can be merged.
+fn symbol_usr(e: &clang::Entity) -> Option<clang::Usr> {
+    match typedef_target(e) {
+        Some((decl, true)) if tag_typedef(e).is_some() => decl.get_usr(),
+        _ => e.get_usr(),
+    }
+}
+
+/// Follows a typedef to the declaration of the type it names, looking through pointers. The flag
+/// is `false` if any pointers were skipped.
+fn typedef_target<'tu>(e: &clang::Entity<'tu>) -> Option<(clang::Entity<'tu>, bool)> {
+    use clang::EntityKind;
+    let mut ty = e.get_typedef_underlying_type()?;
+    let mut direct = true;
+    while let Some(pointee) = ty.get_pointee_type() {
+        ty = pointee;
+        direct = false;
+    }
+    ty.get_declaration()
+        .filter(|d| {
+            matches!(
+                d.get_kind(),
+                EntityKind::StructDecl
+                    | EntityKind::UnionDecl
+                    | EntityKind::EnumDecl
+                    | EntityKind::TypedefDecl
+            )
+        })
+        .map(|d| (d, direct))
+}
+
+fn push_fields<'tu>(
+    entities: &mut Vec<(String, clang::Entity<'tu>)>,
+    parent: &str,
+    record: clang::Entity<'tu>,
+) {
+    for field in record.get_children() {
+        if field.get_kind() == clang::EntityKind::FieldDecl {
+            if let Some(name) = entity_name(&field) {
+                entities.push((format!("{}.{}", parent, name), field));
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Functions to call from C, declared in `rust_clang_doc_generator.h`. Strings are UTF-8 and
+//! NUL-terminated, and those returned are owned by the caller, who frees them with
+//! `rcdg_string_free`. Failures return `NULL`.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{db::Database, json, markdown};
+
+thread_local! {
+    /// The message of the last error of a function on this thread, for `rcdg_last_error`.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Keeps `error` for `rcdg_last_error` and returns `NULL`.
+fn fail<T>(error: impl std::fmt::Display) -> *mut T {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+/// Borrows a string argument, or returns `None` if it is `NULL` or not UTF-8.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or point to a NUL-terminated string that outlives the result.
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Hands a string over to the caller, or returns `NULL` if it contains a NUL byte.
+fn c_string(s: String) -> *mut c_char {
+    CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Returns the message of the last error of a function that returned `NULL` on this thread, or
+/// `NULL` if there was none. The message is owned by the library and valid until the next call.
+#[no_mangle]
+pub extern "C" fn rcdg_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Converts a C doc comment to Markdown like `comment_to_markdown`. `args` are the `n_args`
+/// names of the arguments of the symbol, if it is a function, and may be `NULL` if there are
+/// none. Returns `NULL` for comments in a format that needs libclang.
+///
+/// # Safety
+///
+/// `name` and `comment` must be NUL-terminated strings and `args` must be `NULL` or point to
+/// `n_args` of them.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_comment_to_markdown(
+    name: *const c_char,
+    comment: *const c_char,
+    args: *const *const c_char,
+    n_args: usize,
+) -> *mut c_char {
+    let (name, comment) = match (str_arg(name), str_arg(comment)) {
+        (Some(name), Some(comment)) => (name, comment),
+        _ => return ptr::null_mut(),
+    };
+    let args = if args.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(args, n_args)
+            .iter()
+            .filter_map(|&arg| str_arg(arg).map(ToOwned::to_owned))
+            .collect()
+    };
+    match crate::comment_to_markdown(name, comment, &args) {
+        Ok(Some(markdown)) => c_string(markdown),
+        Ok(None) => ptr::null_mut(),
+        Err(e) => fail(e),
+    }
+}
+
+/// Loads a database written by the extract command, for looking up symbols in with
+/// `rcdg_database_markdown`. Returns `NULL` if it can't be read.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_load(path: *const c_char) -> *mut Database {
+    let path = match str_arg(path) {
+        Some(path) => path,
+        None => return ptr::null_mut(),
+    };
+    match Database::load(path) {
+        Ok(db) => into_database(db),
+        Err(e) => fail(e),
+    }
+}
+
+/// Extracts the docs of every documented symbol like the extract command, which `args` are the
+/// `n_args` options of, like `--c-srcs=foo.h` or `--clang-arg=-DFOO`. Returns `NULL` if the
+/// options are invalid or a source can't be parsed.
+///
+/// # Safety
+///
+/// `args` must be `NULL` or point to `n_args` NUL-terminated strings.
+#[cfg(feature = "libclang")]
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_extract(
+    args: *const *const c_char,
+    n_args: usize,
+) -> *mut Database {
+    let mut argv = vec!["extract"];
+    if !args.is_null() {
+        for &arg in std::slice::from_raw_parts(args, n_args) {
+            match str_arg(arg) {
+                Some(arg) => argv.push(arg),
+                None => return fail("arguments must be UTF-8"),
+            }
+        }
+    }
+    let command = <crate::c::Options as clap::Args>::augment_args(clap::Command::new("extract"));
+    let options = command.try_get_matches_from(argv).and_then(|matches| {
+        <crate::c::Options as clap::FromArgMatches>::from_arg_matches(&matches)
+    });
+    let options = match options {
+        Ok(options) => options,
+        Err(e) => return fail(e),
+    };
+    // a panic can't unwind into C, for example when libclang can't be loaded
+    let db = std::panic::catch_unwind(|| {
+        crate::c::extract_all(&options, &mut |_| std::ops::ControlFlow::Continue(()))
+    });
+    match db {
+        Ok(Ok((db, _))) => into_database(db),
+        Ok(Err(e)) => fail(e),
+        Err(_) => fail("extraction panicked"),
+    }
+}
+
+/// Hands a database over to the caller, with copied docs filled in.
+fn into_database(mut db: Database) -> *mut Database {
+    db.expand_copies();
+    db.expand_callbacks();
+    Box::into_raw(Box::new(db))
+}
+
+/// Returns the names of the symbols in `db` as a JSON array, sorted by name.
+///
+/// # Safety
+///
+/// `db` must be a database returned by `rcdg_database_load` or `rcdg_database_extract`.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_symbols(db: *const Database) -> *mut c_char {
+    let db = match db.as_ref() {
+        Some(db) => db,
+        None => return ptr::null_mut(),
+    };
+    match json::to_string(&db.symbols.keys().collect::<Vec<_>>()) {
+        Ok(names) => c_string(names),
+        Err(e) => fail(e),
+    }
+}
+
+/// Looks up a symbol or alias in `db` and returns its structured docs, location, kind and
+/// signature as JSON, in the format of `extract --format json`, or `NULL` if it has none.
+///
+/// # Safety
+///
+/// `db` must be a database returned by `rcdg_database_load` or `rcdg_database_extract` and
+/// `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_json(
+    db: *const Database,
+    name: *const c_char,
+) -> *mut c_char {
+    let symbol = match (db.as_ref(), str_arg(name)) {
+        (Some(db), Some(name)) => db.get(name),
+        _ => None,
+    };
+    match symbol.map(json::to_string) {
+        Some(Ok(json)) => c_string(json),
+        Some(Err(e)) => fail(e),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Frees a database returned by `rcdg_database_load` or `rcdg_database_extract`.
+///
+/// # Safety
+///
+/// `db` must be `NULL` or a database returned by `rcdg_database_load` or `rcdg_database_extract`
+/// that isn't used again.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_free(db: *mut Database) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
+}
+
+/// Looks up a symbol or alias in `db` and returns its docs as the Markdown the sync command
+/// would write for it, or `NULL` if it has none.
+///
+/// # Safety
+///
+/// `db` must be a database returned by `rcdg_database_load` or `rcdg_database_extract` and
+/// `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_database_markdown(
+    db: *const Database,
+    name: *const c_char,
+) -> *mut c_char {
+    let (db, name) = match (db.as_ref(), str_arg(name)) {
+        (Some(db), Some(name)) => (db, name),
+        _ => return ptr::null_mut(),
+    };
+    let (symbol, options) = match (db.get(name), crate::default_options()) {
+        (Some(symbol), Ok(options)) => (symbol, options),
+        _ => return ptr::null_mut(),
+    };
+    c_string(markdown::render_markdown(
+        db.resolve(name),
+        symbol,
+        db,
+        &HashMap::new(),
+        None,
+        &options,
+    ))
+}
+
+/// Frees a string returned by one of these functions.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by one of these functions that isn't used again.
+#[no_mangle]
+pub unsafe extern "C" fn rcdg_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::doc::Doc;
+
+/// Written at the start of database files, followed by the format version.
+const MAGIC: &[u8; 8] = b"RCDGDOCS";
+const VERSION: u32 = 22;
+
+/// Docs extracted from C sources, keyed by symbol name.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Database {
+    pub symbols: BTreeMap<String, Symbol>,
+    /// Compatibility names mapped to the symbol they forward to.
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Symbol {
+    pub doc: Doc,
+    /// Name of the define set the docs were found with.
+    pub config: Option<String>,
+    /// Attributes of the declaration that affect how it is used.
+    pub attributes: Vec<Attribute>,
+    /// Where the comment was found.
+    pub location: Option<Location>,
+    pub kind: Option<Kind>,
+    /// The declaration as written in C, without any body.
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Kind {
+    Function,
+    Struct,
+    Union,
+    Enum,
+    /// A constant of an enum.
+    Constant,
+    /// A typedef of a function pointer.
+    Callback,
+    /// Any other typedef.
+    Type,
+    /// A member of a struct or union, named like `Struct.field`.
+    Field,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: u32,
+    /// Hash of the raw comment, to notice when it changes.
+    pub hash: u64,
+}
+
+impl Location {
+    pub fn hash_comment(comment: &str) -> u64 {
+        hash(comment.as_bytes())
+    }
+}
+
+/// Hashes data with 64-bit FNV-1a, which unlike the hashers of the standard library gives the
+/// same result with every Rust version.
+pub fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Attribute {
+    /// `warn_unused_result` or `nodiscard`.
+    WarnUnusedResult,
+    /// `noreturn` or `_Noreturn`.
+    NoReturn,
+    Malloc,
+    /// `format(archetype, string_index, first_to_check)`.
+    Format {
+        archetype: String,
+        /// Parameter holding the format string.
+        param: Option<String>,
+    },
+    /// `nonnull`, with the parameters it applies to. Empty for all pointer parameters.
+    NonNull(Vec<String>),
+    /// An enum whose constants are bit flags.
+    Flags,
+    /// A type with functions to take and release a reference, like `foo_ref` and `foo_unref`.
+    RefCounted {
+        take: String,
+        release: String,
+    },
+}
+
+impl Database {
+    /// Follows compatibility aliases starting at `name`, returning the last name in the chain.
+    pub fn resolve<'a>(&'a self, mut name: &'a str) -> &'a str {
+        // bounded in case of a cycle
+        for _ in 0..16 {
+            match self.aliases.get(name) {
+                Some(target) if !self.symbols.contains_key(name) => name = target,
+                _ => break,
+            }
+        }
+        name
+    }
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(self.resolve(name))
+    }
+    /// Returns the symbols referenced by `\copydoc` and similar commands in the docs of `names`
+    /// that aren't in the database.
+    pub fn missing_copies<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a String>,
+    ) -> HashSet<String> {
+        names
+            .into_iter()
+            .filter_map(|name| self.get(name))
+            .flat_map(|s| &s.doc.copies)
+            .filter(|c| self.get(&c.symbol).is_none())
+            .map(|c| c.symbol.clone())
+            .collect()
+    }
+    /// Returns the callback typedefs that parameters in the docs of `names` are declared with that
+    /// aren't in the database.
+    pub fn missing_callbacks<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a String>,
+    ) -> HashSet<String> {
+        names
+            .into_iter()
+            .filter_map(|name| self.get(name))
+            .flat_map(|s| &s.doc.params)
+            .filter_map(|p| p.callback.as_ref())
+            .filter(|callback| self.get(callback).is_none())
+            .cloned()
+            .collect()
+    }
+    /// Fills in the parameters of callbacks that parameters are declared with, so the docs of a
+    /// function say what a callback passed to it receives.
+    pub fn expand_callbacks(&mut self) {
+        let mut expanded = Vec::new();
+        for (name, symbol) in &self.symbols {
+            for (i, param) in symbol.doc.params.iter().enumerate() {
+                let callback = param.callback.as_deref().and_then(|c| self.get(c));
+                if let Some(callback) = callback.filter(|c| !c.doc.params.is_empty()) {
+                    expanded.push((name.clone(), i, callback.doc.params.clone()));
+                }
+            }
+        }
+        for (name, i, params) in expanded {
+            self.symbols.get_mut(&name).unwrap().doc.params[i].callback_params = params;
+        }
+    }
+    /// Replaces `\copydoc` and similar commands with the docs they refer to. Commands referring
+    /// to unknown symbols are dropped.
+    pub fn expand_copies(&mut self) {
+        loop {
+            // only copy from symbols that are already expanded, so chains of copies work and
+            // cycles are left alone
+            let ready = self
+                .symbols
+                .iter()
+                .filter(|(_, s)| !s.doc.copies.is_empty())
+                .filter(|(_, s)| {
+                    s.doc.copies.iter().all(|c| {
+                        self.get(&c.symbol)
+                            .is_none_or(|other| other.doc.copies.is_empty())
+                    })
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                break;
+            }
+            for name in ready {
+                let mut doc = self.symbols[&name].doc.clone();
+                // later copies first, so the positions of earlier ones stay valid
+                for copy in std::mem::take(&mut doc.copies).into_iter().rev() {
+                    if let Some(other) = self.get(&copy.symbol) {
+                        doc.copy_from(&other.doc, &copy);
+                    }
+                }
+                self.symbols.get_mut(&name).unwrap().doc = doc;
+            }
+        }
+        for symbol in self.symbols.values_mut() {
+            symbol.doc.copies.clear();
+        }
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let body = data
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| format!("{} is not a docs database", path.display()))?;
+        let (version, body) = body.split_at(4.min(body.len()));
+        if version != VERSION.to_le_bytes() {
+            return Err(format!(
+                "{} was written by an incompatible version of this program",
+                path.display()
+            )
+            .into());
+        }
+        Ok(bincode::deserialize(body)?)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut file, self)?;
+        file.flush()?;
+        Ok(())
+    }
+    /// Writes the database as JSON, with symbols and aliases sorted by name.
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut json = crate::json::to_string(self)?;
+        json.push('\n');
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1246 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Documentation of a C symbol, independent of the comment syntax it was written in and the
+/// format it is rendered to.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Doc {
+    /// Brief description followed by the detailed description.
+    pub description: Vec<Paragraph>,
+    pub params: Vec<Param>,
+    pub returns: Returns,
+    /// Short remarks that don't belong to a parameter, rendered as a list.
+    pub notes: Vec<Paragraph>,
+    /// Conditions that must hold when the function is called.
+    pub preconditions: Vec<Paragraph>,
+    /// Conditions that hold after the function returns.
+    pub postconditions: Vec<Paragraph>,
+    pub invariants: Vec<Paragraph>,
+    /// Whether the symbol can be used from multiple threads.
+    pub thread_safety: Vec<Paragraph>,
+    /// Set if the comment declares an enum to be a set of bit flags.
+    pub flags: bool,
+    /// Code examples, like the ones included with `\example` or `\snippet`.
+    pub examples: Vec<CodeBlock>,
+    /// Docs to take from other symbols, filled in by [`crate::db::Database::expand_copies`].
+    pub copies: Vec<DocCopy>,
+    /// Doxygen groups the symbol was put in with `\ingroup`.
+    pub groups: Vec<String>,
+    /// Version of the library the symbol was added in, as written after `\since` or `Since:`.
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CodeBlock {
+    /// Language name for the info string of the fenced block, like `c`.
+    pub language: String,
+    pub code: String,
+}
+
+/// A `\copydoc`, `\copybrief` or `\copydetails` command.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DocCopy {
+    pub symbol: String,
+    pub part: CopyPart,
+    /// Index of the description paragraph the copied description goes in front of.
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CopyPart {
+    All,
+    /// The first paragraph of the description.
+    Brief,
+    /// Everything but the first paragraph of the description.
+    Details,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Param {
+    pub name: String,
+    pub annotations: Annotations,
+    pub description: Vec<Paragraph>,
+    /// Name of the callback typedef that is the type of the parameter, if it is one.
+    pub callback: Option<String>,
+    /// Parameters of the callback, filled in from its docs by
+    /// [`crate::db::Database::expand_callbacks`].
+    pub callback_params: Vec<Param>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Returns {
+    pub annotations: Annotations,
+    pub description: Vec<Paragraph>,
+}
+
+/// Semantics of a parameter or return value that the C type alone doesn't express.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Annotations {
+    /// `Some(true)` if the value may be `NULL`, `Some(false)` if it must not be.
+    pub nullable: Option<bool>,
+    pub transfer: Option<Transfer>,
+    pub direction: Option<Direction>,
+    /// Name of the parameter holding the number of elements, if the value is an array.
+    pub array_length: Option<String>,
+}
+
+impl Annotations {
+    /// Fills in the annotations that aren't set here from `other`.
+    pub fn merge(&mut self, other: Annotations) {
+        self.nullable = self.nullable.or(other.nullable);
+        self.transfer = self.transfer.or(other.transfer);
+        self.direction = self.direction.or(other.direction);
+        self.array_length = self.array_length.take().or(other.array_length);
+    }
+}
+
+/// Who owns a value after it is passed to or returned from a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Transfer {
+    /// Ownership stays with the side that had it.
+    None,
+    /// Ownership of the container moves, but not of its elements.
+    Container,
+    /// Ownership of the value moves to the other side.
+    Full,
+    /// The value is a floating reference.
+    Floating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Direction {
+    In,
+    Out,
+    InOut,
+}
+
+pub type Paragraph = Vec<Inline>;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(String),
+    Strong(String),
+    /// Code naming another symbol, like `gtk_widget_show()` or `GtkWidget`.
+    Reference(String),
+    Link {
+        url: String,
+        text: String,
+    },
+    LineBreak,
+    /// A heading, which is always a paragraph of its own. `level` starts at 1 for `\section`.
+    Heading {
+        level: u8,
+        title: String,
+        id: Option<String>,
+    },
+    /// A target for links within the docs.
+    Anchor(String),
+    /// Marks the following text as written in a language, or in all languages if `None`.
+    Language(Option<String>),
+    /// A TeX formula, on its own line if `display` is set.
+    Math {
+        tex: String,
+        display: bool,
+    },
+    Image {
+        /// Location of the image as written in the comment.
+        src: String,
+        alt: String,
+        /// The image file, if it was found.
+        path: Option<PathBuf>,
+    },
+}
+
+impl Doc {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_empty()
+            && self.params.is_empty()
+            && self.returns.description.is_empty()
+            && self.notes.is_empty()
+            && self.preconditions.is_empty()
+            && self.postconditions.is_empty()
+            && self.invariants.is_empty()
+            && self.thread_safety.is_empty()
+            && self.examples.is_empty()
+            && self.copies.is_empty()
+    }
+    pub fn param_mut(&mut self, name: &str) -> Option<&mut Param> {
+        self.params.iter_mut().find(|p| p.name == name)
+    }
+    /// Returns every list of paragraphs in the docs.
+    fn paragraph_lists_mut(&mut self) -> impl Iterator<Item = &mut Vec<Paragraph>> {
+        [
+            &mut self.description,
+            &mut self.returns.description,
+            &mut self.notes,
+            &mut self.preconditions,
+            &mut self.postconditions,
+            &mut self.invariants,
+            &mut self.thread_safety,
+        ]
+        .into_iter()
+        .chain(self.params.iter_mut().map(|p| &mut p.description))
+    }
+    /// Replaces Doxygen `\~language` markers in the text with [`Inline::Language`]. libclang
+    /// doesn't know them and keeps them as text.
+    pub fn find_languages(&mut self) {
+        for paras in self.paragraph_lists_mut() {
+            for para in paras.iter_mut() {
+                if !para
+                    .iter()
+                    .any(|i| matches!(i, Inline::Text(t) if t.contains("\\~") || t.contains("@~")))
+                {
+                    continue;
+                }
+                let mut split = Vec::new();
+                for inline in std::mem::take(para) {
+                    let text = match inline {
+                        Inline::Text(text) => text,
+                        other => {
+                            split.push(other);
+                            continue;
+                        }
+                    };
+                    let mut rest = text.as_str();
+                    while let Some(start) = rest.find("\\~").or_else(|| rest.find("@~")) {
+                        split.push(Inline::Text(rest[..start].to_owned()));
+                        rest = &rest[start + 2..];
+                        let end = rest
+                            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                            .unwrap_or(rest.len());
+                        let language = Some(language_name(&rest[..end])).filter(|l| !l.is_empty());
+                        split.push(Inline::Language(language));
+                        rest = &rest[end..];
+                    }
+                    split.push(Inline::Text(rest.to_owned()));
+                }
+                split.retain(|i| !matches!(i, Inline::Text(t) if t.is_empty()));
+                *para = split;
+            }
+        }
+    }
+    /// Drops the text written for languages other than `language`, both if it is marked with
+    /// `\~language` and if it is a paragraph in a different script than paragraphs written in
+    /// `language`.
+    pub fn select_language(&mut self, language: &str) {
+        let language = language_name(language);
+        for paras in self.paragraph_lists_mut() {
+            // text before the first marker is for all languages
+            let mut current: Option<String> = None;
+            let mut unmarked = Vec::new();
+            for para in paras.iter_mut() {
+                let mut marked = current.is_some();
+                para.retain(|inline| match inline {
+                    Inline::Language(l) => {
+                        current.clone_from(l);
+                        marked = true;
+                        false
+                    }
+                    _ => current.as_ref().is_none_or(|l| *l == language),
+                });
+                unmarked.push(!marked);
+            }
+            // paragraphs duplicated in another language without any markers
+            let scripts = paras
+                .iter()
+                .map(|p| script_language(&plain_text(p)))
+                .collect::<Vec<_>>();
+            if scripts.contains(&Some(language.as_str())) {
+                let mut keep = scripts
+                    .iter()
+                    .zip(unmarked)
+                    .map(|(s, unmarked)| !unmarked || s.is_none_or(|s| s == language));
+                paras.retain(|_| keep.next().unwrap());
+            }
+            for para in paras.iter_mut() {
+                para.retain(|i| !matches!(i, Inline::Text(t) if t.trim().is_empty()));
+            }
+            paras.retain(|p| !p.is_empty());
+        }
+    }
+    /// Finds the copy commands in a raw Doxygen comment. libclang leaves the symbol names of
+    /// these commands in the text, so they are removed from the description.
+    pub fn find_copies(&mut self, comment: &str) {
+        for (command, part) in [
+            ("copydoc", CopyPart::All),
+            ("copybrief", CopyPart::Brief),
+            ("copydetails", CopyPart::Details),
+        ] {
+            for text in command_texts(comment, command) {
+                if let Some(symbol) = text.split_whitespace().next() {
+                    let position = self.remove_word(symbol);
+                    self.copies.push(DocCopy {
+                        symbol: symbol.trim_end_matches("()").to_owned(),
+                        part,
+                        position,
+                    });
+                }
+            }
+        }
+        self.copies.sort_by_key(|c| c.position);
+    }
+    /// Finds the groups of `\ingroup` commands in a raw Doxygen comment.
+    pub fn find_groups(&mut self, comment: &str) {
+        for text in command_texts(comment, "ingroup") {
+            for group in text.split_whitespace() {
+                self.remove_word(group);
+                self.groups.push(group.to_owned());
+            }
+        }
+    }
+    /// Includes the files of `\example` and `\snippet` commands in a raw Doxygen comment,
+    /// searching for them in `dirs`. Returns the files that couldn't be read.
+    pub fn find_examples(&mut self, comment: &str, dirs: &[PathBuf]) -> Vec<String> {
+        let mut missing = Vec::new();
+        for command in ["example", "snippet"] {
+            for text in command_texts(comment, command) {
+                let mut words = text.split_whitespace();
+                let (file, tag) = match (command, words.next(), words.next()) {
+                    ("example", Some(file), _) => (file, None),
+                    ("snippet", Some(file), Some(tag)) => (file, Some(tag)),
+                    _ => continue,
+                };
+                self.remove_word(file);
+                if let Some(tag) = tag {
+                    self.remove_word(tag);
+                }
+                let source = dirs
+                    .iter()
+                    .find_map(|dir| std::fs::read_to_string(dir.join(file)).ok());
+                let code = match (source, tag) {
+                    (Some(source), None) => Some(source),
+                    (Some(source), Some(tag)) => snippet(&source, tag),
+                    (None, _) => None,
+                };
+                match code {
+                    Some(code) => self.examples.push(CodeBlock {
+                        language: code_language(file).to_owned(),
+                        code: code.trim_end().to_owned(),
+                    }),
+                    None => missing.push(match tag {
+                        Some(tag) => format!("{} [{}]", file, tag),
+                        None => file.to_owned(),
+                    }),
+                }
+            }
+        }
+        missing
+    }
+    /// Finds the images of `\image` commands in a raw Doxygen comment. Only one image is kept
+    /// for files that are listed for several output formats.
+    pub fn find_images(&mut self, comment: &str) {
+        let mut seen = Vec::new();
+        for text in command_texts(comment, "image") {
+            let mut words = text.splitn(3, char::is_whitespace);
+            let (format, src) = match (words.next(), words.next()) {
+                (Some(format), Some(src)) => (format, src),
+                _ => continue,
+            };
+            let caption = words
+                .next()
+                .and_then(|rest| rest.trim_start().strip_prefix('"')?.split_once('"'))
+                .map(|(caption, _)| caption);
+            if let Some(caption) = caption {
+                self.remove_word(&format!("\"{}\"", caption));
+            }
+            let position = self.remove_word(src);
+            self.remove_word(format);
+            if seen.iter().any(|s| s == src) {
+                continue;
+            }
+            seen.push(src.to_owned());
+            let image = Inline::Image {
+                src: src.to_owned(),
+                alt: caption.unwrap_or_default().to_owned(),
+                path: None,
+            };
+            let position = position.min(self.description.len());
+            self.description.insert(position, vec![image]);
+        }
+    }
+    /// Looks up the files of images in `dirs`.
+    pub fn resolve_images(&mut self, dirs: &[PathBuf]) {
+        for inline in self.description.iter_mut().flatten() {
+            if let Inline::Image {
+                src,
+                path: path @ None,
+                ..
+            } = inline
+            {
+                *path = dirs.iter().map(|d| d.join(&*src)).find(|p| p.is_file());
+            }
+        }
+    }
+    /// Returns the images in the description.
+    pub fn images(&self) -> impl Iterator<Item = (&str, Option<&Path>)> {
+        self.description
+            .iter()
+            .flatten()
+            .filter_map(|inline| match inline {
+                Inline::Image { src, path, .. } => Some((src.as_str(), path.as_deref())),
+                _ => None,
+            })
+    }
+    /// Turns `\section`, `\subsection`, `\subsubsection` and `\anchor` commands in a raw Doxygen
+    /// comment into headings and anchors.
+    pub fn find_sections(&mut self, comment: &str) {
+        for (level, command) in [(1, "section"), (2, "subsection"), (3, "subsubsection")] {
+            for text in command_texts(comment, command) {
+                let line = text.lines().next().unwrap_or_default();
+                let (name, title) = match line.split_once(char::is_whitespace) {
+                    Some((name, title)) => (name, title.trim()),
+                    None => (line, line),
+                };
+                if name.is_empty() {
+                    continue;
+                }
+                let position = self
+                    .split_at_word(line)
+                    .or_else(|| self.split_at_word(name))
+                    .unwrap_or(self.description.len());
+                let heading = Inline::Heading {
+                    level,
+                    title: title.to_owned(),
+                    id: Some(name.to_owned()),
+                };
+                self.description.insert(position, vec![heading]);
+            }
+        }
+        for text in command_texts(comment, "anchor") {
+            if let Some(name) = text.split_whitespace().next() {
+                let position = self.split_at_word(name).unwrap_or(self.description.len());
+                let anchor = Inline::Anchor(name.to_owned());
+                match self.description.get_mut(position) {
+                    Some(para) => para.insert(0, anchor),
+                    None => self.description.push(vec![anchor]),
+                }
+            }
+        }
+    }
+    /// Removes the last occurrence of `word` from the description and splits the paragraph it
+    /// was found in there. Returns the index of the paragraph following it.
+    fn split_at_word(&mut self, word: &str) -> Option<usize> {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let (p, k, i) = self
+            .description
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(p, para)| {
+                para.iter()
+                    .enumerate()
+                    .rev()
+                    .find_map(|(k, inline)| match inline {
+                        Inline::Text(text) => text
+                            .rmatch_indices(word)
+                            .find(|(i, _)| {
+                                !text[..*i].ends_with(is_word_char)
+                                    && !text[i + word.len()..].starts_with(is_word_char)
+                            })
+                            .map(|(i, _)| (p, k, i)),
+                        _ => None,
+                    })
+            })?;
+        let mut before = std::mem::take(&mut self.description[p]);
+        let mut after = before.split_off(k + 1);
+        if let Some(Inline::Text(text)) = before.pop() {
+            after.insert(0, Inline::Text(text[i + word.len()..].to_owned()));
+            before.push(Inline::Text(text[..i].to_owned()));
+        }
+        let blank = |i: &Inline| matches!(i, Inline::Text(t) if t.trim().is_empty());
+        before.retain(|i| !blank(i));
+        after.retain(|i| !blank(i));
+        self.description.remove(p);
+        let mut position = p;
+        if !before.is_empty() {
+            self.description.insert(position, before);
+            position += 1;
+        }
+        if !after.is_empty() {
+            self.description.insert(position, after);
+        }
+        Some(position)
+    }
+    /// Removes the last occurrence of `word` from the text of the description, since the
+    /// commands usually come after any text of their own. Returns the index of the paragraph
+    /// following the removed word.
+    fn remove_word(&mut self, word: &str) -> usize {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        for (p, para) in self.description.iter_mut().enumerate().rev() {
+            for inline in para.iter_mut().rev() {
+                if let Inline::Text(text) = inline {
+                    let found = text.rmatch_indices(word).find(|(i, _)| {
+                        !text[..*i].ends_with(is_word_char)
+                            && !text[i + word.len()..].starts_with(is_word_char)
+                    });
+                    if let Some((i, _)) = found {
+                        text.replace_range(i..i + word.len(), "");
+                        para.retain(|i| !matches!(i, Inline::Text(t) if t.trim().is_empty()));
+                        if para.is_empty() {
+                            self.description.remove(p);
+                            return p;
+                        }
+                        return p + 1;
+                    }
+                }
+            }
+        }
+        self.description.len()
+    }
+    /// Fills in the parts of the docs of another symbol selected by a copy command. Sections
+    /// other than the description are only taken over if they are empty here.
+    pub fn copy_from(&mut self, other: &Doc, copy: &DocCopy) {
+        let part = copy.part;
+        let description = match part {
+            CopyPart::All => &other.description[..],
+            CopyPart::Brief => &other.description[..other.description.len().min(1)],
+            CopyPart::Details => other.description.get(1..).unwrap_or_default(),
+        };
+        let position = copy.position.min(self.description.len());
+        self.description
+            .splice(position..position, description.iter().cloned());
+        if part == CopyPart::Brief {
+            return;
+        }
+        for param in &other.params {
+            if !self.params.iter().any(|p| p.name == param.name) {
+                self.params.push(param.clone());
+            }
+        }
+        if self.returns.description.is_empty() {
+            self.returns = other.returns.clone();
+        }
+        for (ours, theirs) in [
+            (&mut self.preconditions, &other.preconditions),
+            (&mut self.postconditions, &other.postconditions),
+            (&mut self.invariants, &other.invariants),
+            (&mut self.thread_safety, &other.thread_safety),
+            (&mut self.notes, &other.notes),
+        ] {
+            if ours.is_empty() {
+                ours.clone_from(theirs);
+            }
+        }
+        if self.examples.is_empty() {
+            self.examples.clone_from(&other.examples);
+        }
+        self.flags |= other.flags;
+    }
+    /// Fills in what is missing here from the docs of another declaration of the same symbol,
+    /// like a definition documented in a `.c` file where the header only has a brief comment.
+    /// The other description is taken if it is longer and starts with this one.
+    pub fn merge(&mut self, other: Doc) {
+        if other.description.starts_with(&self.description) {
+            self.description = other.description;
+        }
+        for param in other.params {
+            match self.param_mut(&param.name) {
+                Some(ours) if ours.description.is_empty() => {
+                    ours.description = param.description;
+                    ours.annotations.merge(param.annotations);
+                }
+                Some(ours) => ours.annotations.merge(param.annotations),
+                None => self.params.push(param),
+            }
+        }
+        if self.returns.description.is_empty() {
+            self.returns.description = other.returns.description;
+        }
+        self.returns.annotations.merge(other.returns.annotations);
+        for (ours, theirs) in [
+            (&mut self.preconditions, other.preconditions),
+            (&mut self.postconditions, other.postconditions),
+            (&mut self.invariants, other.invariants),
+            (&mut self.thread_safety, other.thread_safety),
+            (&mut self.notes, other.notes),
+        ] {
+            if ours.is_empty() {
+                *ours = theirs;
+            }
+        }
+        if self.examples.is_empty() {
+            self.examples = other.examples;
+        }
+        if self.copies.is_empty() {
+            self.copies = other.copies;
+        }
+        for group in other.groups {
+            if !self.groups.contains(&group) {
+                self.groups.push(group);
+            }
+        }
+        self.flags |= other.flags;
+        self.since = self.since.take().or(other.since);
+    }
+    /// Moves sentences about thread safety out of the description. Sentences are recognized by
+    /// common phrases, or by being part of one of the `explicit` texts, like the ones found by
+    /// [`command_texts`].
+    pub fn extract_thread_safety(&mut self, explicit: &[String]) {
+        let explicit = explicit
+            .iter()
+            .map(|t| normalize(t))
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        let mut found = Vec::new();
+        for para in &mut self.description {
+            let (matching, rest) = sentences(para).into_iter().partition::<Vec<_>, _>(|s| {
+                let text = normalize(&plain_text(s));
+                THREAD_SAFETY_PHRASES.iter().any(|p| text.contains(p))
+                    || explicit
+                        .iter()
+                        .any(|t| !text.is_empty() && t.contains(&text))
+            });
+            if !matching.is_empty() {
+                found.extend(matching);
+                *para = join_sentences(rest);
+            }
+        }
+        self.description.retain(|p| !p.is_empty());
+        if !found.is_empty() {
+            self.thread_safety.push(join_sentences(found));
+        }
+    }
+    /// Converts a comment parsed by libclang, in the format of `clang_FullComment_getAsXML`.
+    /// The raw `comment` is needed for the parts libclang doesn't keep apart, like formulas.
+    pub fn from_xml(xml: &str, comment: &str) -> Result<Self, roxmltree::Error> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+        let mut doc = Self::default();
+        if let Some(abs) = root.children().find(|n| n.has_tag_name("Abstract")) {
+            doc.description.extend(paragraphs(abs));
+        }
+        let mut formulas = formulas(comment).into_iter();
+        let mut languages = code_languages(comment).into_iter();
+        // formulas are verbatim blocks to libclang, so inline ones split the paragraph around
+        // them in two
+        let mut continues = false;
+        for disc in root.children().filter(|n| n.has_tag_name("Discussion")) {
+            for node in disc.children() {
+                if node.has_tag_name("Verbatim") && node.attribute("kind") == Some("verbatim") {
+                    let text = compact(node.text().unwrap_or_default());
+                    let formula = formulas
+                        .clone()
+                        .position(|Formula(tex, _)| {
+                            !text.is_empty() && compact(&tex).contains(&text)
+                        })
+                        .and_then(|i| formulas.nth(i));
+                    match formula {
+                        Some(Formula(tex, false)) => match doc.description.last_mut() {
+                            Some(para) => {
+                                para.push(Inline::Math {
+                                    tex,
+                                    display: false,
+                                });
+                                continues = true;
+                            }
+                            None => doc.description.push(vec![Inline::Math {
+                                tex,
+                                display: false,
+                            }]),
+                        },
+                        Some(Formula(tex, true)) => {
+                            doc.description
+                                .push(vec![Inline::Math { tex, display: true }]);
+                            continues = false;
+                        }
+                        None => {}
+                    }
+                    continue;
+                }
+                if node.has_tag_name("Verbatim") && node.attribute("kind") == Some("code") {
+                    let text = node.text().unwrap_or_default();
+                    let lines = text.lines().collect::<Vec<_>>();
+                    let indent = lines
+                        .iter()
+                        .filter(|l| !l.trim().is_empty())
+                        .map(|l| l.len() - l.trim_start().len())
+                        .min()
+                        .unwrap_or(0);
+                    let code = lines
+                        .iter()
+                        .map(|l| l.get(indent..).unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let language = languages.next().flatten();
+                    doc.examples.push(CodeBlock {
+                        language: language
+                            .as_deref()
+                            .map_or("text", fence_language)
+                            .to_owned(),
+                        code: code.trim_matches('\n').to_owned(),
+                    });
+                    continue;
+                }
+                if !node.has_tag_name("Para") {
+                    continue;
+                }
+                let target = match node.attribute("kind") {
+                    Some("pre") => &mut doc.preconditions,
+                    Some("post") => &mut doc.postconditions,
+                    Some("invariant") => &mut doc.invariants,
+                    Some("since") => {
+                        let since = paragraph(node)
+                            .iter()
+                            .map(|p| plain_text(p))
+                            .collect::<String>();
+                        doc.since = Some(since.trim().to_owned());
+                        continue;
+                    }
+                    _ if continues => {
+                        continues = false;
+                        let mut paras = paragraph(node).into_iter();
+                        if let (Some(para), Some(first)) =
+                            (doc.description.last_mut(), paras.next())
+                        {
+                            para.extend(first);
+                        }
+                        doc.description.extend(paras);
+                        continue;
+                    }
+                    _ => &mut doc.description,
+                };
+                target.extend(paragraph(node));
+            }
+        }
+        if let Some(params) = root.children().find(|n| n.has_tag_name("Parameters")) {
+            for param in params.children().filter(|n| n.has_tag_name("Parameter")) {
+                if let Some(name) = param
+                    .children()
+                    .find(|n| n.has_tag_name("Name"))
+                    .and_then(|n| n.text())
+                {
+                    // clang reports `in` for every parameter unless the direction is spelled out
+                    let direction = param
+                        .children()
+                        .find(|n| {
+                            n.has_tag_name("Direction") && n.attribute("isExplicit") == Some("1")
+                        })
+                        .and_then(|n| match n.text()? {
+                            "in" => Some(Direction::In),
+                            "out" => Some(Direction::Out),
+                            "in,out" => Some(Direction::InOut),
+                            _ => None,
+                        });
+                    doc.params.push(Param {
+                        name: name.to_owned(),
+                        annotations: Annotations {
+                            direction,
+                            ..Default::default()
+                        },
+                        callback: None,
+                        callback_params: Vec::new(),
+                        description: param
+                            .children()
+                            .filter(|n| n.has_tag_name("Discussion"))
+                            .flat_map(paragraphs)
+                            .collect(),
+                    });
+                }
+            }
+        }
+        if let Some(returns) = root.children().find(|n| n.has_tag_name("ResultDiscussion")) {
+            doc.returns.description.extend(paragraphs(returns));
+        }
+        Ok(doc)
+    }
+}
+
+fn paragraphs<'n>(node: roxmltree::Node<'n, '_>) -> impl Iterator<Item = Paragraph> + 'n {
+    node.children()
+        .filter(|n| n.has_tag_name("Para"))
+        .flat_map(paragraph)
+}
+
+/// Converts a paragraph, along with the HTML tags in it. Returns several paragraphs if it is
+/// split by `<p>` tags, and none if it is empty.
+fn paragraph(para: roxmltree::Node) -> Vec<Paragraph> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Style {
+        Text,
+        Code,
+        Emphasis,
+        Strong,
+    }
+    let mut paras = vec![Vec::new()];
+    let mut style = Style::Text;
+    // target and text of an `<a>` tag that hasn't been closed yet
+    let mut link: Option<(String, String)> = None;
+    for c in para.children() {
+        let text = if c.is_text() {
+            c.text().unwrap_or_default().to_owned()
+        } else {
+            c.descendants()
+                .filter_map(|d| d.is_text().then(|| d.text()).flatten())
+                .collect::<String>()
+        };
+        let current = paras.last_mut().unwrap();
+        let tag_style = match c.tag_name().name() {
+            "monospaced" => Style::Code,
+            "emphasized" => Style::Emphasis,
+            "bold" => Style::Strong,
+            "rawHTML" => {
+                let (name, closing) = match html_tag(&text) {
+                    Some(tag) => tag,
+                    None => continue,
+                };
+                match (name.as_str(), closing) {
+                    ("b" | "strong", false) => style = Style::Strong,
+                    ("em" | "i", false) => style = Style::Emphasis,
+                    ("tt" | "code", false) => style = Style::Code,
+                    ("b" | "strong" | "em" | "i" | "tt" | "code", true) => style = Style::Text,
+                    ("br", _) => current.push(Inline::LineBreak),
+                    ("p", _) if !current.is_empty() => paras.push(Vec::new()),
+                    ("a", false) => {
+                        link = html_attribute(&text, "href").map(|url| (url, String::new()))
+                    }
+                    ("a", true) => {
+                        if let Some((url, text)) = link.take() {
+                            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                            current.push(Inline::Link { url, text });
+                        }
+                    }
+                    ("img", false) => current.push(Inline::Image {
+                        src: html_attribute(&text, "src").unwrap_or_default(),
+                        alt: html_attribute(&text, "alt").unwrap_or_default(),
+                        path: None,
+                    }),
+                    // anything else would be passed through as HTML by rustdoc
+                    _ => {}
+                }
+                continue;
+            }
+            _ => Style::Text,
+        };
+        if let Some((_, link_text)) = &mut link {
+            link_text.push_str(&text);
+            continue;
+        }
+        let style = if tag_style == Style::Text {
+            style
+        } else {
+            tag_style
+        };
+        if style == Style::Text {
+            push_inline(current, Inline::Text(text));
+            continue;
+        }
+        // spaces inside of emphasis would stop it from being recognized as such
+        if text.starts_with(char::is_whitespace) {
+            push_inline(current, Inline::Text(" ".into()));
+        }
+        let trimmed = text.trim().to_owned();
+        if !trimmed.is_empty() {
+            push_inline(
+                current,
+                match style {
+                    Style::Code => Inline::Code(trimmed),
+                    Style::Emphasis => Inline::Emphasis(trimmed),
+                    _ => Inline::Strong(trimmed),
+                },
+            );
+        }
+        if text.ends_with(char::is_whitespace) && text.trim_start() != "" {
+            push_inline(current, Inline::Text(" ".into()));
+        }
+    }
+    paras.retain(|p| !p.is_empty());
+    paras
+}
+
+/// Appends an inline to a paragraph, merging it with the last one if they have the same style.
+pub fn push_inline(para: &mut Paragraph, inline: Inline) {
+    match (para.last_mut(), inline) {
+        (Some(Inline::Text(a)), Inline::Text(b))
+        | (Some(Inline::Code(a)), Inline::Code(b))
+        | (Some(Inline::Emphasis(a)), Inline::Emphasis(b))
+        | (Some(Inline::Strong(a)), Inline::Strong(b)) => a.push_str(&b),
+        (_, inline) => para.push(inline),
+    }
+}
+
+/// Returns the lowercase name of an HTML tag like `<a href="...">` or `</a>`, and whether it is
+/// a closing tag.
+pub fn html_tag(tag: &str) -> Option<(String, bool)> {
+    let tag = tag.strip_prefix('<')?;
+    let (tag, closing) = match tag.strip_prefix('/') {
+        Some(tag) => (tag, true),
+        None => (tag, false),
+    };
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .next()?
+        .to_ascii_lowercase();
+    (!name.is_empty()).then_some((name, closing))
+}
+
+/// Replaces HTML character references like `&amp;` and `&#8212;` with the characters they stand
+/// for. Unknown references are kept as they are.
+pub fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].split_once(';').and_then(|(name, _)| {
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                "ndash" => '–',
+                "mdash" => '—',
+                "hellip" => '…',
+                "copy" => '©',
+                _ => {
+                    let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, name.len() + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Returns the value of an attribute of an HTML start tag like `<img src="foo.png">`.
+pub fn html_attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(i) = rest.find(name) {
+        let preceded_by_space = rest[..i].ends_with(char::is_whitespace);
+        rest = &rest[i + name.len()..];
+        let value = match rest.trim_start().strip_prefix('=') {
+            Some(value) if preceded_by_space => value.trim_start(),
+            _ => continue,
+        };
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next()?.to_owned(),
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()?
+                .trim_end_matches('/')
+                .to_owned(),
+        });
+    }
+    None
+}
+
+/// Lowercase phrases that mark a sentence as being about thread safety.
+const THREAD_SAFETY_PHRASES: &[&str] = &[
+    "thread-safe",
+    "thread safe",
+    "threadsafe",
+    "mt-safe",
+    "mt-unsafe",
+    "main thread",
+    "any thread",
+    "multiple threads",
+    "same thread",
+    "reentrant",
+];
+
+/// Returns the text following each `\name` or `@name` Doxygen command in a raw comment, up to
+/// the next blank line or command, without any `{options}`. libclang doesn't keep the names of
+/// commands it doesn't know.
+pub fn command_texts(comment: &str, name: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut current: Option<String> = None;
+    for line in comment.lines().map(strip_decoration) {
+        let command = line
+            .strip_prefix(['\\', '@'])
+            .and_then(|l| l.strip_prefix(name))
+            .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '{']));
+        if let Some(rest) = command {
+            // options like `\snippet{lineno}` aren't needed
+            let rest = match rest.strip_prefix('{') {
+                Some(options) => options.split_once('}').map_or("", |(_, rest)| rest),
+                None => rest,
+            };
+            texts.extend(current.take());
+            current = Some(rest.trim().to_owned());
+        } else if line.is_empty() || line.starts_with(['\\', '@']) {
+            texts.extend(current.take());
+        } else if let Some(text) = &mut current {
+            text.push('\n');
+            text.push_str(line);
+        }
+    }
+    texts.extend(current);
+    texts
+}
+
+/// Removes the comment delimiters and leading `*` or `/` from a line of a raw comment.
+fn strip_decoration(line: &str) -> &str {
+    line.trim()
+        .trim_start_matches(['/', '*', '!'])
+        .trim_end_matches("*/")
+        .trim()
+}
+
+/// A TeX formula and whether it is a display formula.
+#[derive(Clone)]
+struct Formula(String, bool);
+
+/// Finds the formulas in a raw Doxygen comment, written as `\f$...\f$`, `\f[...\f]` or
+/// `\f{env}{...\f}`.
+fn formulas(comment: &str) -> Vec<Formula> {
+    let text = comment
+        .lines()
+        .map(strip_decoration)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut formulas = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find(['\\', '@']) {
+        rest = &rest[start + 1..];
+        let (end, env) = if let Some(r) = rest.strip_prefix("f$") {
+            rest = r;
+            ("f$", None)
+        } else if let Some(r) = rest.strip_prefix("f[") {
+            rest = r;
+            ("f]", None)
+        } else if let Some((env, r)) = rest
+            .strip_prefix("f{")
+            .and_then(|r| r.split_once('}'))
+            .and_then(|(env, r)| Some((env, r.strip_prefix('{')?)))
+        {
+            rest = r;
+            ("f}", Some(env))
+        } else {
+            continue;
+        };
+        let close = match rest
+            .find(&format!("\\{}", end))
+            .or_else(|| rest.find(&format!("@{}", end)))
+        {
+            Some(close) => close,
+            None => break,
+        };
+        let tex = rest[..close].trim();
+        let tex = match env {
+            Some(env) => format!("\\begin{{{}}}\n{}\n\\end{{{}}}", env, tex, env),
+            None => tex.to_owned(),
+        };
+        formulas.push(Formula(tex, end != "f$"));
+        rest = &rest[close + 1 + end.len()..];
+    }
+    formulas
+}
+
+/// Returns the full lowercase name of a language as used by Doxygen, like `japanese` for `ja`.
+fn language_name(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "en" => "english",
+        "ja" | "jp" => "japanese",
+        "zh" | "cn" => "chinese",
+        "ko" | "kr" => "korean",
+        "ru" => "russian",
+        "de" => "german",
+        "fr" => "french",
+        "es" => "spanish",
+        _ => return name,
+    }
+    .to_owned()
+}
+
+/// Guesses the language of a text from the script it is written in. Text in the Latin script is
+/// assumed to be English.
+fn script_language(text: &str) -> Option<&'static str> {
+    let (mut latin, mut kana, mut han, mut hangul, mut cyrillic) = (0, 0, 0, 0, 0);
+    for c in text.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' => latin += 1,
+            '\u{3040}'..='\u{30ff}' => kana += 1,
+            '\u{4e00}'..='\u{9fff}' => han += 1,
+            '\u{ac00}'..='\u{d7af}' => hangul += 1,
+            '\u{0400}'..='\u{04ff}' => cyrillic += 1,
+            _ => {}
+        }
+    }
+    // code and identifiers are mixed into text in any language, so any other script wins
+    if kana > 0 {
+        Some("japanese")
+    } else if hangul > 0 {
+        Some("korean")
+    } else if han > 0 {
+        Some("chinese")
+    } else if cyrillic > 0 {
+        Some("russian")
+    } else if latin > 0 {
+        Some("english")
+    } else {
+        None
+    }
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Returns the lines of `source` between the two lines marked with `[tag]`, like
+/// `//! [tag]`, without their common indentation.
+fn snippet(source: &str, tag: &str) -> Option<String> {
+    let marker = format!("[{}]", tag);
+    let mut lines = source.lines().skip_while(|l| !l.contains(&marker));
+    lines.next()?;
+    let lines = lines
+        .take_while(|l| !l.contains(&marker))
+        .collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    Some(
+        lines
+            .iter()
+            .map(|l| l.get(indent..).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+fn code_language(file: &str) -> &'static str {
+    fence_language(file.rsplit_once('.').map_or("", |(_, ext)| ext))
+}
+
+/// Returns the info string of a fenced code block in a language named like `.py`, `python` or
+/// `language-python`, as in Doxygen's `\code{.py}` and HTML classes. Unknown languages are
+/// `text`, so that rustdoc doesn't take the code for a Rust doctest.
+pub fn fence_language(name: &str) -> &'static str {
+    let name = name.trim().trim_start_matches('.').to_ascii_lowercase();
+    let name = name
+        .strip_prefix("language-")
+        .or_else(|| name.strip_prefix("lang-"))
+        .unwrap_or(&name);
+    match name {
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" => "cpp",
+        "rs" | "rust" => "rust",
+        "py" | "python" => "python",
+        "sh" | "bash" | "shell" | "console" => "sh",
+        "js" | "javascript" => "javascript",
+        "java" => "java",
+        "m" | "objc" | "objective-c" => "objc",
+        "lua" => "lua",
+        "xml" => "xml",
+        "html" | "htm" => "html",
+        "json" => "json",
+        "cmake" => "cmake",
+        "make" | "makefile" | "mk" => "make",
+        _ => "text",
+    }
+}
+
+/// Returns the languages given to the `\code` commands of a raw Doxygen comment, like `.py` for
+/// `\code{.py}`, in order.
+fn code_languages(comment: &str) -> Vec<Option<String>> {
+    comment
+        .lines()
+        .map(strip_decoration)
+        .filter_map(|line| {
+            line.strip_prefix(['\\', '@'])?
+                .strip_prefix("code")
+                .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '{']))
+        })
+        .map(|rest| {
+            let options = rest.strip_prefix('{')?.split_once('}')?.0;
+            Some(options.to_owned())
+        })
+        .collect()
+}
+
+pub fn plain_text(para: &[Inline]) -> String {
+    para.iter()
+        .map(|i| match i {
+            Inline::Text(t)
+            | Inline::Code(t)
+            | Inline::Emphasis(t)
+            | Inline::Strong(t)
+            | Inline::Reference(t) => t.as_str(),
+            Inline::Link { text, .. } => text.as_str(),
+            Inline::LineBreak | Inline::Anchor(_) | Inline::Language(_) => " ",
+            Inline::Heading { title, .. } => title.as_str(),
+            Inline::Math { tex, .. } => tex.as_str(),
+            Inline::Image { alt, .. } => alt.as_str(),
+        })
+        .collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Splits a paragraph after every `.`, `!` or `?` followed by whitespace in its text.
+fn sentences(para: &[Inline]) -> Vec<Paragraph> {
+    let mut sentences = Vec::new();
+    let mut current = Vec::new();
+    for inline in para {
+        let text = match inline {
+            Inline::Text(text) => text,
+            other => {
+                current.push(other.clone());
+                continue;
+            }
+        };
+        let mut start = 0;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let next_is_space = chars.peek().is_some_and(|(_, n)| n.is_whitespace());
+            if matches!(c, '.' | '!' | '?') && next_is_space {
+                let end = i + c.len_utf8();
+                current.push(Inline::Text(text[start..end].to_owned()));
+                sentences.push(std::mem::take(&mut current));
+                start = end;
+            }
+        }
+        if start < text.len() {
+            current.push(Inline::Text(text[start..].to_owned()));
+        }
+    }
+    if current
+        .iter()
+        .any(|i| !matches!(i, Inline::Text(t) if t.trim().is_empty()))
+    {
+        sentences.push(current);
+    }
+    sentences
+}
+
+fn join_sentences(sentences: Vec<Paragraph>) -> Paragraph {
+    let mut para = Vec::new();
+    for sentence in sentences {
+        if !para.is_empty() {
+            para.push(Inline::Text(" ".into()));
+        }
+        para.extend(sentence);
+    }
+    para
+}
@@ -0,0 +1,186 @@
+#[cfg(feature = "libclang")]
+use crate::c::Declaration;
+
+/// Converts the Markdown docs of a Rust item into the text of a Doxygen comment. The sections
+/// written for parameters and return values become `@param` and `@return` commands, links to
+/// Rust items become plain code spans, and the upstream link and provenance note are dropped.
+pub fn from_markdown(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut section = "";
+    let mut returned = false;
+    for line in markdown.lines() {
+        if let Some(title) = line.strip_prefix("# ") {
+            if matches!(title, "Parameters" | "Returns") {
+                section = title;
+                continue;
+            }
+            section = "";
+        }
+        let trimmed = line.trim();
+        if trimmed.starts_with("See the [upstream documentation]")
+            || trimmed.starts_with("*(from `")
+            || trimmed.starts_with("<!-- from ")
+        {
+            continue;
+        }
+        let line = strip_rust_links(line);
+        match section {
+            "Parameters" => match line.strip_prefix("* ") {
+                Some(item) => out.push(param(item)),
+                // continuation paragraphs of an item are indented below it
+                None => out.push(line.trim_start().to_owned()),
+            },
+            // only the first paragraph starts the command, the rest continue it
+            "Returns" if !trimmed.is_empty() && !returned => {
+                out.push(format!("@return {}", line));
+                returned = true;
+            }
+            _ => out.push(line),
+        }
+    }
+    // dropped headings leave blank lines behind
+    out.dedup_by(|a, b| a.is_empty() && b.is_empty());
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// Converts a parameter list item like ``* `name` *(out)* — text`` into a `@param` command.
+fn param(item: &str) -> String {
+    let (name, rest) = match item.strip_prefix('`').and_then(|i| i.split_once('`')) {
+        Some(split) => split,
+        None => return item.to_owned(),
+    };
+    let mut rest = rest.trim_start();
+    let mut direction = "";
+    for (marker, dir) in [
+        ("*(in)*", "[in]"),
+        ("*(out)*", "[out]"),
+        ("*(in, out)*", "[in,out]"),
+    ] {
+        if let Some(r) = rest.strip_prefix(marker) {
+            direction = dir;
+            rest = r.trim_start();
+        }
+    }
+    let rest = rest.strip_prefix('—').unwrap_or(rest).trim_start();
+    format!("@param{} {} {}", direction, name, rest)
+        .trim_end()
+        .to_owned()
+}
+
+/// Replaces links to Rust items, like ``[`Foo::new`](crate::Foo::new)``, with their text, since
+/// they mean nothing in C. Links to URLs are kept.
+fn strip_rust_links(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let text_end = rest[start..].find("](").map(|i| start + i);
+        let link_end = text_end.and_then(|i| rest[i..].find(')').map(|j| i + j));
+        match (text_end, link_end) {
+            (Some(text_end), Some(link_end))
+                if !rest[text_end + 2..link_end].contains("://")
+                    && !rest[start..text_end].contains(']') =>
+            {
+                out.push_str(&rest[..start]);
+                out.push_str(&rest[start + 1..text_end]);
+                rest = &rest[link_end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Wraps text into a `/** */` comment, with the lines after the first one indented by `indent`.
+pub fn comment(text: &str, indent: &str) -> String {
+    // `*/` inside the text would end the comment early
+    let text = text.replace("*/", "*&#47;");
+    let mut out = String::from("/**");
+    for line in text.lines() {
+        out.push('\n');
+        out.push_str(indent);
+        if line.is_empty() {
+            out.push_str(" *");
+        } else {
+            out.push_str(" * ");
+            out.push_str(line);
+        }
+    }
+    out.push('\n');
+    out.push_str(indent);
+    out.push_str(" */");
+    out
+}
+
+/// Writes comments for declarations in `source`, replacing their existing comments. Comments
+/// after a declaration, like `/**< ... */`, are left alone. Returns the new source.
+#[cfg(feature = "libclang")]
+pub fn write_comments(source: &str, comments: &[(&Declaration, String)]) -> String {
+    let mut replacements = Vec::new();
+    for (decl, text) in comments {
+        match decl.comment {
+            Some(start)
+                if source.get(start..).is_some_and(|s| {
+                    ["/**<", "///<", "/*!<", "//!<"]
+                        .iter()
+                        .any(|p| s.starts_with(p))
+                }) => {}
+            Some(start) if start <= source.len() => {
+                let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+                let indent = &source[line_start..start];
+                let indent = if indent.trim().is_empty() { indent } else { "" };
+                let end = comment_end(source, start);
+                replacements.push((start..end, comment(text, indent)));
+            }
+            _ if decl.start <= source.len() => {
+                let line_start = source[..decl.start].rfind('\n').map_or(0, |i| i + 1);
+                let line = source[line_start..].lines().next().unwrap_or_default();
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let text = format!("{}{}\n", indent, comment(text, indent));
+                replacements.push((line_start..line_start, text));
+            }
+            _ => {}
+        }
+    }
+    replacements.sort_by_key(|(range, _)| range.start);
+    replacements.dedup_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(source.len());
+    let mut last = 0;
+    for (range, text) in replacements {
+        // a comment overlapping the previous one was already replaced along with it
+        if range.start < last {
+            continue;
+        }
+        out.push_str(&source[last..range.start]);
+        out.push_str(&text);
+        last = range.end;
+    }
+    out.push_str(&source[last..]);
+    out
+}
+
+/// Finds the end of the comment starting at `start`, which is either a block comment or a run of
+/// line comments.
+#[cfg(feature = "libclang")]
+fn comment_end(source: &str, start: usize) -> usize {
+    let rest = &source[start..];
+    if rest.starts_with("/*") {
+        return rest.find("*/").map_or(source.len(), |i| start + i + 2);
+    }
+    let mut end = start;
+    let mut offset = start;
+    for line in rest.split_inclusive('\n') {
+        if !line.trim_start().starts_with("//") {
+            break;
+        }
+        end = offset + line.trim_end_matches('\n').len();
+        offset += line.len();
+    }
+    end
+}
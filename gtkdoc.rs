@@ -0,0 +1,262 @@
+use crate::doc::{Annotations, Direction, Doc, Inline, Paragraph, Param, Transfer};
+
+/// Parses a comment in gtk-doc format, like:
+///
+/// ```text
+/// /**
+///  * gtk_window_set_title:
+///  * @window: a #GtkWindow
+///  * @title: (nullable): title of the window
+///  *
+///  * Sets the title of the window.
+///  *
+///  * Returns: %TRUE on success
+///  */
+/// ```
+///
+/// Returns `None` if the comment doesn't start with a symbol name line.
+pub fn parse(comment: &str) -> Option<Doc> {
+    let lines = strip_comment(comment);
+    let mut lines = lines.iter().skip_while(|l| l.is_empty());
+    let first = lines.next()?;
+    let name = symbol_name(first)?;
+
+    enum Target {
+        Param(usize),
+        Description,
+        Returns,
+        ThreadSafety,
+        Deprecated,
+        Since,
+        Ignored,
+    }
+    let mut doc = Doc::default();
+    // annotations of the symbol itself follow the name, like `FooFlags: (flags)`
+    let symbol_annotations = first.trim()[name.len() + 1..]
+        .split(['(', ')'])
+        .map(str::trim)
+        .filter(|a| !a.is_empty());
+    for annotation in symbol_annotations {
+        if annotation == "flags" {
+            doc.flags = true;
+        }
+    }
+    let mut target = Target::Description;
+    let mut in_params = true;
+    let mut para = Vec::new();
+    let flush = |doc: &mut Doc, target: &Target, para: &mut Vec<&str>| {
+        if para.is_empty() {
+            return;
+        }
+        let text = para.join(" ");
+        para.clear();
+        if let Target::Since = target {
+            doc.since = Some(text);
+            return;
+        }
+        let inlines = inlines(&text);
+        match target {
+            Target::Param(i) => doc.params[*i].description.push(inlines),
+            Target::Description => doc.description.push(inlines),
+            Target::Returns => doc.returns.description.push(inlines),
+            Target::ThreadSafety => doc.thread_safety.push(inlines),
+            Target::Deprecated => {
+                let mut note = vec![Inline::Text("Deprecated: ".into())];
+                note.extend(inlines);
+                doc.notes.push(note);
+            }
+            Target::Since | Target::Ignored => {}
+        }
+    };
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            flush(&mut doc, &target, &mut para);
+            in_params = false;
+            target = Target::Description;
+            continue;
+        }
+        if in_params {
+            if let Some((name, rest)) = line.strip_prefix('@').and_then(|l| l.split_once(':')) {
+                flush(&mut doc, &target, &mut para);
+                let (annotations, rest) = split_annotations(rest);
+                let mut param = Param {
+                    name: name.trim().to_owned(),
+                    ..Default::default()
+                };
+                apply_annotations(&mut param.annotations, &annotations);
+                doc.params.push(param);
+                target = Target::Param(doc.params.len() - 1);
+                para.extend(Some(rest.trim()).filter(|r| !r.is_empty()));
+                continue;
+            }
+        }
+        if let Some((tag, rest)) = line.split_once(':') {
+            let tag_target = match tag.to_ascii_lowercase().as_str() {
+                "returns" | "return value" => Some(Target::Returns),
+                "thread safety" | "threadsafety" => Some(Target::ThreadSafety),
+                "deprecated" => Some(Target::Deprecated),
+                "since" => Some(Target::Since),
+                "stability" => Some(Target::Ignored),
+                _ => None,
+            };
+            if let Some(tag_target) = tag_target {
+                flush(&mut doc, &target, &mut para);
+                in_params = false;
+                target = tag_target;
+                let (annotations, rest) = split_annotations(rest);
+                if let Target::Returns = target {
+                    apply_annotations(&mut doc.returns.annotations, &annotations);
+                }
+                para.extend(Some(rest.trim()).filter(|r| !r.is_empty()));
+                continue;
+            }
+        }
+        if !matches!(target, Target::Param(_)) {
+            in_params = false;
+        }
+        para.push(line);
+    }
+    flush(&mut doc, &target, &mut para);
+    Some(doc)
+}
+
+/// Removes the comment delimiters and the leading `*` of each line.
+pub fn strip_comment(comment: &str) -> Vec<&str> {
+    comment
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line
+                .strip_prefix("/**")
+                .or_else(|| line.strip_prefix("/*!"))
+                .or_else(|| line.strip_prefix("/*"))
+                .unwrap_or(line);
+            let line = line.strip_suffix("*/").unwrap_or(line);
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.trim_end()
+        })
+        .collect()
+}
+
+/// Returns the symbol name from the first line of a gtk-doc comment, like `gtk_window_new:`,
+/// `GtkWindow::close-request:` or `GtkWindow:title: (attributes org.gtk.Property.get=...)`.
+fn symbol_name(line: &str) -> Option<&str> {
+    let mut line = line.trim();
+    while let Some(rest) = line.strip_suffix(')') {
+        line = rest[..rest.rfind('(')?].trim_end();
+    }
+    let name = line.strip_suffix(':')?;
+    let mut chars = name.chars();
+    (chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '-')))
+    .then_some(name)
+}
+
+/// Splits a list of annotations like `(transfer full) (nullable): rest` off the start of a
+/// parameter or tag description. Returns no annotations if the list isn't followed by a colon.
+pub fn split_annotations(s: &str) -> (Vec<String>, &str) {
+    let mut annotations = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(inner) = rest.strip_prefix('(') {
+        let end = match inner.find(')') {
+            Some(end) => end,
+            None => return (Vec::new(), s),
+        };
+        annotations.push(inner[..end].trim().to_owned());
+        rest = inner[end + 1..].trim_start();
+    }
+    match rest.strip_prefix(':') {
+        Some(rest) if !annotations.is_empty() => (annotations, rest),
+        _ => (Vec::new(), s),
+    }
+}
+
+pub fn apply_annotations(target: &mut Annotations, annotations: &[String]) {
+    for annotation in annotations {
+        let mut words = annotation.split_whitespace();
+        match words.next() {
+            Some("nullable" | "allow-none") => target.nullable = Some(true),
+            Some("not") if words.next() == Some("nullable") => target.nullable = Some(false),
+            Some("transfer") => {
+                target.transfer = match words.next() {
+                    Some("none") => Some(Transfer::None),
+                    Some("container") => Some(Transfer::Container),
+                    Some("full") => Some(Transfer::Full),
+                    Some("floating") => Some(Transfer::Floating),
+                    _ => target.transfer,
+                }
+            }
+            Some("in") => target.direction = Some(Direction::In),
+            Some("out") => target.direction = Some(Direction::Out),
+            Some("inout") => target.direction = Some(Direction::InOut),
+            Some("array") => {
+                if let Some(length) = words.find_map(|w| w.strip_prefix("length=")) {
+                    target.array_length = Some(length.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts gtk-doc inline markup, like `%NULL`, `#GtkWindow`, `@param` and `func()`, into
+/// code spans and references to other symbols.
+fn inlines(text: &str) -> Paragraph {
+    let text = &crate::doc::decode_entities(text);
+    let mut inlines = Vec::new();
+    let mut plain = String::new();
+    let mut chars = text.char_indices().peekable();
+    let mut prev = ' ';
+    while let Some((i, c)) = chars.next() {
+        let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let boundary = !is_ident(prev) && prev != '%' && prev != '#' && prev != '@';
+        let sigil = matches!(c, '%' | '#' | '@')
+            && chars
+                .peek()
+                .is_some_and(|(_, n)| n.is_ascii_alphabetic() || *n == '_');
+        if boundary && (sigil || c.is_ascii_alphabetic() || c == '_') {
+            let start = if sigil { i + 1 } else { i };
+            let mut end = start;
+            for (j, n) in text[start..].char_indices() {
+                // signals and properties are written like `#GtkWidget::destroy`
+                let extra = c == '#' && matches!(n, ':' | '-');
+                if !is_ident(n) && !extra {
+                    break;
+                }
+                end = start + j + n.len_utf8();
+            }
+            let mut word = text[start..end].trim_end_matches([':', '-']);
+            end = start + word.len();
+            let call = !sigil && text[end..].starts_with("()");
+            if call {
+                end += 2;
+                word = &text[start..end];
+            }
+            if sigil || call {
+                if !plain.is_empty() {
+                    inlines.push(Inline::Text(std::mem::take(&mut plain)));
+                }
+                // parameters aren't symbols that could be looked up anywhere
+                inlines.push(if c == '@' {
+                    Inline::Code(word.to_owned())
+                } else {
+                    Inline::Reference(word.to_owned())
+                });
+            } else {
+                plain.push_str(&text[i..end]);
+            }
+            while chars.peek().is_some_and(|(j, _)| *j < end) {
+                chars.next();
+            }
+            prev = text[..end].chars().next_back().unwrap_or(' ');
+            continue;
+        }
+        plain.push(c);
+        prev = c;
+    }
+    if !plain.is_empty() {
+        inlines.push(Inline::Text(plain));
+    }
+    inlines
+}
@@ -0,0 +1,527 @@
+use crate::{
+    doc::{self, CodeBlock, Doc, Inline, Paragraph, Param},
+    gtkdoc,
+};
+
+/// Tags that only HeaderDoc uses, which tell its comments apart from Doxygen ones.
+const HEADERDOC_TAGS: &[&str] = &[
+    "abstract",
+    "discussion",
+    "function",
+    "typedef",
+    "struct",
+    "enum",
+    "define",
+    "defined",
+    "constant",
+    "field",
+    "callback",
+    "functiongroup",
+    "availability",
+];
+
+/// Tags that name the symbol a comment is for, with the name as their first word.
+const NAME_TAGS: &[&str] = &[
+    "function",
+    "typedef",
+    "struct",
+    "union",
+    "enum",
+    "define",
+    "defined",
+    "var",
+    "class",
+    "method",
+    "property",
+    "callback",
+    "functiongroup",
+    "group",
+    "header",
+    "framework",
+];
+
+/// Tags with text that isn't part of the docs.
+const IGNORED_TAGS: &[&str] = &[
+    "availability",
+    "updated",
+    "version",
+    "author",
+    "copyright",
+    "see",
+    "seealso",
+    "since",
+    "indexgroup",
+    "namespace",
+    "charset",
+    "encoding",
+    "unsorted",
+    "attribute",
+    "attributeblock",
+    "attributelist",
+    "throws",
+    "exception",
+    "related",
+    "compilerflag",
+    "preprocinfo",
+    "ignore",
+];
+
+/// Parses a comment in Apple's HeaderDoc format, like:
+///
+/// ```text
+/// /*!
+///  @function CFStringCreateCopy
+///  @abstract Creates an immutable copy of a string.
+///  @param alloc The allocator to use.
+///  @param theString The string to copy.
+///  @result An immutable copy of @link theString @/link.
+///  */
+/// ```
+///
+/// Returns `None` if the comment doesn't use any tag that only HeaderDoc has, so that Doxygen
+/// comments starting with `/*!` are left to libclang.
+pub fn parse(comment: &str) -> Option<Doc> {
+    let blocks = blocks(comment)?;
+    let mut doc = Doc::default();
+    for block in blocks {
+        let tag = block.tag.to_ascii_lowercase();
+        let text = block.text.as_str();
+        match tag.as_str() {
+            "" | "abstract" | "brief" | "discussion" | "details" => {
+                doc.description.extend(paragraphs(text, &mut doc.examples));
+            }
+            "param" => {
+                let (name, rest) = first_word(text);
+                doc.params.push(Param {
+                    name: name.to_owned(),
+                    description: paragraphs(rest, &mut doc.examples),
+                    ..Default::default()
+                });
+            }
+            "result" | "return" | "returns" => {
+                let (annotations, rest) = gtkdoc::split_annotations(text);
+                gtkdoc::apply_annotations(&mut doc.returns.annotations, &annotations);
+                doc.returns
+                    .description
+                    .extend(paragraphs(rest, &mut doc.examples));
+            }
+            "deprecated" => {
+                let mut paras = paragraphs(text, &mut doc.examples).into_iter();
+                let mut note = vec![Inline::Text("Deprecated".into())];
+                match paras.next() {
+                    Some(first) => {
+                        note.push(Inline::Text(": ".into()));
+                        note.extend(first);
+                    }
+                    None => note.push(Inline::Text(".".into())),
+                }
+                doc.notes.push(note);
+                doc.notes.extend(paras);
+            }
+            "note" | "important" | "warning" | "attention" => {
+                doc.notes.extend(paragraphs(text, &mut doc.examples));
+            }
+            "since" => doc.since = Some(text.trim().to_owned()),
+            tag if NAME_TAGS.contains(&tag) => {
+                // anything after the name is discussion
+                let (_, rest) = first_word(text);
+                doc.description.extend(paragraphs(rest, &mut doc.examples));
+            }
+            // members are documented separately, see `member`
+            _ => {}
+        }
+    }
+    Some(doc)
+}
+
+/// Returns the docs of an enum constant or field from the `@constant` or `@field` tag for it
+/// in the HeaderDoc comment of the enum or struct, since it usually has no comment of its own.
+pub fn member(comment: &str, name: &str) -> Option<Doc> {
+    let block = blocks(comment)?.into_iter().find(|block| {
+        matches!(
+            block.tag.to_ascii_lowercase().as_str(),
+            "constant" | "const" | "field"
+        ) && first_word(&block.text).0 == name
+    })?;
+    let mut examples = Vec::new();
+    let description = paragraphs(first_word(&block.text).1, &mut examples);
+    let doc = Doc {
+        description,
+        examples,
+        ..Default::default()
+    };
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// A tag of a HeaderDoc comment and the text up to the next one.
+struct Block {
+    tag: String,
+    text: String,
+}
+
+/// Splits a HeaderDoc comment into its tags. Text before the first tag has an empty tag.
+fn blocks(comment: &str) -> Option<Vec<Block>> {
+    if !comment.trim_start().starts_with("/*!") {
+        return None;
+    }
+    let lines = gtkdoc::strip_comment(comment);
+    let mut blocks = vec![Block {
+        tag: String::new(),
+        text: String::new(),
+    }];
+    let mut headerdoc = false;
+    // lines of code blocks are kept as they are
+    let mut verbatim = false;
+    for line in lines {
+        let trimmed = line.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        if verbatim {
+            verbatim = !["@/textblock", "@/code", "</pre>"]
+                .iter()
+                .any(|end| lower.starts_with(end));
+        } else if ["@textblock", "@code", "<pre>"]
+            .iter()
+            .any(|start| lower.starts_with(start))
+        {
+            verbatim = true;
+        } else if let Some(rest) = trimmed.strip_prefix('@') {
+            let (tag, text) = first_word(rest);
+            let lower = tag.to_ascii_lowercase();
+            if HEADERDOC_TAGS.contains(&lower.as_str()) {
+                headerdoc = true;
+            }
+            let block_tag = NAME_TAGS.contains(&lower.as_str())
+                || IGNORED_TAGS.contains(&lower.as_str())
+                || matches!(
+                    lower.as_str(),
+                    "abstract"
+                        | "brief"
+                        | "discussion"
+                        | "details"
+                        | "param"
+                        | "result"
+                        | "return"
+                        | "returns"
+                        | "deprecated"
+                        | "note"
+                        | "important"
+                        | "warning"
+                        | "attention"
+                        | "constant"
+                        | "const"
+                        | "field"
+                );
+            if block_tag {
+                blocks.push(Block {
+                    tag: tag.to_owned(),
+                    text: text.to_owned(),
+                });
+                continue;
+            }
+        }
+        let block = blocks.last_mut().unwrap();
+        block.text.push('\n');
+        block.text.push_str(if verbatim { line } else { trimmed });
+    }
+    headerdoc.then_some(blocks)
+}
+
+/// Splits off the first word of a tag's text, like the name of a parameter.
+fn first_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace)
+        .map_or((text, ""), |(word, rest)| (word, rest))
+}
+
+/// Converts the text of a tag into paragraphs, moving code blocks to `examples`.
+fn paragraphs(text: &str, examples: &mut Vec<CodeBlock>) -> Vec<Paragraph> {
+    let mut paras = Vec::new();
+    let mut para = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+    let flush = |para: &mut Vec<&str>, paras: &mut Vec<Paragraph>| {
+        if !para.is_empty() {
+            paras.push(inlines(&para.join(" ")));
+            para.clear();
+        }
+    };
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        if let Some(lines) = &mut code {
+            if ["@/textblock", "@/code", "</pre>"]
+                .iter()
+                .any(|end| lower.starts_with(end))
+            {
+                let lines = code.take().unwrap();
+                let indent = lines
+                    .iter()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| l.len() - l.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                let code = lines
+                    .iter()
+                    .map(|l| l.get(indent..).unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                examples.push(CodeBlock {
+                    language: "c".into(),
+                    code: doc::decode_entities(code.trim_matches('\n')).into_owned(),
+                });
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if ["@textblock", "@code", "<pre>"]
+            .iter()
+            .any(|start| lower.starts_with(start))
+        {
+            flush(&mut para, &mut paras);
+            code = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut para, &mut paras);
+        } else {
+            para.push(trimmed);
+        }
+    }
+    flush(&mut para, &mut paras);
+    paras.retain(|p| !p.is_empty());
+    paras
+}
+
+/// Converts HeaderDoc inline markup, like `@link CFRelease @/link`, `{@link CFRelease}`,
+/// `@c NULL` and `<code>NULL</code>`, into references and code spans.
+fn inlines(text: &str) -> Paragraph {
+    let text = doc::decode_entities(text);
+    let mut para = Vec::new();
+    let mut words = text.split(' ').peekable();
+    let mut plain = String::new();
+    let push = |para: &mut Paragraph, plain: &mut String, inline: Inline| {
+        if !plain.is_empty() {
+            doc::push_inline(para, Inline::Text(std::mem::take(plain)));
+        }
+        doc::push_inline(para, inline);
+    };
+    while let Some(word) = words.next() {
+        let space = if words.peek().is_some() { " " } else { "" };
+        if word == "@link" || word == "{@link" {
+            // the text of the link, if any, is left out in favour of the target
+            let end = if word == "@link" { "@/link" } else { "}" };
+            let mut target = words.next().unwrap_or_default();
+            let mut trailing = "";
+            match target.find(end) {
+                Some(i) => (target, trailing) = (&target[..i], &target[i + end.len()..]),
+                None => {
+                    for w in words.by_ref() {
+                        if let Some(i) = w.find(end) {
+                            trailing = &w[i + end.len()..];
+                            break;
+                        }
+                    }
+                }
+            }
+            push(&mut para, &mut plain, Inline::Reference(target.to_owned()));
+            plain.push_str(trailing);
+            plain.push_str(if words.peek().is_some() { " " } else { "" });
+            continue;
+        }
+        if word == "@c" || word == "@p" {
+            if let Some(code) = words.next() {
+                let end = code
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(code.len());
+                push(&mut para, &mut plain, Inline::Code(code[..end].to_owned()));
+                plain.push_str(&code[end..]);
+                plain.push_str(if words.peek().is_some() { " " } else { "" });
+            }
+            continue;
+        }
+        plain.push_str(word);
+        plain.push_str(space);
+    }
+    if !plain.is_empty() {
+        doc::push_inline(&mut para, Inline::Text(plain));
+    }
+    html_styles(para)
+}
+
+/// Turns text between simple HTML tags like `<code>` and `<b>` into styled inlines.
+fn html_styles(para: Paragraph) -> Paragraph {
+    let mut out = Vec::new();
+    for inline in para {
+        let text = match inline {
+            Inline::Text(text) => text,
+            inline => {
+                out.push(inline);
+                continue;
+            }
+        };
+        let mut rest = text.as_str();
+        let mut style: Option<String> = None;
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end + 1,
+                None => break,
+            };
+            let (name, closing) = match doc::html_tag(&rest[start..end]) {
+                Some(tag) => tag,
+                None => {
+                    doc::push_inline(&mut out, Inline::Text(rest[..end].to_owned()));
+                    rest = &rest[end..];
+                    continue;
+                }
+            };
+            let before = &rest[..start];
+            let inline = match style.as_deref() {
+                Some("code" | "tt") => Inline::Code(before.to_owned()),
+                Some("b" | "strong") => Inline::Strong(before.to_owned()),
+                Some("i" | "em") => Inline::Emphasis(before.to_owned()),
+                _ => Inline::Text(before.to_owned()),
+            };
+            if !before.is_empty() {
+                doc::push_inline(&mut out, inline);
+            }
+            match (name.as_str(), closing) {
+                ("code" | "tt" | "b" | "strong" | "i" | "em", false) => style = Some(name),
+                (_, true) => style = None,
+                ("br", _) => out.push(Inline::LineBreak),
+                _ => {}
+            }
+            rest = &rest[end..];
+        }
+        if !rest.is_empty() {
+            doc::push_inline(&mut out, Inline::Text(rest.to_owned()));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    #[test]
+    fn function() {
+        let comment = r#"/*!
+ @function CFStringCreateCopy
+ @abstract Creates an immutable copy of a string.
+ @discussion The copy is <b>immutable</b>,
+ see {@link CFStringCreateMutableCopy}.
+
+ Release it with @link CFRelease @/link when done.
+ @param alloc The allocator to use, or @c NULL.
+ @param theString The string to copy.
+ @result (transfer full): An immutable copy of <code>theString</code>.
+ @since macOS 10.0
+ @availability Mac OS X v10.0 and later.
+ */"#;
+        let doc = parse(comment).unwrap();
+        assert_eq!(
+            doc.description,
+            [
+                vec![text("Creates an immutable copy of a string.")],
+                vec![
+                    text("The copy is "),
+                    Inline::Strong("immutable".into()),
+                    text(", see "),
+                    Inline::Reference("CFStringCreateMutableCopy".into()),
+                    text("."),
+                ],
+                vec![
+                    text("Release it with "),
+                    Inline::Reference("CFRelease".into()),
+                    text(" when done."),
+                ],
+            ]
+        );
+        let params = doc
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.description.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            params,
+            [
+                (
+                    "alloc",
+                    vec![vec![
+                        text("The allocator to use, or "),
+                        Inline::Code("NULL".into()),
+                        text("."),
+                    ]]
+                ),
+                ("theString", vec![vec![text("The string to copy.")]]),
+            ]
+        );
+        assert_eq!(
+            doc.returns.description,
+            [vec![
+                text("An immutable copy of "),
+                Inline::Code("theString".into()),
+                text("."),
+            ]]
+        );
+        assert_eq!(doc.returns.annotations.transfer, Some(doc::Transfer::Full));
+        assert_eq!(doc.since.as_deref(), Some("macOS 10.0"));
+    }
+
+    #[test]
+    fn code_blocks_and_notes() {
+        let comment = r#"/*!
+ * @typedef CFIndex
+ * An index.
+ * @textblock
+ *     CFIndex i = 0;
+ *       i++;
+ * @/textblock
+ * <pre>
+ * x &lt; y
+ * </pre>
+ * @deprecated Use NSInteger.
+ * @note Signed.
+ */"#;
+        let doc = parse(comment).unwrap();
+        assert_eq!(doc.description, [vec![text("An index.")]]);
+        let examples = doc
+            .examples
+            .iter()
+            .map(|e| e.code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(examples, ["CFIndex i = 0;\n  i++;", "x < y"]);
+        assert_eq!(
+            doc.notes,
+            [
+                vec![text("Deprecated"), text(": "), text("Use NSInteger.")],
+                vec![text("Signed.")],
+            ]
+        );
+    }
+
+    #[test]
+    fn members() {
+        let comment = r#"/*!
+ @enum CFComparisonResult
+ @abstract The result of a comparison.
+ @constant kCFCompareLessThan The first value is smaller.
+ @constant kCFCompareEqualTo The values are equal.
+ */"#;
+        let doc = parse(comment).unwrap();
+        assert_eq!(doc.description, [vec![text("The result of a comparison.")]]);
+        assert_eq!(
+            member(comment, "kCFCompareEqualTo").unwrap().description,
+            [vec![text("The values are equal.")]]
+        );
+        assert!(member(comment, "kCFCompareGreaterThan").is_none());
+    }
+
+    #[test]
+    fn doxygen_comments() {
+        assert!(parse("/*! @brief Doxygen.\n @param x A value. */").is_none());
+        assert!(parse("/** @function foo */").is_none());
+    }
+}
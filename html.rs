@@ -0,0 +1,1033 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    db::{Attribute, Database, Kind, Location, Symbol},
+    doc::{self, CodeBlock, Doc, Inline, Paragraph, Param},
+    gtkdoc,
+};
+
+/// A directory of HTML docs, and the URL they are published at if given as `dir=url`.
+#[derive(Clone, Debug)]
+pub struct HtmlDocs {
+    pub dir: PathBuf,
+    pub url: Option<String>,
+}
+
+impl std::str::FromStr for HtmlDocs {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (dir, url) = match s.split_once('=') {
+            Some((dir, url)) => (dir, Some(url.trim_end_matches('/').to_owned())),
+            None => (s, None),
+        };
+        if dir.is_empty() {
+            return Err(format!("expected `dir` or `dir=url`, got `{}`", s));
+        }
+        Ok(Self {
+            dir: dir.into(),
+            url,
+        })
+    }
+}
+
+/// Adds the docs of the symbols described by HTML pages generated by gtk-doc or Doxygen to `db`,
+/// keeping any docs already in it. If `wanted` is given, only those symbols are added. `found` is
+/// called with each symbol added and the page it was found in.
+pub fn extract(
+    sources: &[HtmlDocs],
+    wanted: Option<&HashSet<String>>,
+    db: &mut Database,
+    found: &mut dyn FnMut(&str, &Path),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wanted = wanted.map(|wanted| {
+        wanted
+            .iter()
+            .map(|name| db.resolve(name).to_owned())
+            .collect::<HashSet<_>>()
+    });
+    for source in sources {
+        let pattern = source.dir.join("**").join("*.html");
+        for path in glob::glob(pattern.to_string_lossy().as_ref())? {
+            let path = path?;
+            let html = std::fs::read(&path)?;
+            let html = String::from_utf8_lossy(&html);
+            let page = Page {
+                path: &path,
+                html: &html,
+                url: source.url.as_deref(),
+            };
+            for (name, symbol) in page.symbols() {
+                if db.symbols.contains_key(&name)
+                    || wanted.as_ref().is_some_and(|w| !w.contains(&name))
+                {
+                    continue;
+                }
+                found(&name, &path);
+                db.symbols.insert(name, symbol);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An HTML page being searched for docs.
+struct Page<'a> {
+    path: &'a Path,
+    html: &'a str,
+    /// URL the directory of the page is published at.
+    url: Option<&'a str>,
+}
+
+impl Page<'_> {
+    fn symbols(&self) -> Vec<(String, Symbol)> {
+        let root = parse(self.html);
+        let mut symbols = Vec::new();
+        self.gtkdoc_symbols(&root, &mut symbols);
+        self.doxygen_symbols(&root, &mut symbols);
+        symbols
+            .into_iter()
+            .filter(|(_, symbol)| !symbol.doc.is_empty())
+            .collect()
+    }
+    fn symbol(&self, element: &Element, doc: Doc, kind: Option<Kind>) -> Symbol {
+        let line = self.html[..element.start].matches('\n').count() + 1;
+        Symbol {
+            attributes: if doc.flags {
+                vec![Attribute::Flags]
+            } else {
+                Vec::new()
+            },
+            doc,
+            config: None,
+            location: Some(Location {
+                file: self.path.to_owned(),
+                line: line as u32,
+                hash: Location::hash_comment(&element.text()),
+            }),
+            kind,
+            signature: None,
+        }
+    }
+    /// Finds the symbols in a gtk-doc page, which describes each one in a `refsect2` section
+    /// headed by its name, like `gtk_window_new ()` or `struct GtkWindow`.
+    fn gtkdoc_symbols(&self, root: &Element, symbols: &mut Vec<(String, Symbol)>) {
+        for section in root.descendants().filter(|e| e.is("div", "refsect2")) {
+            let title = match section.elements().find(|e| e.name == "h3") {
+                Some(title) => normalize(&title.text()),
+                None => continue,
+            };
+            let (name, mut kind) = match title.strip_suffix("()") {
+                Some(name) => (name.trim_end(), Some(Kind::Function)),
+                None => match title.split_once(' ') {
+                    Some(("struct", name)) => (name, Some(Kind::Struct)),
+                    Some(("union", name)) => (name, Some(Kind::Union)),
+                    Some(("enum", name)) => (name, Some(Kind::Enum)),
+                    _ => (title.as_str(), None),
+                },
+            };
+            if !is_identifier(name) {
+                continue;
+            }
+            let mut doc = Doc::default();
+            let mut description = Vec::new();
+            let mut prototype = true;
+            let mut members = Vec::new();
+            for node in &section.children {
+                let element = match node {
+                    Node::Element(element) => element,
+                    Node::Text(_) => {
+                        description.push(node.clone());
+                        continue;
+                    }
+                };
+                match element.name.as_str() {
+                    "h3" => continue,
+                    "pre" if prototype => {
+                        prototype = false;
+                        if kind == Some(Kind::Function) && element.text().contains("(*") {
+                            kind = Some(Kind::Callback);
+                        }
+                        continue;
+                    }
+                    "p" if element.has_class("since") => continue,
+                    _ => {}
+                }
+                if element.is("div", "warning") {
+                    let mut notes = Vec::new();
+                    self.blocks(&element.children, &mut notes, &mut doc.examples);
+                    doc.notes.extend(notes);
+                    continue;
+                }
+                if !element.is("div", "refsect3") {
+                    description.push(node.clone());
+                    continue;
+                }
+                let heading = element
+                    .elements()
+                    .find(|e| e.name == "h4")
+                    .map(|h| normalize(&h.text()))
+                    .unwrap_or_default();
+                match heading.as_str() {
+                    "Parameters" => {
+                        for row in element.descendants().filter(|e| e.name == "tr") {
+                            let cell = |class| row.descendants().find(|e| e.is("td", class));
+                            let name = match cell("parameter_name") {
+                                Some(name) => normalize(&name.text()),
+                                None => continue,
+                            };
+                            let mut param = Param {
+                                name,
+                                ..Default::default()
+                            };
+                            if let Some(description) = cell("parameter_description") {
+                                self.blocks(
+                                    &description.children,
+                                    &mut param.description,
+                                    &mut doc.examples,
+                                );
+                            }
+                            if let Some(annotations) = cell("parameter_annotations") {
+                                gtkdoc::apply_annotations(
+                                    &mut param.annotations,
+                                    &annotations_of(annotations),
+                                );
+                            }
+                            doc.params.push(param);
+                        }
+                    }
+                    "Returns" => {
+                        for annotations in
+                            element.descendants().filter(|e| e.has_class("annotation"))
+                        {
+                            gtkdoc::apply_annotations(
+                                &mut doc.returns.annotations,
+                                &annotations_of(annotations),
+                            );
+                        }
+                        // annotations are left out of the text, so they leave no paragraph behind
+                        let rest = element
+                            .children
+                            .iter()
+                            .filter(|n| !matches!(n, Node::Element(e) if e.name == "h4"))
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        self.blocks(&rest, &mut doc.returns.description, &mut doc.examples);
+                    }
+                    "Members" | "Values" => {
+                        for row in element.descendants().filter(|e| e.name == "tr") {
+                            let cell = |suffix: &str| {
+                                row.descendants().find(|e| {
+                                    e.name == "td"
+                                        && e.attribute("class").is_some_and(|c| {
+                                            c.split_whitespace().any(|c| c.ends_with(suffix))
+                                        })
+                                })
+                            };
+                            if let (Some(member), Some(description)) =
+                                (cell("_member_name"), cell("_member_description"))
+                            {
+                                members.push((normalize(&member.text()), description));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.blocks(&description, &mut doc.description, &mut doc.examples);
+            for (member, description) in members {
+                let mut member_doc = Doc::default();
+                self.blocks(
+                    &description.children,
+                    &mut member_doc.description,
+                    &mut member_doc.examples,
+                );
+                let (member, kind) = match kind {
+                    Some(Kind::Enum) => (member, Some(Kind::Constant)),
+                    _ => (format!("{}.{}", name, member), Some(Kind::Field)),
+                };
+                symbols.push((member, self.symbol(description, member_doc, kind)));
+            }
+            symbols.push((name.to_owned(), self.symbol(section, doc, kind)));
+        }
+    }
+    /// Finds the symbols in a Doxygen page, which describes each one in a `memitem` box, and
+    /// structs on a page of their own with a `textblock` of details.
+    fn doxygen_symbols(&self, root: &Element, symbols: &mut Vec<(String, Symbol)>) {
+        let title = root
+            .descendants()
+            .find(|e| e.is("div", "title"))
+            .map(|t| normalize(&t.text()));
+        let record = title.as_deref().and_then(|title| {
+            let (name, kind) = title.rsplit_once(' ')?.0.rsplit_once(' ')?;
+            match kind {
+                "Struct" => Some((name, Kind::Struct)),
+                "Union" => Some((name, Kind::Union)),
+                _ => None,
+            }
+        });
+        if let Some((name, kind)) = record {
+            if let Some(text) = root.descendants().find(|e| e.is("div", "textblock")) {
+                let mut doc = Doc::default();
+                self.blocks(&text.children, &mut doc.description, &mut doc.examples);
+                symbols.push((name.to_owned(), self.symbol(text, doc, Some(kind))));
+            }
+        }
+        for item in root.descendants().filter(|e| e.is("div", "memitem")) {
+            let (proto, memdoc) = match (
+                item.descendants().find(|e| e.is("div", "memproto")),
+                item.descendants().find(|e| e.is("div", "memdoc")),
+            ) {
+                (Some(proto), Some(memdoc)) => (proto, memdoc),
+                _ => continue,
+            };
+            let memname = match proto.descendants().find(|e| e.is("td", "memname")) {
+                Some(memname) => normalize(&memname.text()),
+                None => continue,
+            };
+            let proto = normalize(&proto.text());
+            let (name, kind) = if let Some((_, rest)) = memname.split_once("(*") {
+                let name = rest
+                    .split(|c: char| !is_identifier_char(c))
+                    .find(|s| !s.is_empty());
+                (name.unwrap_or_default().to_owned(), Some(Kind::Callback))
+            } else {
+                let name = memname
+                    .rsplit(|c: char| !(is_identifier_char(c) || c == ':'))
+                    .find(|s| !s.is_empty())
+                    .unwrap_or_default();
+                let kind = match memname.split_whitespace().next() {
+                    Some("enum") => Some(Kind::Enum),
+                    Some("typedef") => Some(Kind::Type),
+                    Some("#define") => None,
+                    _ if proto.contains('(') => Some(Kind::Function),
+                    _ => None,
+                };
+                // fields are named after the struct as written, which may be its tag
+                match name.rsplit_once("::") {
+                    Some((tag, field)) => {
+                        let record = record.map_or(tag, |(record, _)| record);
+                        (format!("{}.{}", record, field), Some(Kind::Field))
+                    }
+                    None => (name.to_owned(), kind),
+                }
+            };
+            if !is_identifier(name.split('.').next_back().unwrap_or_default()) {
+                continue;
+            }
+            let mut doc = Doc::default();
+            let mut description = Vec::new();
+            for node in &memdoc.children {
+                let element = match node {
+                    Node::Element(element) => element,
+                    Node::Text(_) => {
+                        description.push(node.clone());
+                        continue;
+                    }
+                };
+                if element.is("table", "fieldtable") {
+                    for row in element.descendants().filter(|e| e.name == "tr") {
+                        let (field, description) = match (
+                            row.descendants().find(|e| e.is("td", "fieldname")),
+                            row.descendants().find(|e| e.is("td", "fielddoc")),
+                        ) {
+                            (Some(field), Some(description)) => (field, description),
+                            _ => continue,
+                        };
+                        let field = normalize(&field.text());
+                        let (field, kind) = match kind {
+                            Some(Kind::Enum) => (field, Some(Kind::Constant)),
+                            _ => (format!("{}.{}", name, field), Some(Kind::Field)),
+                        };
+                        let mut field_doc = Doc::default();
+                        self.blocks(
+                            &description.children,
+                            &mut field_doc.description,
+                            &mut field_doc.examples,
+                        );
+                        symbols.push((field, self.symbol(description, field_doc, kind)));
+                    }
+                    continue;
+                }
+                if element.name != "dl" {
+                    description.push(node.clone());
+                    continue;
+                }
+                let dd = element.elements().filter(|e| e.name == "dd");
+                let mut paras = Vec::new();
+                if element.has_class("params") {
+                    for row in element.descendants().filter(|e| e.name == "tr") {
+                        let cells = row
+                            .elements()
+                            .filter(|e| e.name == "td")
+                            .collect::<Vec<_>>();
+                        let name = match cells.iter().find(|c| c.has_class("paramname")) {
+                            Some(name) => normalize(&name.text()),
+                            None => continue,
+                        };
+                        let mut param = Param {
+                            name,
+                            ..Default::default()
+                        };
+                        param.annotations.direction = cells
+                            .iter()
+                            .find(|c| c.has_class("paramdir"))
+                            .and_then(|dir| match normalize(&dir.text()).as_str() {
+                                "[in]" => Some(doc::Direction::In),
+                                "[out]" => Some(doc::Direction::Out),
+                                "[in,out]" => Some(doc::Direction::InOut),
+                                _ => None,
+                            });
+                        if let Some(description) =
+                            cells.last().filter(|c| c.attribute("class").is_none())
+                        {
+                            self.blocks(
+                                &description.children,
+                                &mut param.description,
+                                &mut doc.examples,
+                            );
+                        }
+                        doc.params.push(param);
+                    }
+                    continue;
+                }
+                for dd in dd {
+                    self.blocks(&dd.children, &mut paras, &mut doc.examples);
+                }
+                let section = element
+                    .attribute("class")
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .find(|c| *c != "section")
+                    .unwrap_or_default()
+                    .to_owned();
+                match section.as_str() {
+                    "return" => doc.returns.description.extend(paras),
+                    "note" | "warning" | "attention" | "remark" => doc.notes.extend(paras),
+                    "pre" => doc.preconditions.extend(paras),
+                    "post" => doc.postconditions.extend(paras),
+                    "invariant" => doc.invariants.extend(paras),
+                    "deprecated" => {
+                        for (i, para) in paras.into_iter().enumerate() {
+                            let mut note = Vec::new();
+                            if i == 0 {
+                                note.push(Inline::Text("Deprecated: ".into()));
+                            }
+                            note.extend(para);
+                            doc.notes.push(note);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.blocks(&description, &mut doc.description, &mut doc.examples);
+            symbols.push((name, self.symbol(item, doc, kind)));
+        }
+    }
+    /// Converts HTML into paragraphs, putting code blocks into `examples` since paragraphs can't
+    /// hold them.
+    fn blocks(&self, nodes: &[Node], paras: &mut Vec<Paragraph>, examples: &mut Vec<CodeBlock>) {
+        let mut para = Vec::new();
+        for node in nodes {
+            let element = match node {
+                Node::Element(element) if is_block(&element.name) => element,
+                node => {
+                    self.inlines(node, &mut para);
+                    continue;
+                }
+            };
+            flush(&mut para, paras);
+            if element.name == "pre" || element.is("div", "fragment") {
+                examples.push(CodeBlock {
+                    language: code_language(element).into(),
+                    code: code_text(element).trim_end().to_owned(),
+                });
+                continue;
+            }
+            if let Some(level) = element
+                .name
+                .strip_prefix('h')
+                .and_then(|l| l.parse::<u8>().ok())
+            {
+                // `h1` is the title of the page, so `h2` is the first level within the docs
+                paras.push(vec![Inline::Heading {
+                    level: level.clamp(2, 6) - 1,
+                    title: normalize(&element.text()),
+                    id: element.attribute("id"),
+                }]);
+                continue;
+            }
+            self.blocks(&element.children, paras, examples);
+        }
+        flush(&mut para, paras);
+    }
+    fn inlines(&self, node: &Node, para: &mut Paragraph) {
+        let element = match node {
+            Node::Text(text) => {
+                // whitespace collapses into one space, also across elements
+                let mut collapsed = String::new();
+                let mut space = matches!(para.last(), Some(Inline::Text(t)) if t.ends_with(' '));
+                for c in text.chars() {
+                    if !c.is_whitespace() {
+                        collapsed.push(c);
+                    } else if !space {
+                        collapsed.push(' ');
+                    }
+                    space = c.is_whitespace();
+                }
+                if !collapsed.is_empty() {
+                    doc::push_inline(para, Inline::Text(collapsed));
+                }
+                return;
+            }
+            Node::Element(element) => element,
+        };
+        let text = normalize(&element.text());
+        let styled = |para: &mut Paragraph, inline: Inline| {
+            // spaces inside of emphasis would stop it from being recognized as such
+            if element.text().starts_with(char::is_whitespace) {
+                doc::push_inline(para, Inline::Text(" ".into()));
+            }
+            if !text.is_empty() {
+                doc::push_inline(para, inline);
+            }
+            if element.text().ends_with(char::is_whitespace) {
+                doc::push_inline(para, Inline::Text(" ".into()));
+            }
+        };
+        let class = element.attribute("class").unwrap_or_default();
+        match element.name.as_str() {
+            "br" => para.push(Inline::LineBreak),
+            "img" => {
+                let src = element.attribute("src").unwrap_or_default();
+                let path = self
+                    .path
+                    .parent()
+                    .map(|dir| dir.join(&src))
+                    .filter(|path| path.is_file());
+                para.push(Inline::Image {
+                    alt: element.attribute("alt").unwrap_or_default(),
+                    src,
+                    path,
+                });
+            }
+            "a" if element.attribute("href").is_some() && !text.is_empty() => {
+                let href = element.attribute("href").unwrap_or_default();
+                if href.contains("://") || href.starts_with("mailto:") {
+                    styled(
+                        para,
+                        Inline::Link {
+                            url: href,
+                            text: text.clone(),
+                        },
+                    );
+                } else if is_symbol(&text) {
+                    styled(para, Inline::Reference(text.clone()));
+                } else if let Some(url) = self.url {
+                    let url = format!("{}/{}", url, href.trim_start_matches("./"));
+                    styled(
+                        para,
+                        Inline::Link {
+                            url,
+                            text: text.clone(),
+                        },
+                    );
+                } else {
+                    styled(para, Inline::Text(text.clone()));
+                }
+            }
+            "code" | "tt" | "kbd" | "samp" | "var" | "span" | "acronym"
+                if matches!(class.as_str(), "function" | "type")
+                    && is_identifier(text.trim_end_matches("()")) =>
+            {
+                styled(para, Inline::Reference(text.clone()))
+            }
+            "code" | "tt" | "kbd" | "samp" | "var" => styled(para, Inline::Code(text.clone())),
+            "span"
+                if matches!(
+                    class.as_str(),
+                    "type"
+                        | "function"
+                        | "literal"
+                        | "parameter"
+                        | "structfield"
+                        | "constant"
+                        | "returnvalue"
+                ) =>
+            {
+                styled(para, Inline::Code(text.clone()))
+            }
+            "span" if class == "annotation" => {}
+            "em" | "i" | "cite" => styled(para, Inline::Emphasis(text.clone())),
+            "span" if class == "emphasis" => styled(para, Inline::Emphasis(text.clone())),
+            "b" | "strong" => styled(para, Inline::Strong(text.clone())),
+            _ => {
+                for child in &element.children {
+                    self.inlines(child, para);
+                }
+            }
+        }
+    }
+}
+
+/// An element of an HTML page with everything inside it.
+#[derive(Clone)]
+struct Element {
+    name: String,
+    /// The start tag as written, to read attributes from.
+    tag: String,
+    children: Vec<Node>,
+    /// Byte offset of the start tag in the page.
+    start: usize,
+}
+
+#[derive(Clone)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<String> {
+        doc::html_attribute(&self.tag, name).map(|v| doc::decode_entities(&v).into_owned())
+    }
+    fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .is_some_and(|c| c.split_whitespace().any(|c| c == class))
+    }
+    /// Returns whether this is a `name` element with the class `class`.
+    fn is(&self, name: &str, class: &str) -> bool {
+        self.name == name && self.has_class(class)
+    }
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+    /// Returns the elements inside this one, in document order.
+    fn descendants(&self) -> impl Iterator<Item = &Element> {
+        let mut stack = self.elements().collect::<Vec<_>>();
+        stack.reverse();
+        std::iter::from_fn(move || {
+            let next = stack.pop()?;
+            stack.extend(next.elements().collect::<Vec<_>>().into_iter().rev());
+            Some(next)
+        })
+    }
+    fn text(&self) -> String {
+        self.children.iter().map(node_text).collect()
+    }
+}
+
+fn node_text(node: &Node) -> String {
+    match node {
+        Node::Element(element) => element.text(),
+        Node::Text(text) => text.clone(),
+    }
+}
+
+/// Elements that never have an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "wbr",
+];
+
+/// Parses an HTML page into a tree, leniently: end tags without a start tag are ignored, and
+/// elements that aren't closed end with the one they are in, or where HTML implies it, like
+/// `<p>` at the next block.
+fn parse(html: &str) -> Element {
+    fn close(stack: &mut Vec<Element>) {
+        let element = stack.pop().unwrap();
+        stack
+            .last_mut()
+            .unwrap()
+            .children
+            .push(Node::Element(element));
+    }
+    let mut stack = vec![Element {
+        name: String::new(),
+        tag: String::new(),
+        children: Vec::new(),
+        start: 0,
+    }];
+    let mut pos = 0;
+    while pos < html.len() {
+        let start = html[pos..].find('<').map_or(html.len(), |i| pos + i);
+        let text = &html[pos..start];
+        if !text.is_empty() {
+            let text = doc::decode_entities(text).into_owned();
+            stack.last_mut().unwrap().children.push(Node::Text(text));
+        }
+        let rest = &html[start..];
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            pos = comment
+                .find("-->")
+                .map_or(html.len(), |i| start + 4 + i + 3);
+            continue;
+        }
+        let tag_like = rest[1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+        let end = match rest.find('>') {
+            Some(end) if tag_like => start + end + 1,
+            // a `<` that doesn't start a tag is text
+            _ => {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(Node::Text("<".into()));
+                pos = start + 1;
+                continue;
+            }
+        };
+        let tag = &html[start..end];
+        pos = end;
+        let (name, closing) = match doc::html_tag(tag) {
+            Some(tag) if tag.0.chars().all(|c| c.is_ascii_alphanumeric()) => tag,
+            _ => continue,
+        };
+        if closing {
+            if let Some(i) = stack
+                .iter()
+                .rposition(|e| e.name == name)
+                .filter(|i| *i > 0)
+            {
+                while stack.len() > i {
+                    close(&mut stack);
+                }
+            }
+            continue;
+        }
+        if matches!(name.as_str(), "script" | "style") {
+            let end = format!("</{}", name);
+            pos = html[pos..]
+                .to_ascii_lowercase()
+                .find(&end)
+                .map_or(html.len(), |i| pos + i);
+            continue;
+        }
+        // elements that can't hold the new one end before it
+        let ends: &[&str] = match name.as_str() {
+            "li" => &["li", "p"],
+            "dt" | "dd" => &["dt", "dd", "p"],
+            "tr" => &["tr", "td", "th", "p"],
+            "td" | "th" => &["td", "th", "p"],
+            name if is_block(name) => &["p"],
+            _ => &[],
+        };
+        while stack.len() > 1 && ends.contains(&stack.last().unwrap().name.as_str()) {
+            close(&mut stack);
+        }
+        let element = Element {
+            name,
+            tag: tag.to_owned(),
+            children: Vec::new(),
+            start,
+        };
+        if VOID_ELEMENTS.contains(&element.name.as_str()) || tag.ends_with("/>") {
+            stack
+                .last_mut()
+                .unwrap()
+                .children
+                .push(Node::Element(element));
+        } else {
+            stack.push(element);
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().unwrap()
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "pre"
+            | "table"
+            | "tr"
+            | "td"
+            | "th"
+            | "ul"
+            | "ol"
+            | "li"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "blockquote"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+    )
+}
+
+/// Returns the text of a code block. Doxygen puts each line of code into a `div` of its own.
+/// Returns the language of a code block from the classes of it or of a `<code>` element in it,
+/// like `language-python`.
+fn code_language(element: &Element) -> &'static str {
+    std::iter::once(element)
+        .chain(element.elements().filter(|e| e.name == "code"))
+        .filter_map(|e| e.attribute("class"))
+        .flat_map(|classes| {
+            classes
+                .split_whitespace()
+                .map(doc::fence_language)
+                .collect::<Vec<_>>()
+        })
+        .find(|language| *language != "text")
+        .unwrap_or("text")
+}
+
+fn code_text(element: &Element) -> String {
+    let lines = element
+        .elements()
+        .filter(|e| e.is("div", "line"))
+        .map(Element::text)
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        element.text()
+    } else {
+        lines.join("\n")
+    }
+}
+
+/// Returns the gtk-doc annotations listed in an element, like `[nullable][transfer full]`.
+fn annotations_of(element: &Element) -> Vec<String> {
+    element
+        .text()
+        .split(['[', ']'])
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Adds a paragraph without the whitespace around it, unless it is empty.
+fn flush(para: &mut Paragraph, paras: &mut Vec<Paragraph>) {
+    let mut taken = std::mem::take(para);
+    if let Some(Inline::Text(text)) = taken.first_mut() {
+        *text = text.trim_start().to_owned();
+    }
+    if let Some(Inline::Text(text)) = taken.last_mut() {
+        *text = text.trim_end().to_owned();
+    }
+    taken.retain(|i| !matches!(i, Inline::Text(t) if t.is_empty()));
+    while taken.last() == Some(&Inline::LineBreak) {
+        taken.pop();
+    }
+    if !taken.is_empty() {
+        paras.push(taken);
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns whether the text of a link looks like the name of a C symbol rather than a word,
+/// like `gtk_window_new()`, `GtkWindow` or `NULL`.
+fn is_symbol(text: &str) -> bool {
+    let name = text.trim_end_matches("()");
+    is_identifier(name)
+        && (name.len() < text.len() || name.contains(|c: char| c == '_' || c.is_ascii_uppercase()))
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit()) && name.chars().all(is_identifier_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(html: &str) -> Vec<(String, Symbol)> {
+        let page = Page {
+            path: Path::new("page.html"),
+            html,
+            url: Some("https://example.com/docs"),
+        };
+        page.symbols()
+    }
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    #[test]
+    fn lenient_parse() {
+        let root = parse("<p>one<p>two &amp; <b>three</b><script>1 < 2</script></div>x < y");
+        let names = root.elements().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["p", "p"]);
+        assert_eq!(root.text(), "onetwo & threex < y");
+    }
+
+    #[test]
+    fn gtkdoc_function() {
+        let html = r#"<div class="refsect2">
+<h3>gtk_window_new ()</h3>
+<pre class="programlisting">GtkWidget *gtk_window_new (GtkWindowType type);</pre>
+<p>Creates a new <a class="link" href="GtkWindow.html">GtkWindow</a>, see
+<a href="https://example.org">the guide</a>.</p>
+<div class="refsect3"><h4>Parameters</h4><table><tr>
+<td class="parameter_name"><p>type</p></td>
+<td class="parameter_description"><p>type of window</p></td>
+<td class="parameter_annotations"><span class="annotation">[in]</span></td>
+</tr></table></div>
+<div class="refsect3"><h4>Returns</h4>
+<p>a new <span class="type">GtkWindow</span>. <span class="annotation">[transfer none]</span></p>
+</div>
+<p class="since">Since: 2.0</p>
+</div>"#;
+        let symbols = symbols(html);
+        assert_eq!(symbols.len(), 1);
+        let (name, symbol) = &symbols[0];
+        assert_eq!(name, "gtk_window_new");
+        assert_eq!(symbol.kind, Some(Kind::Function));
+        assert_eq!(
+            symbol.doc.description,
+            [vec![
+                text("Creates a new "),
+                Inline::Reference("GtkWindow".into()),
+                text(", see "),
+                Inline::Link {
+                    url: "https://example.org".into(),
+                    text: "the guide".into(),
+                },
+                text("."),
+            ]]
+        );
+        let param = &symbol.doc.params[0];
+        assert_eq!(param.name, "type");
+        assert_eq!(param.description, [vec![text("type of window")]]);
+        assert_eq!(param.annotations.direction, Some(doc::Direction::In));
+        assert_eq!(
+            symbol.doc.returns.description,
+            [vec![
+                text("a new "),
+                Inline::Reference("GtkWindow".into()),
+                text(".")
+            ]]
+        );
+        assert!(symbol.doc.returns.annotations.transfer.is_some());
+    }
+
+    #[test]
+    fn gtkdoc_enum() {
+        let html = r#"<div class="refsect2"><h3>enum GtkAlign</h3>
+<p>Alignment.</p>
+<div class="refsect3"><h4>Members</h4><table><tr>
+<td class="enum_member_name"><p>GTK_ALIGN_FILL</p></td>
+<td class="enum_member_description"><p>fill the space</p></td>
+</tr></table></div></div>"#;
+        let names = symbols(html)
+            .into_iter()
+            .map(|(name, symbol)| (name, symbol.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("GTK_ALIGN_FILL".to_owned(), Some(Kind::Constant)),
+                ("GtkAlign".to_owned(), Some(Kind::Enum)),
+            ]
+        );
+    }
+
+    #[test]
+    fn doxygen_function() {
+        let html = r#"<div class="memitem">
+<div class="memproto"><table class="memname"><tr>
+<td class="memname">int foo_open </td><td>(</td><td class="paramtype">const char *&#160;</td>
+<td class="paramname"><em>path</em></td><td>)</td></tr></table></div>
+<div class="memdoc"><p>Opens <code>path</code>.</p>
+<h2>Details</h2>
+<dl class="params"><dt>Parameters</dt><dd><table class="params"><tr>
+<td class="paramdir">[in]</td><td class="paramname">path</td><td>file to open</td>
+</tr></table></dd></dl>
+<dl class="section return"><dt>Returns</dt><dd>a descriptor</dd></dl>
+<dl class="section deprecated"><dt>Deprecated</dt><dd>Use foo_open2().</dd></dl>
+<div class="fragment"><div class="line">foo_open(&quot;x&quot;);</div></div>
+</div></div>"#;
+        let symbols = symbols(html);
+        let (name, symbol) = &symbols[0];
+        assert_eq!(name, "foo_open");
+        assert_eq!(symbol.kind, Some(Kind::Function));
+        assert_eq!(
+            symbol.doc.description,
+            [
+                vec![text("Opens "), Inline::Code("path".into()), text(".")],
+                vec![Inline::Heading {
+                    level: 1,
+                    title: "Details".into(),
+                    id: None,
+                }],
+            ]
+        );
+        assert_eq!(symbol.doc.params[0].name, "path");
+        assert_eq!(
+            symbol.doc.params[0].annotations.direction,
+            Some(doc::Direction::In)
+        );
+        assert_eq!(symbol.doc.returns.description, [vec![text("a descriptor")]]);
+        assert_eq!(
+            symbol.doc.notes,
+            [vec![text("Deprecated: "), text("Use foo_open2().")]]
+        );
+        assert_eq!(symbol.doc.examples[0].code, "foo_open(\"x\");");
+    }
+
+    #[test]
+    fn heading_levels() {
+        let levels = |html: &str| {
+            let mut paras = Vec::new();
+            let page = Page {
+                path: Path::new("page.html"),
+                html,
+                url: None,
+            };
+            page.blocks(&parse(html).children, &mut paras, &mut Vec::new());
+            paras
+                .iter()
+                .filter_map(|p| match p.first() {
+                    Some(Inline::Heading { level, .. }) => Some(*level),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            levels("<h1>a</h1><h2>b</h2><h3>c</h3><h6>d</h6><h255>e</h255><h0>f</h0>"),
+            [1, 1, 2, 5]
+        );
+    }
+
+    #[test]
+    fn relative_links() {
+        let html = r#"<div class="refsect2"><h3>foo_init ()</h3>
+<p>See <a href="./intro.html">the introduction</a>.</p></div>"#;
+        assert_eq!(
+            symbols(html)[0].1.doc.description,
+            [vec![
+                text("See "),
+                Inline::Link {
+                    url: "https://example.com/docs/intro.html".into(),
+                    text: "the introduction".into(),
+                },
+                text("."),
+            ]]
+        );
+    }
+}
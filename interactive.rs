@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use crate::db::Database;
+
+/// Reads a file mapping Rust aliases to C symbols, written as `alias = symbol` lines. An empty
+/// symbol means the alias was skipped. Returns an empty map if the file doesn't exist yet.
+pub fn load_map(path: &Path) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut map = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (alias, symbol) = line
+            .split_once('=')
+            .ok_or_else(|| format!("{}:{}: expected `alias = symbol`", path.display(), i + 1))?;
+        let alias = alias.trim();
+        let alias = alias.strip_prefix("r#").unwrap_or(alias);
+        map.insert(alias.to_owned(), symbol.trim().to_owned());
+    }
+    Ok(map)
+}
+
+/// Appends a choice to the mapping file. `None` records that the alias was skipped.
+pub fn record(path: &Path, alias: &str, symbol: Option<&str>) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    match symbol {
+        Some(symbol) => writeln!(file, "{} = {}", alias, symbol),
+        None => writeln!(file, "{} =", alias),
+    }
+}
+
+/// Asks which symbol an alias that doesn't resolve stands for, showing similarly named
+/// symbols. Returns `Ok(None)` if it was skipped, and an error at the end of the input.
+pub fn choose(alias: &str, db: &Database) -> std::io::Result<Option<String>> {
+    let candidates = candidates(alias, db.symbols.keys().map(String::as_str));
+    let mut stderr = std::io::stderr();
+    writeln!(stderr, "{} doesn't match any documented symbol.", alias)?;
+    for (i, candidate) in candidates.iter().enumerate() {
+        writeln!(stderr, "  {}) {}", i + 1, candidate)?;
+    }
+    loop {
+        write!(
+            stderr,
+            "Pick a number, type a symbol name or leave empty to skip: "
+        )?;
+        stderr.flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        let symbol = match answer.parse::<usize>() {
+            Ok(n) => candidates.get(n.wrapping_sub(1)).copied(),
+            Err(_) => Some(answer).filter(|a| db.get(a).is_some()),
+        };
+        match symbol {
+            Some(symbol) => return Ok(Some(symbol.to_owned())),
+            None => writeln!(stderr, "{} is not one of the choices", answer)?,
+        }
+    }
+}
+
+/// Returns up to five names that look like `alias`, the closest first. Names are compared
+/// without case and underscores, since Rust and C spell them differently.
+fn candidates<'a>(alias: &str, names: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let normalize = |s: &str| s.to_lowercase().replace(['_', '.', ':'], "");
+    let alias = normalize(alias);
+    let max = (alias.chars().count() / 3).max(2);
+    let mut scored = names
+        .filter(|name| !name.contains('.'))
+        .filter_map(|name| {
+            let normalized = normalize(name);
+            let score = if normalized.contains(&alias) || alias.contains(&normalized) {
+                // prefixes like `gtk_` make C names longer than their Rust aliases
+                alias.len().abs_diff(normalized.len()).min(max)
+            } else {
+                edit_distance(&alias, &normalized)
+            };
+            (score <= max).then_some((score, name))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_unstable();
+    scored.into_iter().take(5).map(|(_, name)| name).collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,767 @@
+use serde::{
+    de::{self, IntoDeserializer},
+    ser::{self, Serialize},
+};
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Serializes a value as JSON indented by two spaces, with enums written like serde_json does:
+/// unit variants as strings and other variants as an object with the variant name as key.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+#[derive(Default)]
+struct Serializer {
+    out: String,
+    indent: usize,
+}
+
+impl Serializer {
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => self.out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.out.push_str(&"  ".repeat(self.indent));
+    }
+    fn open(&mut self, open: char, close: char, variant: Option<&str>) -> Compound<'_> {
+        if let Some(variant) = variant {
+            self.out.push('{');
+            self.indent += 1;
+            self.newline();
+            self.string(variant);
+            self.out.push_str(": ");
+        }
+        self.out.push(open);
+        self.indent += 1;
+        Compound {
+            ser: self,
+            first: true,
+            close,
+            variant: variant.is_some(),
+        }
+    }
+}
+
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    close: char,
+    /// Set if the value is wrapped in an object with the variant name as key.
+    variant: bool,
+}
+
+impl Compound<'_> {
+    fn element(&mut self) {
+        if !self.first {
+            self.ser.out.push(',');
+        }
+        self.first = false;
+        self.ser.newline();
+    }
+    fn key(&mut self, key: &str) {
+        self.element();
+        self.ser.string(key);
+        self.ser.out.push_str(": ");
+    }
+    fn close(self) {
+        self.ser.indent -= 1;
+        if !self.first {
+            self.ser.newline();
+        }
+        self.ser.out.push(self.close);
+        if self.variant {
+            self.ser.indent -= 1;
+            self.ser.newline();
+            self.ser.out.push('}');
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.out.push_str(if v { "true" } else { "false" });
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.out.push_str(&v.to_string());
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.out.push_str(&v.to_string());
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if v.is_finite() {
+            self.out.push_str(&v.to_string());
+        } else {
+            self.out.push_str("null");
+        }
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.string(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.string(v);
+        Ok(())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        let mut seq = self.open('[', ']', None);
+        for b in v {
+            ser::SerializeSeq::serialize_element(&mut seq, b)?;
+        }
+        seq.close();
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.out.push_str("null");
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let mut map = self.open('{', '}', None);
+        map.key(variant);
+        value.serialize(&mut *map.ser)?;
+        map.close();
+        Ok(())
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(self.open('[', ']', None))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(self.open('[', ']', None))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(self.open('[', ']', None))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(self.open('[', ']', Some(variant)))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(self.open('{', '}', None))
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(self.open('{', '}', None))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(self.open('{', '}', Some(variant)))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element();
+        value.serialize(&mut *self.ser)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // JSON keys are strings, so other keys are written as the string of their JSON
+        let key = to_string(key)?;
+        self.element();
+        if key.starts_with('"') {
+            self.ser.out.push_str(&key);
+        } else {
+            self.ser.string(&key);
+        }
+        self.ser.out.push_str(": ");
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.key(key);
+        value.serialize(&mut *self.ser)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close();
+        Ok(())
+    }
+}
+
+/// Deserializes a value from JSON, with enums written like `to_string` writes them.
+pub fn from_str<T: de::DeserializeOwned>(json: &str) -> Result<T, Error> {
+    let mut parser = Parser { json, pos: 0 };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.pos < json.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    T::deserialize(value)
+}
+
+/// A parsed JSON value, which is then deserialized into the type wanted.
+enum Value {
+    Null,
+    Bool(bool),
+    /// The number as written, so that integers don't go through a float.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        let before = &self.json[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Error(format!("{} at line {} column {}", message, line, column))
+    }
+    fn whitespace(&mut self) {
+        let rest = &self.json[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+    fn eat(&mut self, token: &str) -> bool {
+        self.whitespace();
+        let found = self.json[self.pos..].starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+    fn value(&mut self) -> Result<Value, Error> {
+        self.whitespace();
+        let rest = &self.json[self.pos..];
+        for (literal, value) in [
+            ("null", Value::Null),
+            ("true", Value::Bool(true)),
+            ("false", Value::Bool(false)),
+        ] {
+            if rest.starts_with(literal) {
+                self.pos += literal.len();
+                return Ok(value);
+            }
+        }
+        match rest.chars().next() {
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !self.eat("]") {
+                    loop {
+                        items.push(self.value()?);
+                        if self.eat("]") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return Err(self.error("expected `,` or `]`"));
+                        }
+                    }
+                }
+                Ok(Value::Array(items))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                if !self.eat("}") {
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        if !self.eat(":") {
+                            return Err(self.error("expected `:`"));
+                        }
+                        entries.push((key, self.value()?));
+                        if self.eat("}") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return Err(self.error("expected `,` or `}`"));
+                        }
+                    }
+                }
+                Ok(Value::Object(entries))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .unwrap_or(rest.len());
+                self.pos += len;
+                Ok(Value::Number(rest[..len].to_owned()))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+    fn string(&mut self) -> Result<String, Error> {
+        if !self.json[self.pos..].starts_with('"') {
+            return Err(self.error("expected a string"));
+        }
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.json[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'u')) => {
+                            let unit = |chars: &mut std::str::CharIndices| {
+                                let hex = chars.take(4).map(|(_, c)| c).collect::<String>();
+                                u32::from_str_radix(&hex, 16).ok()
+                            };
+                            let high = unit(&mut chars);
+                            // characters outside the basic plane are escaped as surrogate pairs
+                            let code = match high {
+                                Some(high @ 0xd800..=0xdbff) => {
+                                    let low = match (chars.next(), chars.next()) {
+                                        (Some((_, '\\')), Some((_, 'u'))) => unit(&mut chars),
+                                        _ => None,
+                                    };
+                                    low.filter(|low| (0xdc00..=0xdfff).contains(low))
+                                        .map(|low| {
+                                            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                                        })
+                                }
+                                code => code,
+                            };
+                            match code.and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    self.pos += i;
+                                    return Err(self.error("invalid unicode escape"));
+                                }
+                            }
+                        }
+                        Some((_, c)) => c,
+                        None => break,
+                    };
+                    out.push(escaped);
+                }
+                c => out.push(c),
+            }
+        }
+        self.pos = self.json.len();
+        Err(self.error("unterminated string"))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => {
+                let invalid = || Error(format!("invalid number {}", n));
+                if n.contains(['.', 'e', 'E']) {
+                    visitor.visit_f64(n.parse().map_err(|_| invalid())?)
+                } else if n.starts_with('-') {
+                    visitor.visit_i64(n.parse().map_err(|_| invalid())?)
+                } else {
+                    visitor.visit_u64(n.parse().map_err(|_| invalid())?)
+                }
+            }
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(items) => {
+                let mut seq = de::value::SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(entries) => {
+                let mut map = de::value::MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(entries) if entries.len() == 1 => {
+                let (variant, value) = entries.into_iter().next().unwrap();
+                visitor.visit_enum(Variant { variant, value })
+            }
+            _ => Err(Error(
+                "expected a string or an object with one key for an enum".into(),
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// An enum variant written as an object with the variant name as key.
+struct Variant {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for Variant {
+    type Error = Error;
+    type Variant = Value;
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Value), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = Error;
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Unit,
+        Newtype(Option<u32>),
+        Tuple(i64, String),
+        Struct { inner: Option<Box<Kind>> },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        kinds: Vec<Kind>,
+        size: Option<f64>,
+        empty: Vec<u8>,
+    }
+
+    fn round_trip<T: Serialize + de::DeserializeOwned + std::fmt::Debug + PartialEq>(value: T) {
+        let json = to_string(&value).unwrap();
+        assert_eq!(from_str::<T>(&json).unwrap(), value, "{}", json);
+    }
+
+    #[test]
+    fn escapes() {
+        let text = "quote \" backslash \\ newline \n tab \t return \r bell \u{7} nul \0";
+        assert_eq!(
+            to_string(text).unwrap(),
+            r#""quote \" backslash \\ newline \n tab \t return \r bell \u0007 nul \u0000""#
+        );
+        round_trip(text.to_owned());
+        assert_eq!(
+            from_str::<String>(r#""\u0000\/\b\fé""#).unwrap(),
+            "\0/\u{8}\u{c}é"
+        );
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        assert_eq!(from_str::<String>(r#""\ud83d\ude00""#).unwrap(), "😀");
+        assert_eq!(from_str::<String>(r#""a\uD834\uDD1Eb""#).unwrap(), "a𝄞b");
+        round_trip("😀 written as is".to_owned());
+        assert!(from_str::<String>(r#""\ud83d""#).is_err());
+        assert!(from_str::<String>(r#""\ud83dA""#).is_err());
+        assert!(from_str::<String>(r#""\ude00""#).is_err());
+    }
+
+    #[test]
+    fn nested() {
+        round_trip(Item {
+            name: "item".into(),
+            kinds: vec![
+                Kind::Unit,
+                Kind::Newtype(None),
+                Kind::Newtype(Some(3)),
+                Kind::Tuple(-1, "two".into()),
+                Kind::Struct { inner: None },
+                Kind::Struct {
+                    inner: Some(Box::new(Kind::Struct {
+                        inner: Some(Box::new(Kind::Unit)),
+                    })),
+                },
+            ],
+            size: Some(1.5),
+            empty: Vec::new(),
+        });
+        round_trip(Some(vec![(1u64, 'c')]));
+        round_trip(u64::MAX);
+        round_trip(i64::MIN);
+    }
+
+    #[test]
+    fn enum_forms() {
+        assert_eq!(to_string(&Kind::Unit).unwrap(), r#""Unit""#);
+        assert_eq!(
+            to_string(&Kind::Newtype(Some(1))).unwrap(),
+            "{\n  \"Newtype\": 1\n}"
+        );
+        assert_eq!(
+            from_str::<Kind>(r#"{"Tuple": [2, "x"]}"#).unwrap(),
+            Kind::Tuple(2, "x".into())
+        );
+        assert!(from_str::<Kind>(r#"{"Unit": null, "Tuple": [2, "x"]}"#).is_err());
+        assert!(from_str::<Kind>("3").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let error = |json| from_str::<Item>(json).unwrap_err().to_string();
+        assert_eq!(error(""), "expected a value at line 1 column 1");
+        assert_eq!(
+            error("{\n  \"name\" \"item\"}"),
+            "expected `:` at line 2 column 10"
+        );
+        assert_eq!(
+            error(r#"{"name": "item""#),
+            "expected `,` or `}` at line 1 column 16"
+        );
+        assert_eq!(
+            error(r#"{"name": "item"#),
+            "unterminated string at line 1 column 15"
+        );
+        assert_eq!(error("{} {}"), "trailing characters at line 1 column 4");
+        assert!(from_str::<Vec<u32>>("[1 2]").is_err());
+        assert!(from_str::<Vec<u32>>("[1,]").is_err());
+        assert!(from_str::<u32>("-1").is_err());
+        assert!(from_str::<String>(r#""\u12""#).is_err());
+        assert!(error(r#"{"name": 1}"#).contains("invalid type"));
+    }
+}
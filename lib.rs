@@ -0,0 +1,107 @@
+use std::{collections::HashMap, path::PathBuf};
+
+pub mod book;
+#[cfg(feature = "libclang")]
+pub mod build;
+#[cfg(feature = "libclang")]
+pub mod c;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod db;
+pub mod doc;
+pub mod doxygen;
+pub mod gtkdoc;
+pub mod headerdoc;
+pub mod html;
+pub mod interactive;
+pub mod json;
+pub mod man;
+pub mod markdown;
+pub mod rust;
+pub mod structured;
+#[cfg(feature = "libclang")]
+pub mod sync;
+
+/// Copies the docs of the symbols in `db` into the Rust source `rust_src` and returns the
+/// result, like the sync command with default options but without reading or writing any files.
+/// Meant for golden-file tests of the generated docs.
+pub fn sync_to_string(
+    rust_src: &str,
+    db: &db::Database,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut file = rust::RustFile::from_source(
+        PathBuf::new(),
+        rust_src.to_owned(),
+        &rust::Options::default(),
+    )?;
+    let mut db = db.clone();
+    db.expand_copies();
+    db.expand_callbacks();
+    rust::resolve_alternatives([&file], &mut db);
+    let options = default_options()?;
+    let signatures = rust::shared_signatures([&file]);
+    let docs = file
+        .doc_locations
+        .keys()
+        .filter_map(|name| {
+            let symbol = db.get(name)?;
+            let doc = markdown::render(
+                db.resolve(name),
+                symbol,
+                &db,
+                &HashMap::new(),
+                signatures.get(name.as_str()).copied().flatten(),
+                &options,
+            );
+            Some((name.clone(), doc))
+        })
+        .collect();
+    file.replace_docs(&docs, &HashMap::new(), None, &mut Vec::new());
+    Ok(file.source)
+}
+
+/// Converts the C doc comment of the symbol `name`, taking `args` as the names of its arguments
+/// if it is a function, to the Markdown docs the sync command would write for it. Only comments
+/// in the gtk-doc, HeaderDoc or structured formats are understood, since Doxygen ones need
+/// libclang to be parsed; returns `None` for others. This doesn't need libclang or any files, so
+/// previews of the generated docs, like a web playground, can use it when built for wasm32
+/// without the `libclang` feature.
+pub fn comment_to_markdown(
+    name: &str,
+    comment: &str,
+    args: &[String],
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let parsed = gtkdoc::parse(comment)
+        .or_else(|| headerdoc::parse(comment))
+        .or_else(|| structured::parse(comment, args));
+    let mut doc = match parsed {
+        Some(doc) => doc,
+        None => return Ok(None),
+    };
+    doc.find_languages();
+    doc.find_sections(comment);
+    doc.extract_thread_safety(&doc::command_texts(comment, "threadsafety"));
+    let symbol = db::Symbol {
+        doc,
+        config: None,
+        attributes: Vec::new(),
+        location: None,
+        kind: None,
+        signature: None,
+    };
+    Ok(Some(markdown::render_markdown(
+        name,
+        &symbol,
+        &db::Database::default(),
+        &HashMap::new(),
+        None,
+        &default_options()?,
+    )))
+}
+
+/// The defaults of the command line options for rendering docs.
+pub(crate) fn default_options() -> Result<markdown::Options, clap::Error> {
+    let command = <markdown::Options as clap::Args>::augment_args(clap::Command::new("sync"));
+    let matches = command.try_get_matches_from(["sync"])?;
+    <markdown::Options as clap::FromArgMatches>::from_arg_matches(&matches)
+}
@@ -1,394 +1,323 @@
-use std::{borrow::Cow, collections::HashMap, io::Read, ops::Range, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use markdown_gen::markdown;
-use proc_macro2::{LineColumn, Span};
-use syn::spanned::Spanned;
+use rust_clang_doc_generator::{c, doxygen, rust, sync};
 
 /// Copies doc comments from C sources into Rust sources.
 ///
-/// Any Rust functions/structs/enums annotated with `#[doc(alias = "func")]`
-/// will receive doc comments from the corresponding C function.
-#[derive(clap::Parser, Debug, Default)]
+/// Any Rust functions/structs/enums/types annotated with `#[doc(alias = "func")]`
+/// will receive doc comments from the corresponding C function. Foreign items with
+/// `#[link_name]` are matched by that symbol name, and `reverse` also matches items with
+/// `#[export_name]` or `#[no_mangle]` by theirs.
+#[derive(clap::Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Rewrite Rust files in place.
+    #[clap(subcommand)]
+    command: Option<Command>,
+    #[clap(flatten)]
+    sync: SyncArgs,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Extract docs for every documented symbol in C sources into a database.
+    Extract(ExtractArgs),
+    /// Copy docs into Rust sources. This is the default if no command is given.
+    Sync(Box<SyncArgs>),
+    /// Copy docs of aliased Rust items into Doxygen comments of the C declarations.
+    Reverse(ReverseArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ExtractArgs {
+    #[clap(flatten)]
+    c: c::Options,
+    /// File to write the database to.
+    #[clap(long)]
+    save_db: PathBuf,
+    /// Format to write the database in.
+    #[clap(long, value_enum, default_value_t)]
+    format: Format,
+    /// Also write a Make-style depfile with the database as target and the C sources and the
+    /// headers they include as dependencies, for build systems like Meson or CMake.
+    #[clap(long, value_name = "FILE")]
+    gen_depfile: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+enum Format {
+    /// The binary format read by --load-db.
+    #[default]
+    Db,
+    /// JSON with the structured docs, location, kind and signature of every symbol, for other
+    /// tools.
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReverseArgs {
+    /// Rewrite C files in place.
     #[clap(short, long)]
     in_place: bool,
     /// Backup files before writing. Must be used with -i.
     #[clap(short, long)]
     backup: bool,
-    /// List of C sources to pull doc comments from.
-    #[clap(short, long)]
-    c_srcs: Vec<PathBuf>,
-    /// List of Rust sources to parse and insert doc comments into.
+    #[clap(flatten)]
+    c: c::Options,
+    #[clap(flatten)]
+    rust: rust::Options,
+    /// List of Rust sources to take doc comments from.
     rust_srcs: Vec<PathBuf>,
 }
 
-mod keywords {
-    syn::custom_keyword!(alias);
+#[derive(clap::Args, Debug, Default)]
+struct SyncArgs {
+    /// Rewrite Rust files in place.
+    #[clap(short, long)]
+    in_place: bool,
+    /// Backup files before writing. Must be used with -i.
+    #[clap(short, long)]
+    backup: bool,
+    /// Fail instead of only warning if generated docs contain something rustdoc would warn
+    /// about, like bare URLs or unclosed code blocks.
+    #[clap(long)]
+    deny_warnings: bool,
+    /// Also write a Make-style depfile with every file the docs were made from as dependencies,
+    /// like the Rust sources, the C sources and the headers they include, for build systems like
+    /// Meson or CMake to sync again when any of them changes. Its target is the depfile itself,
+    /// which is written last, since the Rust sources can't depend on themselves.
+    #[clap(long, value_name = "FILE")]
+    gen_depfile: Option<PathBuf>,
+    #[clap(flatten)]
+    options: sync::Options,
 }
 
-struct DocComment(syn::LitStr);
+/// Cancelled by the first Ctrl-C, so that the file being written is finished and nothing else
+/// is done.
+static INTERRUPT: OnceLock<sync::CancellationToken> = OnceLock::new();
 
-impl syn::parse::Parse for DocComment {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        input.parse::<syn::Token![=]>()?;
-        let s = input.parse::<syn::LitStr>()?;
-        input.parse::<syn::parse::Nothing>()?;
-        Ok(Self(s))
-    }
-}
-
-impl DocComment {
-    fn find(attrs: &[syn::Attribute]) -> Option<syn::LitStr> {
-        attrs.iter().find_map(|a| {
-            a.path
-                .is_ident("doc")
-                .then(|| syn::parse2::<Self>(a.tokens.clone()).ok().map(|d| d.0))
-                .flatten()
-        })
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = <Args as clap::Parser>::parse();
+    INTERRUPT.get_or_init(sync::CancellationToken::new);
+    #[cfg(unix)]
+    handle_interrupts();
+    match args.command {
+        Some(Command::Extract(args)) => extract(args),
+        Some(Command::Sync(args)) => sync(*args),
+        Some(Command::Reverse(args)) => reverse(args),
+        None => sync(args.sync),
     }
 }
 
-struct DocAlias(String);
-
-impl syn::parse::Parse for DocAlias {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let content;
-        syn::parenthesized!(content in input);
-        input.parse::<syn::parse::Nothing>()?;
-        content.parse::<keywords::alias>()?;
-        content.parse::<syn::Token![=]>()?;
-        let s = content.parse::<syn::LitStr>()?;
-        if content.peek(syn::Token![,]) {
-            content.parse::<syn::Token![,]>()?;
+#[cfg(unix)]
+fn handle_interrupts() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        if let Some(token) = INTERRUPT.get() {
+            token.cancel();
+        }
+        // a second Ctrl-C stops the program at once
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
         }
-        content.parse::<syn::parse::Nothing>()?;
-        Ok(Self(s.value()))
     }
-}
-
-impl DocAlias {
-    fn find(attrs: &[syn::Attribute]) -> Option<String> {
-        attrs.iter().find_map(|a| {
-            a.path
-                .is_ident("doc")
-                .then(|| syn::parse2::<Self>(a.tokens.clone()).ok().map(|d| d.0))
-                .flatten()
-        })
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
     }
 }
 
-struct Source<'s> {
-    full: &'s str,
-    lines: Vec<&'s str>,
+fn interrupted() -> bool {
+    INTERRUPT
+        .get()
+        .is_some_and(sync::CancellationToken::is_cancelled)
 }
 
-impl<'s> Source<'s> {
-    fn position(&self, pos: LineColumn) -> Option<usize> {
-        self.lines.get(pos.line - 1).and_then(|l| {
-            let index = l
-                .char_indices()
-                .nth(pos.column)
-                .map(|i| i.0)
-                .unwrap_or_else(|| l.len());
-            Some(l.get(index..index)?.as_ptr() as usize - self.full.as_ptr() as usize)
-        })
-    }
-    fn range_for(&self, span: Span) -> Option<Range<usize>> {
-        Some(self.position(span.start())?..self.position(span.end())?)
-    }
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so that the
+/// file is never left half-written, even if the program is killed.
+fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
 }
 
-struct DocVisitor<'s> {
-    source: Source<'s>,
-    doc_locations: HashMap<String, Vec<(usize, Range<usize>)>>,
+/// Prints where the docs of a symbol were found, for `--verbose`.
+fn print_found(symbol: &str, path: &Path, config: Option<&str>, merged: bool) {
+    match (merged, config) {
+        (true, _) => eprintln!("{}: also found in {}", symbol, path.display()),
+        (false, Some(config)) => {
+            eprintln!("{}: found in {} ({})", symbol, path.display(), config)
+        }
+        (false, None) => eprintln!("{}: found in {}", symbol, path.display()),
+    }
 }
 
-impl<'s> DocVisitor<'s> {
-    fn try_replace_docs(&mut self, span: Span, attrs: &[syn::Attribute]) {
-        if let Some(alias) = DocAlias::find(attrs) {
-            let locations = self.doc_locations.entry(alias).or_default();
-            let mut has_docs = false;
-            if let Some(doc) = DocComment::find(attrs) {
-                if let Some(range) = self.source.range_for(doc.span()) {
-                    locations.push((doc.span().start().column, range));
-                    has_docs = true;
-                }
-            }
-            if !has_docs {
-                if let Some(pos) = self.source.position(span.start()) {
-                    locations.push((span.start().column, pos..pos));
-                }
+fn extract(args: ExtractArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let token = INTERRUPT.get().unwrap();
+    let (db, inputs) = c::extract_all(&args.c, &mut |event| {
+        match event {
+            c::Event::Found {
+                symbol,
+                path,
+                config,
+                merged,
+            } if args.c.verbose => print_found(symbol, path, config, merged),
+            c::Event::MissingExample { symbol, file } => {
+                eprintln!(
+                    "warning: example {} in the docs of {} not found",
+                    file, symbol
+                )
             }
+            _ => {}
         }
+        token.check()
+    })?;
+    match args.format {
+        Format::Db => db.save(&args.save_db)?,
+        Format::Json => db.save_json(&args.save_db)?,
+    }
+    if let Some(path) = &args.gen_depfile {
+        write_depfile(path, [args.save_db.as_path()], &inputs)?;
     }
+    Ok(())
 }
 
-impl<'ast, 's> syn::visit::Visit<'ast> for DocVisitor<'s> {
-    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_item_fn(self, i);
-    }
-    fn visit_impl_item_method(&mut self, i: &'ast syn::ImplItemMethod) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_impl_item_method(self, i);
+/// Writes a depfile in the format of `gcc -MD`, with a rule making `targets` depend on `inputs`.
+fn write_depfile<'a>(
+    path: &Path,
+    targets: impl IntoIterator<Item = &'a Path>,
+    inputs: &BTreeSet<PathBuf>,
+) -> std::io::Result<()> {
+    let escape = |path: &Path| {
+        path.to_string_lossy()
+            .replace('$', "$$")
+            .replace('#', "\\#")
+            .replace(' ', "\\ ")
+    };
+    let targets = targets.into_iter().map(escape).collect::<Vec<_>>();
+    let mut depfile = format!("{}:", targets.join(" "));
+    for input in inputs {
+        depfile.push_str(" \\\n  ");
+        depfile.push_str(&escape(input));
     }
-    fn visit_item_struct(&mut self, i: &'ast syn::ItemStruct) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_item_struct(self, i);
-    }
-    fn visit_item_enum(&mut self, i: &'ast syn::ItemEnum) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_item_enum(self, i);
+    depfile.push('\n');
+    write_file(path, depfile)
+}
+
+fn reverse(mut args: ReverseArgs) -> Result<(), Box<dyn std::error::Error>> {
+    args.rust.exported_symbols = true;
+    let mut warnings = Vec::new();
+    let files = sync::parse_rust_srcs(&args.rust_srcs, &args.rust, &mut warnings, &mut |_| {
+        INTERRUPT.get().unwrap().check()
+    })?;
+    for warning in warnings {
+        eprintln!("{}", warning);
     }
-    fn visit_variant(&mut self, i: &'ast syn::Variant) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_variant(self, i);
+    let mut docs = HashMap::new();
+    for (alias, doc) in files.iter().flat_map(|f| &f.docs) {
+        docs.entry(alias.clone())
+            .or_insert_with(|| doxygen::from_markdown(doc));
     }
-    fn visit_item_const(&mut self, i: &'ast syn::ItemConst) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_item_const(self, i);
+    let names = docs.keys().cloned().collect::<HashSet<_>>();
+    let decls = c::declarations(&args.c, &names)?;
+    let mut by_file = BTreeMap::<_, Vec<_>>::new();
+    for (name, decl) in &decls {
+        by_file
+            .entry(&decl.file)
+            .or_default()
+            .push((decl, docs[name].clone()));
     }
-    fn visit_impl_item_const(&mut self, i: &'ast syn::ImplItemConst) {
-        self.try_replace_docs(i.span(), &i.attrs);
-        syn::visit::visit_impl_item_const(self, i);
+    for (path, comments) in by_file {
+        if interrupted() {
+            return Err("interrupted before writing every file".into());
+        }
+        let orig = std::fs::read_to_string(path)?;
+        let source = doxygen::write_comments(&orig, &comments);
+        if !args.in_place {
+            println!("{}:\n{}", path.display(), source);
+        } else if source != orig {
+            if args.backup {
+                write_file(&path.with_extension("bk"), orig)?;
+            }
+            write_file(path, source)?;
+        }
     }
+    Ok(())
 }
 
-struct RustFile {
-    path: PathBuf,
-    source: String,
-    doc_locations: HashMap<String, Vec<(usize, Range<usize>)>>,
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = <Args as clap::Parser>::parse();
-    let mut files = Vec::new();
-    for src in args.rust_srcs {
-        for path in glob::glob(src.to_string_lossy().as_ref())? {
-            let path = path?;
-            if !path.is_file() {
-                continue;
+fn sync(args: SyncArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let token = INTERRUPT.get().unwrap();
+    let verbose = args.options.c.verbose;
+    let outcome = sync::run_with_progress(&args.options, |event| {
+        match event {
+            sync::Event::Found {
+                symbol,
+                path,
+                config,
+                merged,
+            } if verbose => print_found(symbol, path, config, merged),
+            sync::Event::Expanded { path, alias } if verbose => {
+                eprintln!("{}: {} found after macro expansion", path.display(), alias)
             }
-            let mut file = std::fs::File::open(&path)?;
-            let mut source = String::new();
-            file.read_to_string(&mut source)?;
-            let ast = syn::parse_file(&source)?;
-            let doc_locations = {
-                let mut visitor = DocVisitor {
-                    source: Source {
-                        full: source.as_str(),
-                        lines: source.lines().collect(),
-                    },
-                    doc_locations: HashMap::new(),
-                };
-                syn::visit::Visit::visit_file(&mut visitor, &ast);
-                visitor.doc_locations
-            };
-            files.push(RustFile {
+            sync::Event::WrittenWithCfg {
+                alias,
                 path,
-                source,
-                doc_locations,
-            });
+                line,
+                cfg,
+            } if verbose => eprintln!(
+                "{}: docs written to {}:{} for cfg({})",
+                alias,
+                path.display(),
+                line,
+                cfg
+            ),
+            _ => {}
         }
-    }
-    let clang = clang::Clang::new().unwrap();
-    let index = clang::Index::new(&clang, true, false);
-    let mut c_docs = files
-        .iter()
-        .flat_map(|f| f.doc_locations.keys().cloned().map(|s| (s, String::new())))
-        .collect::<HashMap<_, _>>();
-    for src in args.c_srcs {
-        for path in glob::glob(src.to_string_lossy().as_ref())? {
-            let path = path?;
-            if !path.is_file() {
-                continue;
+        token.check()
+    })?;
+    let mut problems = 0;
+    for warning in &outcome.warnings {
+        match warning {
+            // aliases that don't resolve are pruned or left alone on purpose
+            sync::Warning::Unresolved { .. } | sync::Warning::Ambiguous { .. }
+                if !args.options.c.verbose =>
+            {
+                continue
             }
-            let parser = index.parser(path);
-            let tu = parser.parse()?;
-            let entity = tu.get_entity();
-            let mut res = Ok(());
-            entity.visit_children(|e, _| {
-                use clang::EntityKind;
-                let k = e.get_kind();
-                if k == EntityKind::FunctionDecl
-                    || k == EntityKind::StructDecl
-                    || k == EntityKind::TypedefDecl
-                    || k == EntityKind::EnumDecl
-                    || k == EntityKind::EnumConstantDecl
-                {
-                    if let (Some(name), Some(comment)) = (e.get_name(), e.get_parsed_comment()) {
-                        if let Some(doc) = c_docs.get_mut(&name) {
-                            if doc.is_empty() {
-                                match xml_to_markdown(&comment.as_xml()) {
-                                    Ok(d) => *doc = d,
-                                    Err(e) => {
-                                        res = Err(e);
-                                        return clang::EntityVisitResult::Break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                clang::EntityVisitResult::Recurse
-            });
-            res?;
+            sync::Warning::Docs { .. } => problems += 1,
+            _ => {}
         }
+        eprintln!("{}", warning);
     }
-    for mut file in files {
-        let mut changed = false;
-        let orig = (args.in_place && args.backup).then(|| file.source.clone());
-        let mut replacements = Vec::new();
-        for (ident, ranges) in file.doc_locations {
-            if let Some(doc) = c_docs.get(&ident) {
-                if !doc.is_empty() {
-                    changed = true;
-                    for (column, range) in ranges {
-                        let doc = if column > 0 {
-                            let mut doc = doc
-                                .lines()
-                                .enumerate()
-                                .map(|(i, line)| {
-                                    let mut line = line.to_owned();
-                                    if i > 0 {
-                                        line.insert_str(0, &" ".repeat(column));
-                                    }
-                                    line
-                                })
-                                .collect::<Vec<_>>()
-                                .join("\n");
-                            doc.push('\n');
-                            doc.push_str(&" ".repeat(column));
-                            doc.into()
-                        } else {
-                            Cow::Borrowed(doc.as_str())
-                        };
-                        replacements.push((doc, range));
-                    }
-                }
-            }
-        }
-        replacements.sort_by_key(|(_, range)| range.start);
-        for (doc, range) in replacements.into_iter().rev() {
-            file.source.replace_range(range, doc.as_ref());
+    if args.deny_warnings && problems > 0 {
+        return Err(format!("generated docs have {} warnings", problems).into());
+    }
+    for file in outcome.files {
+        if interrupted() {
+            return Err("interrupted before writing every file".into());
         }
         if !args.in_place {
             println!("{}:\n{}", file.path.display(), file.source);
-        } else if changed {
-            if let Some(orig) = orig {
-                std::fs::write(file.path.with_extension("bk"), orig.into_bytes())?;
+        } else if file.changed {
+            if args.backup {
+                write_file(&file.path.with_extension("bk"), file.original)?;
             }
-            std::fs::write(file.path, file.source.into_bytes())?;
+            write_file(&file.path, file.source)?;
         }
     }
-    Ok(())
-}
-
-fn get_paragraphs<'n>(
-    node: roxmltree::Node<'n, '_>,
-) -> impl Iterator<Item = markdown::Paragraph<'n>> + 'n {
-    use markdown::AsMarkdown;
-    node.children()
-        .filter(|n| n.has_tag_name("Para"))
-        .map(|para| {
-            para.children().fold("".paragraph(), |item, c| {
-                if c.is_text() {
-                    return item.append(c.text().unwrap());
-                } else if c.is_element() {
-                    if let Some(t) = c.text() {
-                        if c.has_tag_name("emphasized") {
-                            return item.append(t.code());
-                        } else {
-                            return item.append(t);
-                        }
-                    } else {
-                        return c.descendants().fold(item, |item, cc| {
-                            if cc.is_text() {
-                                item.append(c.text().unwrap())
-                            } else {
-                                item
-                            }
-                        });
-                    }
-                }
-                item
-            })
-        })
-}
-
-#[inline]
-fn write_paragraphs(md: &mut markdown::Markdown<Vec<u8>>, node: roxmltree::Node) {
-    for para in get_paragraphs(node) {
-        md.write(para).unwrap();
-    }
-}
-
-fn xml_to_markdown(xml: &str) -> Result<String, roxmltree::Error> {
-    use markdown_gen::markdown::AsMarkdown;
-    /*
-    xmltree::Element::parse(xml.as_bytes())
-        .unwrap()
-        .write_with_config(
-            std::io::stderr(),
-            xmltree::EmitterConfig::new().perform_indent(true),
-        )
-        .unwrap();
-    eprintln!("");
-    */
-    let document = roxmltree::Document::parse(xml)?;
-    let mut md = markdown::Markdown::new(Vec::new());
-
-    let root = document.root_element();
-    if let Some(abs) = root.children().find(|n| n.has_tag_name("Abstract")) {
-        write_paragraphs(&mut md, abs);
-    }
-    for disc in root.children().filter(|n| n.has_tag_name("Discussion")) {
-        write_paragraphs(&mut md, disc);
-    }
-    if let Some(params) = root.children().find(|n| n.has_tag_name("Parameters")) {
-        let mut has_params = false;
-        let list = params
-            .children()
-            .filter(|n| n.has_tag_name("Parameter"))
-            .fold(markdown::List::new(false), |list, param| {
-                if let Some(name) = param
-                    .children()
-                    .find(|n| n.has_tag_name("Name"))
-                    .and_then(|n| n.text())
-                {
-                    has_params = true;
-                    let item = name.code().paragraph();
-                    let item = param.children().fold(item, |item, n| {
-                        if n.has_tag_name("Discussion") {
-                            return get_paragraphs(n)
-                                .fold(item, |item, para| item.append("\n\n ").append(para));
-                        }
-                        item
-                    });
-                    return list.item(item);
-                }
-                list
-            });
-        if has_params {
-            md.write("Parameters".heading(1)).unwrap();
-            md.write(list.paragraph().append("\n")).unwrap();
-        }
+    if let Some(state) = &outcome.state {
+        state.save()?;
     }
-    if let Some(returns) = root.children().find(|n| n.has_tag_name("ResultDiscussion")) {
-        md.write("Returns").unwrap();
-        write_paragraphs(&mut md, returns);
+    if let Some(path) = &args.gen_depfile {
+        write_depfile(path, [path.as_path()], &outcome.inputs)?;
     }
-    let inner = md.into_inner();
-    let src = String::from_utf8_lossy(&inner);
-    let src = src
-        .lines()
-        .map(|line| {
-            let mut line = line.to_owned();
-            line.insert_str(0, "/// ");
-            line
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-    Ok(src)
+    Ok(())
 }
@@ -0,0 +1,868 @@
+use std::{collections::HashSet, path::Path};
+
+use crate::{
+    db::{Database, Kind, Location, Symbol},
+    doc::{self, CodeBlock, Doc, Inline, Paragraph},
+};
+
+/// Adds the docs of the functions described by man pages in section 3 to `db`, keeping any docs
+/// already in it. Each path is a page or a directory searched for pages, like `foo.3`. If
+/// `wanted` is given, only those functions are added. `found` is called with each function added
+/// and its page.
+pub fn extract(
+    paths: &[impl AsRef<Path>],
+    wanted: Option<&HashSet<String>>,
+    db: &mut Database,
+    found: &mut dyn FnMut(&str, &Path),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wanted = wanted.map(|wanted| {
+        wanted
+            .iter()
+            .map(|name| db.resolve(name).to_owned())
+            .collect::<HashSet<_>>()
+    });
+    for path in paths {
+        let path = path.as_ref();
+        let pages = if path.is_dir() {
+            let pattern = path.join("**").join("*.3*");
+            glob::glob(pattern.to_string_lossy().as_ref())?.collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![path.to_owned()]
+        };
+        for page in pages {
+            if !page.is_file() {
+                continue;
+            }
+            let source = std::fs::read(&page)?;
+            let source = String::from_utf8_lossy(&source);
+            let (names, parsed) = match parse(&source) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+            for name in names {
+                if db.symbols.contains_key(&name)
+                    || wanted.as_ref().is_some_and(|w| !w.contains(&name))
+                {
+                    continue;
+                }
+                found(&name, &page);
+                let symbol = Symbol {
+                    doc: parsed.doc.clone(),
+                    config: None,
+                    attributes: Vec::new(),
+                    location: Some(Location {
+                        file: page.clone(),
+                        line: parsed.line,
+                        hash: Location::hash_comment(&source),
+                    }),
+                    kind: parsed.functions.contains(&name).then_some(Kind::Function),
+                    signature: None,
+                };
+                db.symbols.insert(name, symbol);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What a man page says about the symbols it is named after.
+struct Page {
+    doc: Doc,
+    /// Names declared as functions in the synopsis.
+    functions: HashSet<String>,
+    /// Line of the NAME section.
+    line: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Name,
+    Synopsis,
+    Description,
+    Returns,
+    Errors,
+    Examples,
+    Notes,
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Font {
+    Roman,
+    Bold,
+    Italic,
+    Code,
+}
+
+/// Parses a man page written with either the `man` or the `mdoc` macros. Returns the names from
+/// its NAME section and the docs of the DESCRIPTION, RETURN VALUE, ERRORS, EXAMPLES and NOTES
+/// sections, or `None` if it has no names.
+fn parse(source: &str) -> Option<(Vec<String>, Page)> {
+    let mut parser = Parser {
+        section: Section::Other,
+        doc: Doc::default(),
+        errors: Vec::new(),
+        name: Vec::new(),
+        synopsis: Vec::new(),
+        names: Vec::new(),
+        functions: HashSet::new(),
+        para: Vec::new(),
+        font: Font::Roman,
+        tag: false,
+        code: None,
+        name_line: 1,
+    };
+    for (i, line) in source.lines().enumerate() {
+        parser.line(line, i as u32 + 1);
+    }
+    parser.flush();
+    let mut doc = parser.doc;
+    doc.returns.description.extend(parser.errors);
+    let mut names = parser.names;
+    if names.is_empty() {
+        // the `man` macros write the names as text, like `foo, bar \- do things`
+        let text = parser
+            .name
+            .iter()
+            .map(|p| plain_text(p))
+            .collect::<Vec<_>>();
+        let text = text.join(" ");
+        let list = text.split(" - ").next().unwrap_or_default();
+        names = list
+            .split(',')
+            .map(|n| n.trim().to_owned())
+            .filter(|n| is_identifier(n))
+            .collect();
+    }
+    let synopsis = parser
+        .synopsis
+        .iter()
+        .map(|p| plain_text(p))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut functions = parser.functions;
+    for name in &names {
+        if synopsis.contains(&format!("{}(", name)) || synopsis.contains(&format!("{} (", name)) {
+            functions.insert(name.clone());
+        }
+    }
+    (!names.is_empty()).then_some((
+        names,
+        Page {
+            doc,
+            functions,
+            line: parser.name_line,
+        },
+    ))
+}
+
+struct Parser {
+    section: Section,
+    doc: Doc,
+    errors: Vec<Paragraph>,
+    /// Text of the NAME section.
+    name: Vec<Paragraph>,
+    /// Text of the SYNOPSIS section.
+    synopsis: Vec<Paragraph>,
+    /// Names given with the `.Nm` macro.
+    names: Vec<String>,
+    /// Functions declared with `.Fn` or `.Fo` in the synopsis.
+    functions: HashSet<String>,
+    para: Paragraph,
+    /// Font set by `\f` escapes, which lasts until the next one.
+    font: Font,
+    /// Set if the next line is the tag of a `.TP` list item.
+    tag: bool,
+    /// Lines of the code block being read, if any.
+    code: Option<String>,
+    /// Line of the NAME section.
+    name_line: u32,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str, number: u32) {
+        let request = line
+            .strip_prefix('.')
+            .or_else(|| line.strip_prefix('\''))
+            .map(str::trim_start);
+        let (name, args) = match request {
+            Some(request) => match request.split_once(char::is_whitespace) {
+                Some((name, args)) => (name, args.trim()),
+                None => (request, ""),
+            },
+            None => {
+                match &mut self.code {
+                    Some(code) => {
+                        code.push_str(&unescape(line));
+                        code.push('\n');
+                    }
+                    None if line.trim().is_empty() => self.flush(),
+                    None => self.text(line),
+                }
+                return;
+            }
+        };
+        match name {
+            "\\\"" | "" => {}
+            _ if name.starts_with("\\\"") => {}
+            "SH" | "Sh" => {
+                self.flush();
+                let title = unescape(&words(args).join(" ")).to_ascii_uppercase();
+                self.section = match title.as_str() {
+                    "NAME" => {
+                        self.name_line = number;
+                        Section::Name
+                    }
+                    "SYNOPSIS" => Section::Synopsis,
+                    "DESCRIPTION" => Section::Description,
+                    "RETURN VALUE" | "RETURN VALUES" => Section::Returns,
+                    "ERRORS" => Section::Errors,
+                    "EXAMPLE" | "EXAMPLES" => Section::Examples,
+                    "NOTES" | "CAVEATS" => Section::Notes,
+                    _ => Section::Other,
+                };
+            }
+            "PP" | "LP" | "P" | "Pp" | "sp" | "SS" | "Ss" | "HP" => self.flush(),
+            "TP" => {
+                self.flush();
+                self.tag = true;
+            }
+            "IP" => {
+                self.flush();
+                let args = words(args);
+                if let Some(tag) = args.first().filter(|t| !t.is_empty()) {
+                    self.text(tag);
+                    self.para.push(Inline::Text(" — ".into()));
+                }
+            }
+            "It" => {
+                self.flush();
+                if !args.is_empty() {
+                    self.mdoc(args);
+                    self.para.push(Inline::Text(" — ".into()));
+                }
+            }
+            "br" => self.para.push(Inline::LineBreak),
+            "nf" | "EX" => self.start_code(),
+            "Bd" if args.contains("-literal") || args.contains("-unfilled") => self.start_code(),
+            "fi" | "EE" | "Ed" => self.end_code(),
+            "B" | "I" | "SB" | "SM" => {
+                let font = if name == "I" {
+                    Font::Italic
+                } else {
+                    Font::Bold
+                };
+                self.fonts(&[words(args).join(" ")], [font, font]);
+            }
+            "BR" | "RB" | "IR" | "RI" | "BI" | "IB" => {
+                let font = |c| match c {
+                    'B' => Font::Bold,
+                    'I' => Font::Italic,
+                    _ => Font::Roman,
+                };
+                let mut chars = name.chars();
+                let fonts = [font(chars.next().unwrap()), font(chars.next().unwrap())];
+                self.fonts(&words(args), fonts);
+            }
+            "Nm" if self.section == Section::Name => {
+                self.names.extend(
+                    words(args)
+                        .into_iter()
+                        .filter(|n| is_identifier(n))
+                        .map(|n| n.to_owned()),
+                );
+            }
+            "Fn" | "Fo" if self.section == Section::Synopsis => {
+                if let Some(&function) = words(args).first() {
+                    self.functions.insert(function.to_owned());
+                }
+            }
+            "Rv" => {
+                let functions = words(args)
+                    .into_iter()
+                    .filter(|a| *a != "-std")
+                    .collect::<Vec<_>>();
+                let mut para = vec![Inline::Text("The ".into())];
+                for (i, function) in functions.iter().enumerate() {
+                    if i > 0 {
+                        para.push(Inline::Text(", ".into()));
+                    }
+                    para.push(Inline::Reference(format!("{}()", function)));
+                }
+                para.push(Inline::Text(
+                    " function returns the value 0 if successful; otherwise the value -1 is \
+                     returned and the global variable "
+                        .into(),
+                ));
+                para.push(Inline::Code("errno".into()));
+                para.push(Inline::Text(" is set to indicate the error.".into()));
+                self.flush();
+                self.para = para;
+                self.flush();
+            }
+            _ if is_callable(name) || name == "Nd" => self.mdoc(line[1..].trim_start()),
+            // anything else only affects the layout
+            _ => {}
+        }
+    }
+    /// Adds a line of text, with the font escapes in it.
+    fn text(&mut self, line: &str) {
+        self.space();
+        let mut run = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                run.push(c);
+                continue;
+            }
+            match chars.peek() {
+                Some('f') => {
+                    chars.next();
+                    let font = match chars.next() {
+                        Some('(') => chars.by_ref().take(2).collect::<String>(),
+                        Some('[') => chars.by_ref().take_while(|c| *c != ']').collect(),
+                        Some(c) => c.to_string(),
+                        None => String::new(),
+                    };
+                    self.run(&std::mem::take(&mut run), self.font);
+                    self.font = match font.as_str() {
+                        "B" | "3" => Font::Bold,
+                        "I" | "2" => Font::Italic,
+                        "C" | "CW" | "CR" | "CB" => Font::Code,
+                        _ => Font::Roman,
+                    };
+                }
+                _ => {
+                    run.push_str(&unescape(&format!("\\{}", escape(&mut chars))));
+                }
+            }
+        }
+        self.run(&run, self.font);
+        if std::mem::take(&mut self.tag) {
+            self.para.push(Inline::Text(" — ".into()));
+        }
+    }
+    /// Adds the arguments of a macro like `.BR`, which alternate between two fonts without space
+    /// between them.
+    fn fonts(&mut self, args: &[impl AsRef<str>], fonts: [Font; 2]) {
+        self.space();
+        for (i, arg) in args.iter().enumerate() {
+            self.run(&unescape(arg.as_ref()), fonts[i % 2]);
+        }
+        if std::mem::take(&mut self.tag) {
+            self.para.push(Inline::Text(" — ".into()));
+        }
+    }
+    /// Adds the text of an `mdoc` macro line, which can call further macros.
+    fn mdoc(&mut self, line: &str) {
+        self.space();
+        let mut words = words(line).into_iter();
+        let mut current = "";
+        let mut close = Vec::new();
+        let mut punctuation = String::new();
+        let mut space = false;
+        while let Some(word) = words.next() {
+            if is_callable(word) {
+                current = word;
+                let (open, closing) = match current {
+                    "Pq" => ("(", ")"),
+                    "Dq" => ("“", "”"),
+                    "Sq" => ("‘", "’"),
+                    "Bq" | "Op" => ("[", "]"),
+                    "Ns" => {
+                        space = false;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                if space {
+                    doc::push_inline(&mut self.para, Inline::Text(" ".into()));
+                }
+                doc::push_inline(&mut self.para, Inline::Text(open.into()));
+                close.push(closing);
+                space = false;
+                continue;
+            }
+            let text = unescape(word);
+            // punctuation at the end of the line goes after any closing quotes
+            if is_punctuation(&text) {
+                punctuation.push_str(&text);
+                space = true;
+                continue;
+            }
+            if !punctuation.is_empty() {
+                let punctuation = std::mem::take(&mut punctuation);
+                doc::push_inline(&mut self.para, Inline::Text(punctuation));
+            }
+            if space {
+                doc::push_inline(&mut self.para, Inline::Text(" ".into()));
+            }
+            space = true;
+            let inline = match current {
+                "Fn" => {
+                    // the rest are the arguments of the function
+                    words.by_ref().take_while(|w| !is_punctuation(w)).count();
+                    Inline::Reference(format!("{}()", text))
+                }
+                "Xr" => {
+                    words.next();
+                    Inline::Reference(format!("{}()", text))
+                }
+                "Fa" | "Ft" | "Dv" | "Er" | "Va" | "Li" | "Ql" | "Nm" | "Cm" | "Ev" | "Pa"
+                | "Ic" | "Vt" => Inline::Code(text),
+                "Fl" => Inline::Code(format!("-{}", text)),
+                "Em" | "Ar" => Inline::Emphasis(text),
+                "Sy" => Inline::Strong(text),
+                _ => Inline::Text(text),
+            };
+            doc::push_inline(&mut self.para, inline);
+        }
+        if current == "Nm" && !space {
+            if let Some(name) = self.names.first() {
+                self.para.push(Inline::Code(name.clone()));
+            }
+        }
+        for close in close.into_iter().rev() {
+            doc::push_inline(&mut self.para, Inline::Text(close.into()));
+        }
+        if !punctuation.is_empty() {
+            doc::push_inline(&mut self.para, Inline::Text(punctuation));
+        }
+    }
+    /// Adds a run of text in a font. Bold names of symbols become code, and names followed by
+    /// the section of their man page, like `open(2)`, references.
+    fn run(&mut self, text: &str, font: Font) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(Inline::Strong(name) | Inline::Code(name)) = self.para.last() {
+            // a call like `foo()` is also written with the parentheses in another font
+            let section = text
+                .strip_prefix('(')
+                .and_then(|t| t.split_once(')'))
+                .filter(|(section, _)| {
+                    section.is_empty() || section.starts_with(|c: char| c.is_ascii_digit())
+                });
+            if let (Some((_, rest)), true) = (section, is_identifier(name)) {
+                let reference = Inline::Reference(format!("{}()", name));
+                *self.para.last_mut().unwrap() = reference;
+                return self.run(rest, font);
+            }
+        }
+        let trimmed = text.trim();
+        let inline = match font {
+            Font::Roman => {
+                doc::push_inline(&mut self.para, Inline::Text(text.to_owned()));
+                return;
+            }
+            _ if trimmed.is_empty() => return,
+            Font::Code => Inline::Code(trimmed.to_owned()),
+            Font::Bold if is_symbol(trimmed) => Inline::Code(trimmed.to_owned()),
+            Font::Bold => Inline::Strong(trimmed.to_owned()),
+            Font::Italic => Inline::Emphasis(trimmed.to_owned()),
+        };
+        // spaces inside of emphasis would stop it from being recognized as such
+        if text.starts_with(char::is_whitespace) {
+            doc::push_inline(&mut self.para, Inline::Text(" ".into()));
+        }
+        doc::push_inline(&mut self.para, inline);
+        if text.ends_with(char::is_whitespace) {
+            doc::push_inline(&mut self.para, Inline::Text(" ".into()));
+        }
+    }
+    /// Separates the text of a new line from the previous one.
+    fn space(&mut self) {
+        if !self.para.is_empty()
+            && !matches!(self.para.last(), Some(Inline::Text(t)) if t.ends_with(' '))
+        {
+            doc::push_inline(&mut self.para, Inline::Text(" ".into()));
+        }
+    }
+    fn start_code(&mut self) {
+        self.flush();
+        self.code = Some(String::new());
+    }
+    fn end_code(&mut self) {
+        if let Some(code) = self.code.take() {
+            let code = code.trim_end().to_owned();
+            if !code.is_empty() && matches!(self.section, Section::Description | Section::Examples)
+            {
+                self.doc.examples.push(CodeBlock {
+                    language: "c".into(),
+                    code,
+                });
+            }
+        }
+    }
+    /// Ends the current paragraph, adding it to the docs of the section it is in.
+    fn flush(&mut self) {
+        self.end_code();
+        self.tag = false;
+        let mut para = std::mem::take(&mut self.para);
+        if let Some(Inline::Text(text)) = para.first_mut() {
+            *text = text.trim_start().to_owned();
+        }
+        if let Some(Inline::Text(text)) = para.last_mut() {
+            *text = text.trim_end().trim_end_matches(" —").to_owned();
+        }
+        para.retain(|i| !matches!(i, Inline::Text(t) if t.is_empty()));
+        if para.is_empty() {
+            return;
+        }
+        match self.section {
+            Section::Name => self.name.push(para),
+            Section::Synopsis => self.synopsis.push(para),
+            Section::Description => self.doc.description.push(para),
+            Section::Returns => self.doc.returns.description.push(para),
+            Section::Errors => self.errors.push(para),
+            Section::Notes => self.doc.notes.push(para),
+            Section::Examples | Section::Other => {}
+        }
+    }
+}
+
+/// `mdoc` macros that can be called from the arguments of another one.
+fn is_callable(word: &str) -> bool {
+    matches!(
+        word,
+        "Fn" | "Fa"
+            | "Ft"
+            | "Dv"
+            | "Er"
+            | "Va"
+            | "Xr"
+            | "Em"
+            | "Sy"
+            | "Ar"
+            | "Li"
+            | "Ql"
+            | "Nm"
+            | "No"
+            | "Ns"
+            | "Pq"
+            | "Dq"
+            | "Sq"
+            | "Cm"
+            | "Fl"
+            | "Ev"
+            | "Pa"
+            | "Ic"
+            | "Vt"
+            | "Bq"
+            | "Op"
+            | "Sx"
+    )
+}
+
+/// Returns whether an `mdoc` argument is punctuation, which is written without space before it.
+fn is_punctuation(word: &str) -> bool {
+    matches!(word, "." | "," | ";" | ":" | "?" | "!" | ")" | "]")
+}
+
+/// Splits the arguments of a macro, which can be quoted to hold spaces.
+fn words(args: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = args.trim_start();
+    while !rest.is_empty() {
+        let (word, next) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        if word.starts_with("\\\"") {
+            break;
+        }
+        words.push(word);
+        rest = next.trim_start();
+    }
+    words
+}
+
+/// Reads the escape after a `\`, without the `\`, like `-` or `(em`.
+fn escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut escape = String::new();
+    match chars.next() {
+        Some(c @ ('(' | '*')) => {
+            escape.push(c);
+            match chars.next() {
+                Some('(') => {
+                    escape.push('(');
+                    escape.extend(chars.by_ref().take(2));
+                }
+                Some('[') => {
+                    escape.push('[');
+                    escape.extend(chars.by_ref().take_while(|c| *c != ']'));
+                    escape.push(']');
+                }
+                Some(c) if escape == "(" => {
+                    escape.push(c);
+                    escape.extend(chars.next());
+                }
+                Some(c) => escape.push(c),
+                None => {}
+            }
+        }
+        Some('[') => {
+            escape.push('[');
+            escape.extend(chars.by_ref().take_while(|c| *c != ']'));
+            escape.push(']');
+        }
+        Some('s') => {
+            escape.push('s');
+            while chars
+                .peek()
+                .is_some_and(|c| matches!(c, '+' | '-' | '0'..='9'))
+            {
+                escape.extend(chars.next());
+            }
+        }
+        Some('"') => {
+            escape.push('"');
+            chars.for_each(drop);
+        }
+        Some(c) => escape.push(c),
+        None => {}
+    }
+    escape
+}
+
+/// Replaces the escapes in text with the characters they stand for, and drops those that only
+/// change the layout or the font.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let escape = escape(&mut chars);
+        let name = escape
+            .trim_start_matches(['(', '*', '['])
+            .trim_start_matches(['(', '['])
+            .trim_end_matches(']');
+        let replacement = match escape.chars().next() {
+            Some('(' | '[' | '*') => match name {
+                "em" => "—",
+                "en" => "–",
+                "hy" => "-",
+                "aq" => "'",
+                "dq" => "\"",
+                "lq" => "“",
+                "rq" => "”",
+                "oq" => "‘",
+                "cq" => "’",
+                "bu" => "•",
+                "co" => "©",
+                "rg" | "R" => "®",
+                "tm" | "Tm" => "™",
+                "mu" => "×",
+                "<=" => "≤",
+                ">=" => "≥",
+                "!=" => "≠",
+                "->" => "→",
+                "ha" => "^",
+                "ti" => "~",
+                "rs" => "\\",
+                "sl" => "/",
+                "ga" => "`",
+                _ => "",
+            },
+            Some('-') => "-",
+            Some('e' | '\\') => "\\",
+            Some(' ' | '~' | '0') => " ",
+            Some('.') => ".",
+            Some('\'') => "'",
+            Some('`') => "`",
+            // `\&`, `\c`, `\|`, `\^`, `\s` and comments don't show up in the text
+            _ => "",
+        };
+        out.push_str(replacement);
+    }
+    out
+}
+
+fn plain_text(para: &[Inline]) -> String {
+    para.iter()
+        .map(|inline| match inline {
+            Inline::Text(t)
+            | Inline::Code(t)
+            | Inline::Emphasis(t)
+            | Inline::Strong(t)
+            | Inline::Reference(t) => t.as_str(),
+            _ => " ",
+        })
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns whether bold text looks like the name of a C symbol or constant, like `open()`,
+/// `O_RDONLY` or `EINVAL`, rather than emphasis.
+fn is_symbol(text: &str) -> bool {
+    let name = text.trim_end_matches("()");
+    is_identifier(name)
+        && (name.len() < text.len()
+            || name.contains('_')
+            || name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    fn code(text: &str) -> Inline {
+        Inline::Code(text.into())
+    }
+
+    #[test]
+    fn man_macros() {
+        let source = r#".\" a comment
+.TH FOO_OPEN 3
+.SH NAME
+foo_open, foo_close \- open and close foos
+.SH SYNOPSIS
+.B int foo_open(const char *path);
+.SH DESCRIPTION
+.BR foo_open ()
+opens the foo at
+.IR path ,
+see
+.BR open (2).
+.PP
+The flags are
+.B FOO_READ
+and \fBsome\fP \fIother\fR \f(CWflags\fP\(em all \e optional.
+.TP
+.B FOO_READ
+Open for reading.
+.SH RETURN VALUE
+Returns 0.
+.SH ERRORS
+.TP
+.B EINVAL
+The path is invalid.
+.SH EXAMPLES
+.EX
+foo_open("x");
+.EE
+"#;
+        let (names, page) = parse(source).unwrap();
+        assert_eq!(names, ["foo_open", "foo_close"]);
+        assert_eq!(page.line, 3);
+        assert!(page.functions.contains("foo_open"));
+        assert!(!page.functions.contains("foo_close"));
+        assert_eq!(
+            page.doc.description,
+            [
+                vec![
+                    Inline::Reference("foo_open()".into()),
+                    text(" opens the foo at "),
+                    Inline::Emphasis("path".into()),
+                    text(", see "),
+                    Inline::Reference("open()".into()),
+                    text("."),
+                ],
+                vec![
+                    text("The flags are "),
+                    code("FOO_READ"),
+                    text(" and "),
+                    Inline::Strong("some".into()),
+                    text(" "),
+                    Inline::Emphasis("other".into()),
+                    text(" "),
+                    code("flags"),
+                    text("— all \\ optional."),
+                ],
+                vec![code("FOO_READ"), text(" — Open for reading.")],
+            ]
+        );
+        assert_eq!(
+            page.doc.returns.description,
+            [
+                vec![text("Returns 0.")],
+                vec![code("EINVAL"), text(" — The path is invalid.")],
+            ]
+        );
+        assert_eq!(page.doc.examples[0].code, "foo_open(\"x\");");
+    }
+
+    #[test]
+    fn mdoc_macros() {
+        let source = r#".Dd January 1, 2024
+.Dt FOO_OPEN 3
+.Sh NAME
+.Nm foo_open
+.Nd open a foo
+.Sh SYNOPSIS
+.Ft int
+.Fn foo_open "const char *path"
+.Sh DESCRIPTION
+The
+.Nm
+function opens
+.Fa path ,
+see
+.Xr open 2 .
+.Pp
+Pass
+.Dv FOO_READ
+.Pq or Dv FOO_WRITE .
+.Sh RETURN VALUES
+.Rv -std foo_open
+"#;
+        let (names, page) = parse(source).unwrap();
+        assert_eq!(names, ["foo_open"]);
+        assert!(page.functions.contains("foo_open"));
+        assert_eq!(
+            page.doc.description,
+            [
+                vec![
+                    text("The "),
+                    code("foo_open"),
+                    text(" function opens "),
+                    code("path"),
+                    text(", see "),
+                    Inline::Reference("open()".into()),
+                    text("."),
+                ],
+                vec![
+                    text("Pass "),
+                    code("FOO_READ"),
+                    text(" (or "),
+                    code("FOO_WRITE"),
+                    text(")."),
+                ],
+            ]
+        );
+        assert_eq!(
+            page.doc.returns.description[0][1],
+            Inline::Reference("foo_open()".into())
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            unescape(r#"a\-b \(lq\*(rq \[em] \e \&.\s-1small\s0 \" comment"#),
+            "a-b “” — \\ .small "
+        );
+        assert_eq!(
+            words(r#"one "two three" four \" comment"#),
+            ["one", "two three", "four"]
+        );
+    }
+
+    #[test]
+    fn no_names() {
+        assert!(parse(".SH DESCRIPTION\nText.\n").is_none());
+    }
+}
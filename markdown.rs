@@ -0,0 +1,1251 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    db::{Attribute, Database, Kind, Symbol},
+    doc::{Annotations, CodeBlock, Direction, Inline, Paragraph, Param, Transfer},
+    rust::{ItemPath, Returns, Signature},
+};
+
+#[derive(clap::Args, Debug, Default)]
+#[group(skip)]
+pub struct Options {
+    /// Describe C conventions in terms of the Rust types they usually map to, for example
+    /// nullable pointers as `Option`.
+    #[clap(long)]
+    pub map_types: bool,
+    /// Only keep the docs written for this language, like `english` or `ja`, if a comment has
+    /// them in several languages.
+    #[clap(long)]
+    pub doc_lang: Option<String>,
+    /// Heading level to render `\section` at, with subsections below it.
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pub section_level: u8,
+    /// Normalize the typography of the docs, so that they don't change with the style of the
+    /// C comments: straighten quotes, write dashes in one style and trim trailing whitespace.
+    #[clap(long, value_enum)]
+    pub typography: Option<Typography>,
+    /// Link to the upstream docs of each symbol and of referenced symbols, with `{name}` replaced
+    /// by the C name and `{kind}` by a kind like `func`, `struct`, `enum`, `flags`, `const`,
+    /// `callback` or `type`.
+    #[clap(long)]
+    pub upstream_url_template: Option<String>,
+    /// Link references to C symbols to the public Rust items that have them as alias, if those
+    /// are found in the Rust sources of the same crate. Other references stay code spans.
+    #[clap(long)]
+    pub intra_doc_links: bool,
+    /// Describe the C return value in an Errors section for functions returning a `Result`,
+    /// since it usually only tells if there was an error.
+    #[clap(long)]
+    pub returns_as_errors: bool,
+    /// Keep parameters the Rust function doesn't take, like user data or the length of a
+    /// slice, with a note that they are handled automatically instead of leaving them out.
+    #[clap(long)]
+    pub keep_dropped_params: bool,
+    /// For C functions that return a boolean and have out parameters, which the Rust function
+    /// returns in an `Option` or `Result` instead, describe the out parameters under Returns
+    /// and turn `TRUE` and `FALSE` into `Some` and `None` or `Ok` and `Err`.
+    #[clap(long)]
+    pub rewrite_bool_returns: bool,
+    /// Show the C declaration in a code block at the top of the docs, after the summary line.
+    #[clap(long)]
+    pub include_c_signature: bool,
+    /// Note for types with functions to take and release a reference, like `foo_ref` and
+    /// `foo_unref`, with `{ref}` and `{unref}` replaced by their names. The default says that
+    /// `Clone` and `Drop` call them; an empty note leaves it out.
+    #[clap(long)]
+    pub refcount_note: Option<String>,
+    /// Record where the docs of each symbol were taken from at the end of its docs.
+    #[clap(long, value_enum)]
+    pub provenance: Option<Provenance>,
+    /// How to render formulas.
+    #[clap(long, value_enum, default_value_t)]
+    pub math: MathStyle,
+    /// Language to mark code blocks with that have none or one that rustdoc would run as a
+    /// doctest, like `rust`, since code from the C docs can't be tested. Languages that would
+    /// still be doctests are replaced by `text`.
+    #[clap(long, value_name = "LANG", default_value = "text")]
+    pub code_block_language: String,
+    /// Copy images referenced by the docs into this directory, and link to them there.
+    #[clap(long)]
+    pub asset_dir: Option<PathBuf>,
+    /// URL or path to link to images copied with --asset-dir by, instead of the directory itself.
+    #[clap(long, requires = "asset_dir")]
+    pub asset_url: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MathStyle {
+    /// As code, with display formulas in `text` blocks.
+    #[default]
+    Code,
+    /// Delimited by `$` and `$$`, for rendering with KaTeX.
+    Katex,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Typography {
+    /// Write `--` as an em dash.
+    Unicode,
+    /// Write em dashes as `--` and en dashes as `-`.
+    Ascii,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provenance {
+    /// A visible line with the file name and line number.
+    Footer,
+    /// An HTML comment with the path, line number and a hash of the C comment.
+    Comment,
+}
+
+/// Copies the images referenced by the docs of a symbol into the asset directory, if one is
+/// configured.
+pub fn copy_assets(symbol: &Symbol, options: &Options) -> std::io::Result<()> {
+    let dir = match &options.asset_dir {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    for (_, path) in symbol.doc.images() {
+        if let Some(path) = path {
+            if let Some(name) = path.file_name() {
+                std::fs::create_dir_all(dir)?;
+                std::fs::copy(path, dir.join(name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders the docs of the symbol `name` as `///` doc comment lines, without a trailing newline.
+///
+/// References to symbols in `paths` are linked to those Rust items. `signature` is that of the
+/// Rust function the docs are for, if it is one.
+pub fn render(
+    name: &str,
+    symbol: &Symbol,
+    db: &Database,
+    paths: &HashMap<String, ItemPath>,
+    signature: Option<&Signature>,
+    options: &Options,
+) -> String {
+    doc_comment(&render_markdown(
+        name, symbol, db, paths, signature, options,
+    ))
+}
+
+/// Turns Markdown into `///` doc comment lines.
+fn doc_comment(markdown: &str) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() {
+                "///".to_owned()
+            } else {
+                format!("/// {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Separates hand-written docs from the imported ones below it when merging.
+const MERGE_MARKER: &str = "<!-- imported from the C docs, edit above this line -->";
+
+/// Combines the existing Markdown docs of an item with newly rendered `///` docs. Everything in
+/// `existing` above the marker is kept, and the rendered docs replace what is below it. Headings
+/// of the rendered docs that are also in the kept docs are moved a level down, so they don't
+/// look like a second section of the same name.
+pub fn merge(existing: &str, rendered: &str) -> String {
+    let kept = existing
+        .split_once(MERGE_MARKER)
+        .map_or(existing, |(kept, _)| kept)
+        .trim_end();
+    if kept.is_empty() {
+        return rendered.to_owned();
+    }
+    let taken = headings(kept)
+        .map(|(_, title)| title.to_owned())
+        .collect::<HashSet<_>>();
+    let mut imported = rendered
+        .lines()
+        .map(|line| {
+            let line = line.strip_prefix("///").unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).to_owned()
+        })
+        .collect::<Vec<_>>();
+    let text = imported.join("\n");
+    for (i, title) in headings(&text).collect::<Vec<_>>() {
+        if taken.contains(title) {
+            imported[i].insert(0, '#');
+        }
+    }
+    doc_comment(&format!(
+        "{}\n\n{}\n\n{}",
+        kept,
+        MERGE_MARKER,
+        imported.join("\n")
+    ))
+}
+
+/// Returns the line numbers and titles of the ATX headings in Markdown, outside code blocks.
+fn headings(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut fence: Option<&str> = None;
+    text.lines().enumerate().filter_map(move |(i, line)| {
+        let trimmed = line.trim_start();
+        let run = trimmed.len() - trimmed.trim_start_matches(['`', '~']).len();
+        match fence {
+            Some(open) if trimmed.starts_with(open) => fence = None,
+            Some(_) => {}
+            None if run >= 3 => fence = Some(&trimmed[..run]),
+            None if line.starts_with('#') => {
+                let title = line.trim_start_matches('#');
+                if title.is_empty() || title.starts_with(' ') {
+                    return Some((i, title.trim()));
+                }
+            }
+            None => {}
+        }
+        None
+    })
+}
+
+/// Renders the docs of the symbol `name` as plain Markdown.
+pub fn render_markdown(
+    name: &str,
+    symbol: &Symbol,
+    db: &Database,
+    paths: &HashMap<String, ItemPath>,
+    signature: Option<&Signature>,
+    options: &Options,
+) -> String {
+    let paragraph = |para: &Paragraph| paragraph(para, db, paths, options);
+    let doc = match &options.doc_lang {
+        Some(language) => {
+            let mut doc = symbol.doc.clone();
+            doc.select_language(language);
+            Cow::Owned(doc)
+        }
+        None => Cow::Borrowed(&symbol.doc),
+    };
+    // the error out parameter of a function that returns a `Result` instead, like `GError
+    // **error`, is described under Errors along with what the description says about errors
+    let error_param = doc.params.last().filter(|param| {
+        is_error_param(&param.name)
+            && signature.is_some_and(|s| {
+                s.returns == Returns::Result
+                    && s.params.as_ref().is_some_and(|n| !n.contains(&param.name))
+            })
+    });
+    let mut errors = Vec::new();
+    let mut sections = Vec::new();
+    for (i, para) in doc.description.iter().enumerate() {
+        if error_param.is_some() && i > 0 && mentions_error_domain(para) {
+            errors.push(paragraph(para));
+        } else {
+            sections.push(paragraph(para));
+        }
+    }
+    if let (true, Some(signature)) = (options.include_c_signature, &symbol.signature) {
+        // rustdoc shows the first paragraph in item lists, so it has to stay the summary
+        let block = code_block(
+            &CodeBlock {
+                language: "c".to_owned(),
+                code: signature.clone(),
+            },
+            options,
+        );
+        sections.insert(sections.len().min(1), block);
+    }
+    let mut dropped = dropped_params(&doc.params, signature);
+    // a Rust function can't take `...` unless it is the C function itself
+    let variadic = symbol.signature.as_deref().is_some_and(is_variadic)
+        && signature.is_some_and(|s| !s.variadic);
+    if variadic {
+        for (param, dropped) in doc.params.iter().zip(&mut dropped) {
+            *dropped |= param.name == "...";
+        }
+    }
+    let mut params = doc.params.iter().cloned().zip(dropped).collect::<Vec<_>>();
+    if let Some(error_param) = error_param {
+        params.pop();
+        let description = error_param
+            .description
+            .iter()
+            .filter(|para| !is_error_boilerplate(para))
+            .map(paragraph);
+        errors.splice(0..0, description);
+    }
+    fold_array_lengths(&mut params, signature);
+    let rewrite = match signature.map(|s| s.returns) {
+        Some(Returns::Option) => Some(("Some", "None")),
+        Some(Returns::Result) => Some(("Ok", "Err")),
+        _ => None,
+    }
+    .filter(|_| {
+        options.rewrite_bool_returns && symbol.signature.as_deref().is_some_and(returns_boolean)
+    });
+    // out parameters become the value the Rust function returns
+    let mut outputs = Vec::new();
+    if rewrite.is_some() {
+        let (out, rest) = params.into_iter().partition(|(param, dropped)| {
+            *dropped && param.annotations.direction == Some(Direction::Out)
+        });
+        outputs = out;
+        params = rest;
+        for (param, _) in &mut outputs {
+            param.annotations.direction = None;
+        }
+    }
+    let params = params
+        .iter()
+        .filter(|(param, dropped)| {
+            !*dropped || (options.keep_dropped_params && param.name != "...")
+        })
+        .collect::<Vec<_>>();
+    if !params.is_empty() {
+        sections.push("# Parameters".to_owned());
+        let items = params
+            .into_iter()
+            .map(|(param, dropped)| param_item(param, *dropped, &paragraph, options))
+            .collect::<Vec<_>>();
+        sections.push(items.join("\n"));
+    }
+    let mut returns = doc
+        .returns
+        .description
+        .iter()
+        .map(|para| match rewrite {
+            Some((yes, no)) => paragraph(&rewrite_booleans(para, yes, no)),
+            None => paragraph(para),
+        })
+        .collect::<Vec<_>>();
+    if let Some(first) = returns.first_mut() {
+        for note in annotation_notes(&doc.returns.annotations, true, options) {
+            append_sentence(first, note);
+        }
+    }
+    let mut values = Vec::new();
+    if !outputs.is_empty() {
+        values.push("The returned value holds:".to_owned());
+        let items = outputs
+            .iter()
+            .map(|(param, _)| param_item(param, false, &paragraph, options))
+            .collect::<Vec<_>>();
+        values.push(items.join("\n"));
+    }
+    match signature.map(|s| s.returns) {
+        _ if returns.is_empty() && values.is_empty() => {}
+        // the return value became an out parameter or was dropped, so describing it misleads
+        Some(Returns::Unit) => {}
+        Some(Returns::Result) if options.returns_as_errors => {
+            errors.splice(0..0, returns);
+            if !values.is_empty() {
+                sections.push("# Returns".to_owned());
+                sections.extend(values);
+            }
+        }
+        _ => {
+            sections.push("# Returns".to_owned());
+            sections.extend(returns);
+            sections.extend(values);
+        }
+    }
+    if !errors.is_empty() {
+        sections.push("# Errors".to_owned());
+        sections.extend(errors);
+    }
+    for (title, conditions) in [
+        ("Preconditions", &doc.preconditions),
+        ("Postconditions", &doc.postconditions),
+        ("Invariants", &doc.invariants),
+    ] {
+        if !conditions.is_empty() {
+            sections.push(format!("# {}", title));
+            sections.push(list(conditions.iter().map(paragraph)));
+        }
+    }
+    if !doc.examples.is_empty() {
+        sections.push("# Examples".to_owned());
+        sections.extend(doc.examples.iter().map(|block| code_block(block, options)));
+    }
+    if !doc.thread_safety.is_empty() {
+        sections.push("# Thread safety".to_owned());
+        sections.extend(doc.thread_safety.iter().map(paragraph));
+    }
+    let formats = symbol
+        .attributes
+        .iter()
+        .any(|a| matches!(a, Attribute::Format { .. }));
+    let notes = doc
+        .notes
+        .iter()
+        .map(paragraph)
+        .chain(
+            symbol
+                .attributes
+                .iter()
+                .filter_map(|a| attribute_note(a, options)),
+        )
+        .chain(variadic.then(|| variadic_note(formats)))
+        .collect::<Vec<_>>();
+    if !notes.is_empty() {
+        sections.push("# Notes".to_owned());
+        sections.push(list(notes));
+    }
+    if let Some(url) = upstream_url(name, symbol.kind, db, options) {
+        sections.push(format!("See the [upstream documentation](<{}>).", url));
+    }
+    if let (Some(provenance), Some(location)) = (options.provenance, &symbol.location) {
+        let file = location
+            .file
+            .strip_prefix(std::env::current_dir().unwrap_or_default());
+        let file = file.unwrap_or(&location.file);
+        sections.push(match provenance {
+            Provenance::Footer => {
+                let name = file.file_name().map(Path::new).unwrap_or(file);
+                format!(
+                    "*(from {})*",
+                    code_span(&format!("{}:{}", name.display(), location.line))
+                )
+            }
+            Provenance::Comment => format!(
+                "<!-- from {}:{} hash {:016x} -->",
+                file.display(),
+                location.line,
+                location.hash
+            ),
+        });
+    }
+    let text = sections.join("\n\n");
+    if options.typography.is_some() {
+        text.lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        text
+    }
+}
+
+/// Checks if a C declaration like `gboolean foo_get_bar(Foo *foo, int *bar)` returns a boolean.
+fn returns_boolean(signature: &str) -> bool {
+    let head = match signature.find('(') {
+        Some(end) => &signature[..end],
+        None => return false,
+    };
+    !head.contains('*')
+        && head.split_whitespace().rev().skip(1).any(|word| {
+            matches!(
+                word,
+                "gboolean" | "bool" | "_Bool" | "BOOL" | "Bool" | "jboolean"
+            )
+        })
+}
+
+/// Replaces `TRUE` and `FALSE` in a paragraph about a boolean return value, like "%TRUE if the
+/// key was found", with what the Rust function returns instead.
+fn rewrite_booleans(para: &Paragraph, yes: &str, no: &str) -> Paragraph {
+    para.iter()
+        .map(|inline| match inline {
+            Inline::Code(code) | Inline::Reference(code) => {
+                match code.trim_start_matches('%').to_ascii_lowercase().as_str() {
+                    "true" => Inline::Code(yes.to_owned()),
+                    "false" => Inline::Code(no.to_owned()),
+                    _ => inline.clone(),
+                }
+            }
+            _ => inline.clone(),
+        })
+        .collect()
+}
+
+/// Checks if a parameter is named like an error out parameter, such as `GError **error`.
+fn is_error_param(name: &str) -> bool {
+    matches!(name, "error" | "err" | "errp" | "error_out" | "out_error")
+}
+
+/// Checks for descriptions of an error parameter that only say what it is, like gtk-doc's
+/// "return location for a #GError, or %NULL".
+fn is_error_boilerplate(para: &Paragraph) -> bool {
+    let text = crate::doc::plain_text(para).to_lowercase();
+    text.starts_with("return location for") || text.starts_with("location to store")
+}
+
+/// Checks if a paragraph mentions an error domain or code, like `G_IO_ERROR_NOT_FOUND`.
+fn mentions_error_domain(para: &Paragraph) -> bool {
+    para.iter().any(|inline| match inline {
+        Inline::Code(code) | Inline::Reference(code) => {
+            code.split('_').any(|word| word == "ERROR")
+                && code
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }
+        _ => false,
+    })
+}
+
+/// Folds pointer and length parameters, like `data` with `(array length=len)` and `len`, that
+/// became a single slice of the Rust function into one entry named after the slice. The length is
+/// left out even with `--keep-dropped-params`, its docs are added to the entry.
+fn fold_array_lengths(params: &mut Vec<(Param, bool)>, signature: Option<&Signature>) {
+    let slices = match signature {
+        Some(signature) => &signature.slices,
+        None => return,
+    };
+    // slices no C parameter is named like stand for a renamed pointer
+    let mut renamed = slices
+        .iter()
+        .filter(|slice| !params.iter().any(|(p, _)| &p.name == *slice))
+        .cloned()
+        .collect::<Vec<_>>()
+        .into_iter();
+    let mut folded = Vec::new();
+    for i in 0..params.len() {
+        let length = match &params[i].0.annotations.array_length {
+            Some(length) => length,
+            None => continue,
+        };
+        let j = match params
+            .iter()
+            .position(|(p, dropped)| &p.name == length && *dropped)
+        {
+            Some(j) => j,
+            None => continue,
+        };
+        let name = match &params[i] {
+            (param, _) if slices.contains(&param.name) => param.name.clone(),
+            (_, true) => match renamed.next() {
+                Some(name) => name,
+                None => continue,
+            },
+            _ => continue,
+        };
+        let description = params[j].0.description.clone();
+        let (param, dropped) = &mut params[i];
+        param.name = name;
+        param.annotations.array_length = None;
+        param.description.extend(description);
+        *dropped = false;
+        folded.push(j);
+    }
+    folded.sort_unstable();
+    folded.dedup();
+    for j in folded.into_iter().rev() {
+        params.remove(j);
+    }
+}
+
+/// Renders a parameter as a list item, followed by a nested list of the parameters of its callback.
+fn param_item(
+    param: &Param,
+    dropped: bool,
+    paragraph: &impl Fn(&Paragraph) -> String,
+    options: &Options,
+) -> String {
+    let mut item = format!("* `{}`", param.name);
+    match param.annotations.direction {
+        Some(Direction::In) => item.push_str(" *(in)*"),
+        Some(Direction::Out) => item.push_str(" *(out)*"),
+        Some(Direction::InOut) => item.push_str(" *(in, out)*"),
+        None => {}
+    }
+    if dropped {
+        item.push_str(" *(handled automatically)*");
+    }
+    let mut paras = param.description.iter().map(paragraph);
+    let mut first = paras.next().unwrap_or_default();
+    for note in annotation_notes(&param.annotations, false, options) {
+        append_sentence(&mut first, note);
+    }
+    if !first.is_empty() {
+        item.push_str(" — ");
+        item.push_str(&first);
+    }
+    for para in paras {
+        item.push_str("\n\n");
+        item.push_str(&indent(&para, "  "));
+    }
+    if !param.callback_params.is_empty() {
+        let params = param
+            .callback_params
+            .iter()
+            .map(|p| param_item(p, false, paragraph, options))
+            .collect::<Vec<_>>();
+        item.push_str("\n\n  The callback receives:\n\n");
+        item.push_str(&indent(&params.join("\n"), "  "));
+    }
+    item
+}
+
+/// Returns for each C parameter if the Rust function leaves it out. The first parameter is taken
+/// to be `self` for methods. Nothing is left out if none of the names match, since the Rust
+/// function then probably renamed the parameters.
+fn dropped_params(params: &[Param], signature: Option<&Signature>) -> Vec<bool> {
+    let (names, receiver) = match signature {
+        Some(Signature {
+            params: Some(names),
+            receiver,
+            ..
+        }) => (names, *receiver),
+        _ => return vec![false; params.len()],
+    };
+    let skip = usize::from(receiver);
+    let matched = params
+        .iter()
+        .skip(skip)
+        .filter(|p| names.contains(&p.name))
+        .count();
+    if matched == 0 && !names.is_empty() {
+        return vec![false; params.len()];
+    }
+    params
+        .iter()
+        .enumerate()
+        // variable arguments have no name to match, see `is_variadic`
+        .map(|(i, p)| i >= skip && p.name != "..." && !names.contains(&p.name))
+        .collect()
+}
+
+/// Looks for constructs in rendered docs that rustdoc would warn about: unclosed code blocks,
+/// bare URLs, reference links without a definition and `[foo]` that would be read as an
+/// intra-doc link. Returns a message for each one.
+pub fn check(doc: &str) -> Vec<String> {
+    let lines = doc
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix("///").unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect::<Vec<_>>();
+    let is_definition = |line: &str| {
+        let line = line.trim_start();
+        line.starts_with('[')
+            && line
+                .find("]:")
+                .is_some_and(|end| !line[1..end].contains(['[', ']']))
+    };
+    let definitions = lines
+        .iter()
+        .filter(|line| is_definition(line))
+        .map(|line| {
+            let line = line.trim_start();
+            link_label(&line[1..line.find("]:").unwrap_or(1)])
+        })
+        .collect::<Vec<_>>();
+    let mut warnings = Vec::new();
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut paragraph = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+        let run = fence_char.map_or(0, |c| trimmed.len() - trimmed.trim_start_matches(c).len());
+        if let Some((c, len, _)) = fence {
+            if fence_char == Some(c) && run >= len && trimmed[run..].trim().is_empty() {
+                fence = None;
+            }
+            continue;
+        }
+        if run >= 3 {
+            check_inline(&paragraph.join("\n"), &definitions, &mut warnings);
+            paragraph.clear();
+            fence = fence_char.map(|c| (c, run, number + 1));
+            if is_doctest(&trimmed[run..]) {
+                warnings.push(format!(
+                    "the code block starting on line {} would run as a doctest",
+                    number + 1
+                ));
+            }
+        } else if line.trim().is_empty() {
+            check_inline(&paragraph.join("\n"), &definitions, &mut warnings);
+            paragraph.clear();
+        } else if !is_definition(line) {
+            paragraph.push(*line);
+        }
+    }
+    check_inline(&paragraph.join("\n"), &definitions, &mut warnings);
+    if let Some((_, _, line)) = fence {
+        warnings.push(format!(
+            "the code block starting on line {} is never closed",
+            line
+        ));
+    }
+    warnings
+}
+
+/// Checks the links and URLs in the text of a paragraph.
+fn check_inline(text: &str, definitions: &[String], warnings: &mut Vec<String>) {
+    let chars = text.chars().collect::<Vec<_>>();
+    // code spans and escaped characters are masked, so that only markup is left
+    let mut masked = chars.clone();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' && i + 1 < chars.len() {
+            masked[i..i + 2].fill(' ');
+            i += 2;
+        } else if chars[i] == '`' {
+            let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+            let mut end = None;
+            let mut j = i + run;
+            while j < chars.len() {
+                let other = chars[j..].iter().take_while(|&&c| c == '`').count();
+                if other == run {
+                    end = Some(j + run);
+                    break;
+                }
+                j += other.max(1);
+            }
+            match end {
+                Some(end) => {
+                    masked[i..end].fill(' ');
+                    i = end;
+                }
+                None => i += run,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    let closing = |from: usize, open: char, close: char| {
+        let mut depth = 0;
+        for (j, &c) in masked.iter().enumerate().skip(from) {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+        }
+        None
+    };
+    let text_of = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+    let mut i = 0;
+    while i < masked.len() {
+        match masked[i] {
+            // autolinks and HTML
+            '<' => match masked[i..].iter().position(|&c| c == '>') {
+                Some(end) => i += end + 1,
+                None => i += 1,
+            },
+            '[' => {
+                let end = match closing(i, '[', ']') {
+                    Some(end) => end,
+                    None => {
+                        i += 1;
+                        continue;
+                    }
+                };
+                let label = text_of(i + 1..end);
+                match masked.get(end + 1) {
+                    Some('(') => match closing(end + 1, '(', ')') {
+                        Some(close) => i = close + 1,
+                        None => i = end + 1,
+                    },
+                    Some('[') => {
+                        let close = closing(end + 1, '[', ']').unwrap_or(end + 1);
+                        let reference = text_of(end + 2..close);
+                        let reference = if reference.is_empty() {
+                            label
+                        } else {
+                            reference
+                        };
+                        if !definitions.contains(&link_label(&reference)) {
+                            warnings.push(format!(
+                                "the reference link `[{}]` has no definition",
+                                reference
+                            ));
+                        }
+                        i = close + 1;
+                    }
+                    _ => {
+                        let path = label.trim_matches('`');
+                        let looks_like_path = !path.is_empty()
+                            && path.chars().all(|c| {
+                                c.is_alphanumeric()
+                                    || matches!(c, '_' | ':' | '(' | ')' | '!' | '@')
+                            });
+                        if looks_like_path && !definitions.contains(&link_label(&label)) {
+                            warnings
+                                .push(format!("`[{}]` would be read as an intra-doc link", label));
+                        }
+                        i = end + 1;
+                    }
+                }
+            }
+            'h' if masked[i..].starts_with(&['h', 't', 't', 'p'])
+                && i.checked_sub(1)
+                    .is_none_or(|p| !masked[p].is_alphanumeric()) =>
+            {
+                let url = masked[i..]
+                    .iter()
+                    .take_while(|c| !c.is_whitespace())
+                    .collect::<String>();
+                if url.starts_with("http://") || url.starts_with("https://") {
+                    let url = url.trim_end_matches(['.', ',', ';', ':', ')']);
+                    warnings.push(format!("bare URL `{}`, write it as `<{}>`", url, url));
+                }
+                i += url.chars().count().max(1);
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// Normalizes a link label for comparison, like Markdown does.
+fn link_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn paragraph(
+    para: &Paragraph,
+    db: &Database,
+    paths: &HashMap<String, ItemPath>,
+    options: &Options,
+) -> String {
+    // display formulas become blocks of their own between the text around them
+    let mut blocks = Vec::new();
+    let mut out = String::new();
+    for inline in para {
+        match inline {
+            Inline::Math { tex, display: true } => {
+                blocks.push(std::mem::take(&mut out));
+                blocks.push(match options.math {
+                    MathStyle::Code => code_block(
+                        &CodeBlock {
+                            language: "text".into(),
+                            code: tex.clone(),
+                        },
+                        options,
+                    ),
+                    MathStyle::Katex => format!("$$\n{}\n$$", tex),
+                });
+                continue;
+            }
+            Inline::Math { tex, .. } => match options.math {
+                MathStyle::Code => out.push_str(&code_span(tex)),
+                MathStyle::Katex => out.push_str(&format!("${}$", tex.trim())),
+            },
+            Inline::Text(text) => out.push_str(&prose(text, options)),
+            Inline::Code(code) => out.push_str(&code_span(code)),
+            Inline::Reference(name) => {
+                let symbol = name.trim_end_matches("()");
+                let kind = db.get(symbol).and_then(|s| s.kind);
+                match (
+                    paths.get(db.resolve(symbol)),
+                    upstream_url(symbol, kind, db, options),
+                ) {
+                    (Some(path), _) => {
+                        // raw identifiers keep their `r#` in the link but not in its text, and
+                        // items of other crates are named along with their crate
+                        let item = match path.module.split("::").next() {
+                            Some(krate) if krate != "crate" => {
+                                format!("{}::{}", krate, path.item.replace("r#", ""))
+                            }
+                            _ => path.item.replace("r#", ""),
+                        };
+                        let text = format!("{}{}", item, &name[symbol.len()..]);
+                        out.push_str(&format!(
+                            "[{}]({}::{})",
+                            code_span(&text),
+                            path.module,
+                            path.item
+                        ));
+                    }
+                    (None, Some(url)) => out.push_str(&format!("[{}](<{}>)", code_span(name), url)),
+                    (None, None) => out.push_str(&code_span(name)),
+                }
+            }
+            Inline::Emphasis(text) => {
+                out.push('*');
+                out.push_str(&prose(text, options));
+                out.push('*');
+            }
+            Inline::Strong(text) => {
+                out.push_str("**");
+                out.push_str(&prose(text, options));
+                out.push_str("**");
+            }
+            Inline::Link { url, text } => {
+                out.push_str(&format!("[{}](<{}>)", prose(text, options), url));
+            }
+            Inline::LineBreak => out.push_str("\\\n"),
+            Inline::Heading { level, title, id } => {
+                let level = options
+                    .section_level
+                    .max(1)
+                    .saturating_add(level.saturating_sub(1))
+                    .min(6);
+                out.push_str(&"#".repeat(level as usize));
+                out.push(' ');
+                if let Some(id) = id {
+                    out.push_str(&anchor(id));
+                }
+                out.push_str(&prose(title, options));
+            }
+            Inline::Anchor(id) => out.push_str(&anchor(id)),
+            Inline::Language(_) => {}
+            Inline::Image { src, alt, path } => {
+                let file_name = path.as_deref().and_then(|p| p.file_name());
+                let link = match (&options.asset_dir, file_name) {
+                    (Some(dir), Some(name)) => match &options.asset_url {
+                        Some(url) => {
+                            format!("{}/{}", url.trim_end_matches('/'), name.to_string_lossy())
+                        }
+                        None => dir.join(name).to_string_lossy().into_owned(),
+                    },
+                    _ => src.clone(),
+                };
+                out.push_str(&format!("![{}](<{}>)", prose(alt, options), link));
+            }
+        }
+    }
+    blocks.push(out);
+    blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, block)| {
+            // even entries are text, odd ones formulas
+            if i % 2 == 0 {
+                block
+                    .split("\\\n")
+                    .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .collect::<Vec<_>>()
+                    .join("\\\n")
+            } else {
+                block
+            }
+        })
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn anchor(id: &str) -> String {
+    format!("<a id=\"{}\"></a>", id.replace(['"', '<', '>', '&'], ""))
+}
+
+/// Fills in the upstream URL template for a symbol. Without a known kind, this only works if the
+/// template doesn't need one.
+fn upstream_url(
+    name: &str,
+    kind: Option<Kind>,
+    db: &Database,
+    options: &Options,
+) -> Option<String> {
+    let template = options.upstream_url_template.as_ref()?;
+    // `%NULL` and friends are referenced all the time but have no docs of their own
+    if matches!(name, "NULL" | "TRUE" | "FALSE") {
+        return None;
+    }
+    let mut url = template.replace("{name}", name);
+    if url.contains("{kind}") {
+        let kind = match kind? {
+            Kind::Function => "func",
+            Kind::Struct => "struct",
+            Kind::Union => "union",
+            Kind::Enum
+                if db
+                    .get(name)
+                    .is_some_and(|s| s.attributes.contains(&Attribute::Flags)) =>
+            {
+                "flags"
+            }
+            Kind::Enum => "enum",
+            Kind::Constant => "const",
+            Kind::Callback => "callback",
+            Kind::Type => "type",
+            Kind::Field => "field",
+        };
+        url = url.replace("{kind}", kind);
+    }
+    Some(url)
+}
+
+/// Escapes text that isn't code, after normalizing its typography if enabled.
+fn prose(text: &str, options: &Options) -> String {
+    match options.typography {
+        Some(style) => escape(&typography(text, style)),
+        None => escape(text),
+    }
+}
+
+/// Straightens curly quotes and writes dashes in the given style.
+fn typography(text: &str, style: Typography) -> String {
+    let text = text
+        .replace(['‘', '’', '‚', '‛'], "'")
+        .replace(['“', '”', '„', '‟'], "\"");
+    match style {
+        Typography::Unicode => {
+            // only a free-standing `--`, to keep command line options like `--help` intact
+            let mut out = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            while let Some(i) = rest.find("--") {
+                let before = rest[..i].chars().next_back();
+                let after = rest[i + 2..].chars().next();
+                let free = |c: Option<char>| c.is_none_or(char::is_whitespace);
+                out.push_str(&rest[..i]);
+                if free(before) && free(after) {
+                    out.push('—');
+                    rest = &rest[i + 2..];
+                } else {
+                    let dashes = rest[i..].len() - rest[i..].trim_start_matches('-').len();
+                    out.push_str(&rest[i..i + dashes]);
+                    rest = &rest[i + dashes..];
+                }
+            }
+            out.push_str(rest);
+            out
+        }
+        Typography::Ascii => text.replace('—', "--").replace('–', "-"),
+    }
+}
+
+/// Escapes characters that would otherwise be read as Markdown syntax.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '[' | ']' | '<') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn code_span(code: &str) -> String {
+    // the fence has to be longer than any run of backticks inside the span
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{} {} {}", fence, code, fence)
+    } else {
+        format!("{}{}{}", fence, code, fence)
+    }
+}
+
+fn code_block(block: &CodeBlock, options: &Options) -> String {
+    let fence = "`".repeat(longest_run(&block.code, '`').max(2) + 1);
+    let language = if !is_doctest(&block.language) {
+        block.language.as_str()
+    } else if !is_doctest(&options.code_block_language) {
+        options.code_block_language.as_str()
+    } else {
+        "text"
+    };
+    format!("{}{}\n{}\n{}", fence, language, block.code, fence)
+}
+
+/// Checks if rustdoc would run a code block with this info string as a doctest, which it does
+/// for blocks without a language, in Rust, or with only attributes like `no_run`.
+fn is_doctest(info: &str) -> bool {
+    info.split([',', ' ', '\t'])
+        .filter(|word| !word.is_empty())
+        .all(|word| {
+            matches!(
+                word,
+                "rust" | "no_run" | "should_panic" | "compile_fail" | "test_harness"
+            ) || word.starts_with("edition")
+        })
+}
+
+/// Renders a bullet list, with one item per paragraph.
+fn list(items: impl IntoIterator<Item = String>) -> String {
+    items
+        .into_iter()
+        .map(|item| format!("* {}", indent(&item, "  ").trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for t in text.chars() {
+        run = if t == c { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn append_sentence(text: &mut String, sentence: String) {
+    if !text.is_empty() {
+        if !text.ends_with(['.', '!', '?', ':']) {
+            text.push('.');
+        }
+        text.push(' ');
+    }
+    text.push_str(&sentence);
+}
+
+/// Describes the annotations of a parameter, or of the return value if `returned` is set. The
+/// direction of a parameter is shown next to its name instead.
+fn annotation_notes(annotations: &Annotations, returned: bool, options: &Options) -> Vec<String> {
+    let mut notes = Vec::new();
+    match (annotations.nullable, options.map_types) {
+        (Some(true), false) => notes.push("May be `NULL`.".into()),
+        (Some(false), false) => notes.push("Must not be `NULL`.".into()),
+        (Some(true), true) => notes.push("May be `None`.".into()),
+        // a non-nullable pointer maps to a reference, which can't be null anyway
+        (Some(false), true) | (None, _) => {}
+    }
+    // for out parameters the transfer applies to the value handed back to the caller
+    let to_caller = returned || matches!(annotations.direction, Some(Direction::Out));
+    let transfer = match (annotations.transfer, to_caller) {
+        (Some(Transfer::Full), true) => Some("The caller takes ownership of the value."),
+        (Some(Transfer::Full), false) => Some("The function takes ownership of the value."),
+        (Some(Transfer::Container), true) => {
+            Some("The caller takes ownership of the container, but not of its elements.")
+        }
+        (Some(Transfer::Container), false) => {
+            Some("The function takes ownership of the container, but not of its elements.")
+        }
+        (Some(Transfer::None), true) => {
+            Some("Ownership stays with the function; the value must not be freed.")
+        }
+        (Some(Transfer::None), false) => Some("The caller keeps ownership of the value."),
+        (Some(Transfer::Floating), _) => Some("The reference is floating."),
+        (None, _) => None,
+    };
+    notes.extend(transfer.map(String::from));
+    if let Some(length) = &annotations.array_length {
+        notes.push(format!(
+            "Array with the number of elements given by {}.",
+            code_span(length)
+        ));
+    }
+    notes
+}
+
+/// Checks if a C declaration like `void log(const char *format, ...)` takes variable arguments.
+fn is_variadic(signature: &str) -> bool {
+    signature
+        .trim_end()
+        .trim_end_matches(';')
+        .trim_end()
+        .strip_suffix(')')
+        .is_some_and(|s| s.trim_end().ends_with("..."))
+}
+
+fn variadic_note(formats: bool) -> String {
+    if formats {
+        "The C function takes the values for its format string as variable arguments. Here \
+         they are formatted in Rust instead, for example with `format!`."
+            .into()
+    } else {
+        "The C function takes a variable number of arguments, which are passed differently \
+         here."
+            .into()
+    }
+}
+
+fn attribute_note(attr: &Attribute, options: &Options) -> Option<String> {
+    Some(match attr {
+        Attribute::WarnUnusedResult => "The return value must be used.".into(),
+        Attribute::NoReturn => "This function never returns.".into(),
+        Attribute::Malloc => {
+            "The returned pointer is newly allocated and does not alias any other pointer.".into()
+        }
+        Attribute::Format {
+            archetype,
+            param: Some(param),
+        } => format!("`{}` is a `{}`-style format string.", param, archetype),
+        Attribute::Format {
+            archetype,
+            param: None,
+        } => format!("Takes a `{}`-style format string.", archetype),
+        Attribute::NonNull(params) if params.is_empty() => {
+            "Pointer parameters must not be `NULL`.".into()
+        }
+        Attribute::NonNull(params) => {
+            let params = params
+                .iter()
+                .map(|p| format!("`{}`", p))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Must not be `NULL`: {}.", params)
+        }
+        Attribute::Flags => "The values are bit flags that can be combined with `|`.".into(),
+        Attribute::RefCounted { take, release } => {
+            let note = options.refcount_note.as_deref().unwrap_or(
+                "Values are reference counted with `{ref}` and `{unref}`. `Clone` takes a new \
+                 reference and `Drop` releases it, so they don't need to be called directly.",
+            );
+            if note.is_empty() {
+                return None;
+            }
+            note.replace("{ref}", take).replace("{unref}", release)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_url() {
+        assert_eq!(
+            check("/// See https://example.com/docs."),
+            ["bare URL `https://example.com/docs`, write it as `<https://example.com/docs>`"]
+        );
+        assert!(check("/// See <https://example.com/docs>.").is_empty());
+        assert!(check("/// See [the docs](https://example.com/docs).").is_empty());
+        assert!(check("/// Set `url` to `https://example.com`.").is_empty());
+    }
+
+    #[test]
+    fn unclosed_fence() {
+        assert_eq!(
+            check("/// Example:\n///\n/// ```text\n/// foo();"),
+            ["the code block starting on line 3 is never closed"]
+        );
+        assert!(check("/// ```text\n/// [foo] https://example.com\n/// ```").is_empty());
+        assert!(check("/// ````text\n/// ```\n/// ````").is_empty());
+    }
+
+    #[test]
+    fn reference_links() {
+        let defined = "/// See [the docs][docs].\n///\n/// [docs]: https://example.com";
+        assert!(check(defined).is_empty());
+        assert_eq!(
+            check("/// See [the docs][docs]."),
+            ["the reference link `[docs]` has no definition"]
+        );
+        assert!(check("/// See [Docs][].\n///\n/// [docs]: https://example.com").is_empty());
+    }
+
+    #[test]
+    fn intra_doc_links() {
+        assert_eq!(
+            check("/// Returns [foo]."),
+            ["`[foo]` would be read as an intra-doc link"]
+        );
+        assert!(check("/// Returns [foo].\n///\n/// [foo]: https://example.com").is_empty());
+        assert!(check("/// Indexes with `array[foo]`.").is_empty());
+        assert!(check("/// Matches ``[foo]`` and \\[bar\\].").is_empty());
+        assert!(check("/// Takes a [list of] values.").is_empty());
+    }
+}
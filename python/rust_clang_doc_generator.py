@@ -0,0 +1,129 @@
+"""Python bindings for the extraction database of rust-clang-doc-generator.
+
+They call the C API of the library with ctypes rather than being a PyO3 extension module, so
+that the library built for C works as it is. Structured docs are passed as JSON. Build the
+library with the `capi` feature:
+
+    cargo rustc --lib --features capi --crate-type cdylib
+
+The library is looked up in the `RCDG_LIBRARY` environment variable, then next to this module,
+then by the system's usual search.
+"""
+
+import ctypes
+import ctypes.util
+import json
+import os
+import sys
+
+__all__ = ["Database", "Error", "comment_to_markdown"]
+
+
+class Error(Exception):
+    """An error reported by the library."""
+
+
+def _load():
+    name = {"darwin": "librust_clang_doc_generator.dylib", "win32": "rust_clang_doc_generator.dll"}
+    name = name.get(sys.platform, "librust_clang_doc_generator.so")
+    candidates = [
+        os.environ.get("RCDG_LIBRARY"),
+        os.path.join(os.path.dirname(os.path.abspath(__file__)), name),
+        ctypes.util.find_library("rust_clang_doc_generator"),
+    ]
+    for path in filter(None, candidates):
+        if os.path.exists(path) or not os.path.dirname(path):
+            return ctypes.CDLL(path)
+    raise ImportError("librust_clang_doc_generator not found, set RCDG_LIBRARY to its path")
+
+
+_lib = _load()
+_strings = ctypes.POINTER(ctypes.c_char_p)
+for _name, _args, _ret in [
+    ("rcdg_last_error", [], ctypes.c_char_p),
+    ("rcdg_comment_to_markdown", [ctypes.c_char_p, ctypes.c_char_p, _strings, ctypes.c_size_t], ctypes.c_void_p),
+    ("rcdg_database_load", [ctypes.c_char_p], ctypes.c_void_p),
+    ("rcdg_database_free", [ctypes.c_void_p], None),
+    ("rcdg_database_markdown", [ctypes.c_void_p, ctypes.c_char_p], ctypes.c_void_p),
+    ("rcdg_database_json", [ctypes.c_void_p, ctypes.c_char_p], ctypes.c_void_p),
+    ("rcdg_database_symbols", [ctypes.c_void_p], ctypes.c_void_p),
+    ("rcdg_string_free", [ctypes.c_void_p], None),
+]:
+    _function = getattr(_lib, _name)
+    _function.argtypes = _args
+    _function.restype = _ret
+# only there if the library was built with libclang
+_extract = getattr(_lib, "rcdg_database_extract", None)
+if _extract is not None:
+    _extract.argtypes = [_strings, ctypes.c_size_t]
+    _extract.restype = ctypes.c_void_p
+
+
+def _error():
+    message = _lib.rcdg_last_error()
+    return Error(message.decode() if message else "unknown error")
+
+
+def _take(pointer):
+    """Returns a string returned by the library as `str` and frees it, or None for NULL."""
+    if not pointer:
+        return None
+    try:
+        return ctypes.string_at(pointer).decode()
+    finally:
+        _lib.rcdg_string_free(pointer)
+
+
+def _array(strings):
+    encoded = [s.encode() for s in strings]
+    return (ctypes.c_char_p * len(encoded))(*encoded), len(encoded)
+
+
+def comment_to_markdown(name, comment, args=()):
+    """Converts a C doc comment of the symbol `name` to the Markdown the sync command would
+    write for it, taking `args` as the names of its arguments if it is a function. Returns None
+    for comments that need libclang to be parsed, like Doxygen ones."""
+    array, count = _array(args)
+    return _take(_lib.rcdg_comment_to_markdown(name.encode(), comment.encode(), array, count))
+
+
+class Database:
+    """The docs of C symbols, loaded from a file written by the extract command or extracted
+    from C sources."""
+
+    def __init__(self, pointer):
+        if not pointer:
+            raise _error()
+        self._pointer = pointer
+
+    @classmethod
+    def load(cls, path):
+        return cls(_lib.rcdg_database_load(os.fsencode(path)))
+
+    @classmethod
+    def extract(cls, *args):
+        """Parses C sources like the extract command, which `args` are the options of, like
+        `"--c-srcs=foo.h"` or `"--clang-arg=-DFOO"`."""
+        if _extract is None:
+            raise Error("the library was built without the libclang feature")
+        array, count = _array(args)
+        return cls(_extract(array, count))
+
+    def __del__(self):
+        pointer, self._pointer = getattr(self, "_pointer", None), None
+        if pointer:
+            _lib.rcdg_database_free(pointer)
+
+    def symbols(self):
+        """Returns the names of the symbols with docs, sorted."""
+        return json.loads(_take(_lib.rcdg_database_symbols(self._pointer)))
+
+    def markdown(self, name):
+        """Returns the docs of a symbol or alias as Markdown, or None if it has none."""
+        return _take(_lib.rcdg_database_markdown(self._pointer, name.encode()))
+
+    def ir(self, name):
+        """Returns the structured docs, location, kind and signature of a symbol or alias as
+        parsed JSON, in the format of `extract --format json`, or None if it has none."""
+        text = _take(_lib.rcdg_database_json(self._pointer, name.encode()))
+        return None if text is None else json.loads(text)
@@ -0,0 +1,40 @@
+"""Smoke test of the Python bindings against a database, run by `tests/python.rs` with the library
+in `RCDG_LIBRARY` and a database written from `tests/golden/gtkdoc/db.json` in `RCDG_TEST_DB`.
+"""
+
+import os
+import unittest
+
+import rust_clang_doc_generator as rcdg
+
+
+class DatabaseTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.db = rcdg.Database.load(os.environ["RCDG_TEST_DB"])
+
+    def test_symbols(self):
+        self.assertEqual(self.db.symbols(), ["FooBar", "foo_bar_get_name", "foo_bar_set_name"])
+
+    def test_markdown(self):
+        markdown = self.db.markdown("foo_bar_get_name")
+        self.assertTrue(markdown.startswith("Gets the name of `bar`."), markdown)
+        self.assertIsNone(self.db.markdown("missing"))
+
+    def test_ir(self):
+        ir = self.db.ir("foo_bar_get_name")
+        self.assertEqual(ir["kind"], "Function")
+        self.assertEqual(ir["doc"]["params"][0]["name"], "bar")
+        self.assertIsNone(self.db.ir("missing"))
+
+    def test_load_error(self):
+        with self.assertRaises(rcdg.Error):
+            rcdg.Database.load(os.path.join(os.path.dirname(__file__), "missing.db"))
+
+    def test_comment_to_markdown(self):
+        markdown = rcdg.comment_to_markdown("foo_new", "/**\n * foo_new:\n *\n * Creates a foo.\n */")
+        self.assertEqual(markdown, "Creates a foo.")
+
+
+if __name__ == "__main__":
+    unittest.main()
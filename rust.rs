@@ -0,0 +1,1430 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Read,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::{LineColumn, Span};
+use syn::{ext::IdentExt, punctuated::Punctuated, spanned::Spanned};
+
+/// Returns the string of an attribute like `#[doc = "..."]` or `#[link_name = "..."]`, in any
+/// form of string literal.
+fn string_value(meta: &syn::Meta) -> Option<syn::LitStr> {
+    match meta {
+        syn::Meta::NameValue(syn::MetaNameValue {
+            value:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }),
+            ..
+        }) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Doc comments, written as `///` or as `#[doc = "..."]`. Docs given by a macro, like
+/// `#[doc = include_str!("...")]`, aren't included, so they are left alone.
+struct DocComment;
+
+impl DocComment {
+    fn get(attr: &syn::Attribute) -> Option<syn::LitStr> {
+        if attr.path().is_ident("doc") {
+            string_value(&attr.meta)
+        } else {
+            None
+        }
+    }
+    /// Returns the doc attributes with their text. The span covers the whole attribute, which is
+    /// the comment itself for `///` comments.
+    fn find_all(attrs: &[syn::Attribute]) -> Vec<(Span, syn::LitStr)> {
+        attrs
+            .iter()
+            .filter_map(|a| Some((a.span(), Self::get(a)?)))
+            .collect()
+    }
+}
+
+/// Finds the aliases given by `doc(alias = "a")` or `doc(alias("a", "b"))` attributes, also
+/// inside `cfg_attr`. Aliases are taken literally, so they can contain `::` or anything else.
+struct DocAlias;
+
+impl DocAlias {
+    fn find(attrs: &[syn::Attribute]) -> Option<String> {
+        Self::find_all(attrs).into_iter().next()
+    }
+    /// Returns all aliases of an item. Aliases written like raw identifiers, as in `r#type`, are
+    /// compared without the `r#`, since C symbols can't have it.
+    fn find_all(attrs: &[syn::Attribute]) -> Vec<String> {
+        attrs
+            .iter()
+            .flat_map(|a| Self::find_in_meta(&a.meta))
+            .map(|alias| match alias.strip_prefix("r#") {
+                Some(alias) => alias.to_owned(),
+                None => alias,
+            })
+            .collect()
+    }
+    /// Looks for aliases in `doc(...)`, or in the attributes of `cfg_attr(predicate, attrs...)`,
+    /// which can themselves be `cfg_attr` again.
+    fn find_in_meta(meta: &syn::Meta) -> Vec<String> {
+        let list = match meta {
+            syn::Meta::List(list) => list,
+            _ => return Vec::new(),
+        };
+        let nested =
+            match list.parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) {
+                Ok(nested) => nested,
+                Err(_) => return Vec::new(),
+            };
+        if list.path.is_ident("cfg_attr") {
+            return nested.iter().skip(1).flat_map(Self::find_in_meta).collect();
+        }
+        if !list.path.is_ident("doc") {
+            return Vec::new();
+        }
+        nested
+            .iter()
+            .flat_map(|meta| match meta {
+                syn::Meta::NameValue(nv) if nv.path.is_ident("alias") => match &nv.value {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    }) => vec![s.value()],
+                    _ => Vec::new(),
+                },
+                syn::Meta::List(list) if list.path.is_ident("alias") => list
+                    .parse_args_with(Punctuated::<syn::LitStr, syn::Token![,]>::parse_terminated)
+                    .map(|aliases| aliases.iter().map(syn::LitStr::value).collect())
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the C symbol an item without `doc(alias)` stands for: the name given by `link_name`,
+/// or if `exported`, the name given by `export_name` or its own name if it has `no_mangle`.
+fn symbol_name(attrs: &[syn::Attribute], ident: &syn::Ident, exported: bool) -> Option<String> {
+    attrs.iter().find_map(|a| {
+        // edition 2024 writes these as `#[unsafe(no_mangle)]`
+        let meta = match &a.meta {
+            syn::Meta::List(list) if list.path.is_ident("unsafe") => list.parse_args().ok()?,
+            meta => meta.clone(),
+        };
+        if meta.path().is_ident("link_name") || exported && meta.path().is_ident("export_name") {
+            string_value(&meta).map(|s| s.value())
+        } else if exported && meta.path().is_ident("no_mangle") {
+            Some(ident.unraw().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Flags types declared by a `bitflags!` invocation.
+struct Bitflags(Vec<BitflagsStruct>);
+
+struct BitflagsStruct {
+    span: Span,
+    attrs: Vec<syn::Attribute>,
+    public: bool,
+    ident: syn::Ident,
+    consts: Vec<BitflagsConst>,
+}
+
+struct BitflagsConst {
+    span: Span,
+    attrs: Vec<syn::Attribute>,
+    ident: Option<syn::Ident>,
+}
+
+impl syn::parse::Parse for Bitflags {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut structs = Vec::new();
+        while !input.is_empty() {
+            let span = input.span();
+            let attrs = input.call(syn::Attribute::parse_outer)?;
+            let public = is_public(&input.parse()?);
+            // `impl Foo: u32 { ... }` adds flags to a type declared elsewhere
+            if input.peek(syn::Token![impl]) {
+                input.parse::<syn::Token![impl]>()?;
+            } else {
+                input.parse::<syn::Token![struct]>()?;
+            }
+            let ident = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![:]>()?;
+            input.parse::<syn::Type>()?;
+            let content;
+            syn::braced!(content in input);
+            let mut consts = Vec::new();
+            while !content.is_empty() {
+                let span = content.span();
+                let attrs = content.call(syn::Attribute::parse_outer)?;
+                content.parse::<syn::Token![const]>()?;
+                // `const _ = !0;` retains unknown bits and has no docs
+                let ident = if content.peek(syn::Token![_]) {
+                    content.parse::<syn::Token![_]>()?;
+                    None
+                } else {
+                    Some(content.parse::<syn::Ident>()?)
+                };
+                content.parse::<syn::Token![=]>()?;
+                content.parse::<syn::Expr>()?;
+                content.parse::<syn::Token![;]>()?;
+                consts.push(BitflagsConst { span, attrs, ident });
+            }
+            structs.push(BitflagsStruct {
+                span,
+                attrs,
+                public,
+                ident,
+                consts,
+            });
+        }
+        Ok(Self(structs))
+    }
+}
+
+struct Source<'s> {
+    full: &'s str,
+    lines: Vec<&'s str>,
+}
+
+impl<'s> Source<'s> {
+    fn position(&self, pos: LineColumn) -> Option<usize> {
+        self.lines.get(pos.line - 1).and_then(|l| {
+            let index = l
+                .char_indices()
+                .nth(pos.column)
+                .map(|i| i.0)
+                .unwrap_or_else(|| l.len());
+            Some(l.get(index..index)?.as_ptr() as usize - self.full.as_ptr() as usize)
+        })
+    }
+    fn range_for(&self, span: Span) -> Option<Range<usize>> {
+        Some(self.position(span.start())?..self.position(span.end())?)
+    }
+    /// Returns the whitespace at the start of the line containing byte offset `pos`, up to `pos`.
+    fn indent(&self, pos: usize) -> String {
+        let start = self.full[..pos].rfind('\n').map_or(0, |i| i + 1);
+        self.full[start..pos]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect()
+    }
+}
+
+/// Where the docs of an aliased item are written.
+pub struct DocLocation {
+    /// Line of the item, counted from 1.
+    pub line: usize,
+    /// The `cfg` predicate the item and the items around it are compiled with, if any.
+    pub cfg: Option<String>,
+    /// The whitespace the line of the docs starts with, which continuation lines get too.
+    indent: String,
+    /// Where the docs are inserted: in front of the first attribute that isn't a doc comment,
+    /// or of the item itself, so that the docs come first.
+    pos: usize,
+    /// The existing docs, which are removed when the docs are replaced.
+    existing: Vec<Range<usize>>,
+    /// Set if the existing docs are written as `#[doc = "..."]` attributes, like bindgen does,
+    /// so the new ones are written the same way.
+    attribute: bool,
+}
+
+/// Docs of an item that were left alone because their place in the source overlaps the docs of
+/// another item, like an item visited twice through nested spans.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Overlap {
+    /// Line of the item, counted from 1.
+    pub line: usize,
+    pub alias: String,
+    /// Alias of the item whose docs were replaced instead.
+    pub other: String,
+    /// Line of that item.
+    pub other_line: usize,
+}
+
+/// The path of an aliased item, for linking to it from the docs of other items.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemPath {
+    /// Path of the module, like `crate::window`.
+    pub module: String,
+    /// Path of the item within the module, like `Window::new`.
+    pub item: String,
+}
+
+/// What an aliased Rust function returns, as far as it matters for the Returns section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Returns {
+    /// Nothing, so the C return value was turned into something else.
+    Unit,
+    /// A `Result`, so the C return value probably tells if there was an error.
+    Result,
+    /// An `Option`, so the C return value may tell if there is a value.
+    Option,
+    Other,
+}
+
+/// The parts of the signature of an aliased Rust function that its docs are adapted to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub returns: Returns,
+    /// Names of the parameters after `self`, or `None` if some are patterns instead of names.
+    pub params: Option<Vec<String>>,
+    /// Set if the function takes `self`, which stands for the first C parameter.
+    pub receiver: bool,
+    /// Set if the function takes `...`, which only functions in `extern` blocks can.
+    pub variadic: bool,
+    /// Names of the parameters that are slices or strings, which can stand for a pointer and a
+    /// length in C.
+    pub slices: Vec<String>,
+}
+
+impl Signature {
+    fn of(sig: &syn::Signature) -> Self {
+        let params = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Receiver(_) => None,
+                syn::FnArg::Typed(arg) => Some(match &*arg.pat {
+                    syn::Pat::Ident(pat) => Some(pat.ident.unraw().to_string()),
+                    _ => None,
+                }),
+            })
+            .collect();
+        let slices = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Typed(arg) if is_slice(&arg.ty) => match &*arg.pat {
+                    syn::Pat::Ident(pat) => Some(pat.ident.unraw().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        Self {
+            returns: Returns::of(&sig.output),
+            params,
+            receiver: sig.receiver().is_some(),
+            variadic: sig.variadic.is_some(),
+            slices,
+        }
+    }
+}
+
+/// Checks for types like `&[u8]`, `&mut [T]`, `&str`, `Vec<T>` and `String`, which carry their
+/// length with them.
+fn is_slice(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(_) => true,
+            syn::Type::Path(path) => path.path.is_ident("str"),
+            _ => false,
+        },
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "Vec" || s.ident == "String"),
+        _ => false,
+    }
+}
+
+impl Returns {
+    fn of(output: &syn::ReturnType) -> Self {
+        match output {
+            syn::ReturnType::Default => Self::Unit,
+            syn::ReturnType::Type(_, ty) => match &**ty {
+                syn::Type::Tuple(tuple) if tuple.elems.is_empty() => Self::Unit,
+                syn::Type::Path(path)
+                    if path
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|s| s.ident == "Result") =>
+                {
+                    Self::Result
+                }
+                syn::Type::Path(path)
+                    if path
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|s| s.ident == "Option") =>
+                {
+                    Self::Option
+                }
+                _ => Self::Other,
+            },
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Default)]
+#[group(skip)]
+pub struct Options {
+    /// Match tuple structs wrapping a single raw pointer, like `struct Window(*mut GtkWindow)`,
+    /// by the name of the pointed-to type if they have no alias.
+    #[clap(long)]
+    pub infer_from_inner_type: bool,
+    /// Also match items with `export_name` or `no_mangle` by their symbol name. Set by
+    /// `reverse`, as such items are Rust code exported to C rather than bindings.
+    #[clap(skip)]
+    pub exported_symbols: bool,
+}
+
+struct DocVisitor<'s> {
+    source: Source<'s>,
+    options: &'s Options,
+    doc_locations: BTreeMap<String, Vec<DocLocation>>,
+    docs: BTreeMap<String, String>,
+    /// Path of the current module, if the file is part of a crate.
+    module: Option<String>,
+    /// The type whose members are visited.
+    parent: Option<String>,
+    /// Set while visiting an `impl Trait for Type` block, whose items have no visibility of
+    /// their own and are public along with the trait.
+    trait_impl: bool,
+    item_paths: Vec<(String, ItemPath)>,
+    signatures: Vec<(String, Signature)>,
+    alternatives: Vec<(String, Vec<String>)>,
+    /// `cfg` predicates of the modules and impl blocks around the visited items.
+    cfgs: Vec<String>,
+}
+
+/// Converts a name like `StartOfLine` or `HTTPServer` to `START_OF_LINE` or `HTTP_SERVER`.
+fn screaming_snake_case(ident: &syn::Ident) -> String {
+    let chars = ident.unraw().to_string().chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if boundary {
+            out.push('_');
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
+
+/// Returns the name of the type pointed to if `fields` is a single raw pointer to a named type.
+fn pointee_name(fields: &Punctuated<syn::Field, syn::Token![,]>) -> Option<String> {
+    let field = match fields.len() {
+        1 => fields.first()?,
+        _ => return None,
+    };
+    match &field.ty {
+        syn::Type::Ptr(ptr) => match &*ptr.elem {
+            syn::Type::Path(path) if path.qself.is_none() => {
+                Some(path.path.segments.last()?.ident.unraw().to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the predicates of the `cfg` attributes in `attrs`.
+fn cfgs(attrs: &[syn::Attribute]) -> impl Iterator<Item = String> + '_ {
+    attrs.iter().filter_map(|a| match &a.meta {
+        syn::Meta::List(list) if list.path.is_ident("cfg") => Some(list.tokens.to_string()),
+        _ => None,
+    })
+}
+
+impl<'s> DocVisitor<'s> {
+    fn new(source: &'s str, module: Option<String>, options: &'s Options) -> Self {
+        Self {
+            source: Source {
+                full: source,
+                lines: source.lines().collect(),
+            },
+            options,
+            doc_locations: BTreeMap::new(),
+            docs: BTreeMap::new(),
+            module,
+            parent: None,
+            trait_impl: false,
+            item_paths: Vec::new(),
+            signatures: Vec::new(),
+            alternatives: Vec::new(),
+            cfgs: Vec::new(),
+        }
+    }
+    fn try_replace_docs(&mut self, span: Span, attrs: &[syn::Attribute]) -> Option<String> {
+        self.try_replace_docs_or(span, attrs, None)
+    }
+    /// Like `try_replace_docs`, but uses `fallback` as the alias of items without one.
+    fn try_replace_docs_or(
+        &mut self,
+        span: Span,
+        attrs: &[syn::Attribute],
+        fallback: Option<String>,
+    ) -> Option<String> {
+        let mut aliases = DocAlias::find_all(attrs).into_iter();
+        let alias = aliases.next().or(fallback)?;
+        let others = aliases.collect::<Vec<_>>();
+        if !others.is_empty() {
+            self.alternatives.push((alias.clone(), others));
+        }
+        self.add_location(alias.clone(), span, attrs);
+        Some(alias)
+    }
+    fn add_location(&mut self, alias: String, span: Span, attrs: &[syn::Attribute]) {
+        let docs = DocComment::find_all(attrs);
+        if !docs.is_empty() && !self.docs.contains_key(&alias) {
+            let text = docs
+                .iter()
+                .map(|(_, d)| {
+                    let line = d.value();
+                    line.strip_prefix(' ').unwrap_or(&line).to_owned()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.docs.insert(alias.clone(), text);
+        }
+        let cfgs = self
+            .cfgs
+            .iter()
+            .cloned()
+            .chain(cfgs(attrs))
+            .collect::<Vec<_>>();
+        let cfg = match cfgs.len() {
+            0 => None,
+            1 => cfgs.into_iter().next(),
+            _ => Some(format!("all({})", cfgs.join(", "))),
+        };
+        let line = span.start().line;
+        let mut runs = Vec::<Range<usize>>::new();
+        for range in docs
+            .iter()
+            .filter_map(|(span, _)| self.source.range_for(*span))
+        {
+            // a run of docs with nothing else between them is replaced as a whole
+            match runs.last_mut() {
+                Some(last) if self.source.full[last.end..range.start].trim().is_empty() => {
+                    last.end = range.end
+                }
+                _ => runs.push(range),
+            }
+        }
+        let other = attrs.iter().find(|a| DocComment::get(a).is_none());
+        let pos = match (other, runs.last()) {
+            (Some(attr), _) => self.source.position(attr.span().start()),
+            // the item itself follows the last doc comment
+            (None, Some(last)) => {
+                let rest = &self.source.full[last.end..];
+                Some(last.end + rest.len() - rest.trim_start().len())
+            }
+            (None, None) => self.source.position(span.start()),
+        };
+        let pos = match pos {
+            Some(pos) => pos,
+            None => return,
+        };
+        let location = DocLocation {
+            line,
+            cfg,
+            indent: self.source.indent(pos),
+            pos,
+            attribute: runs
+                .first()
+                .is_some_and(|r| self.source.full[r.clone()].starts_with('#')),
+            existing: runs,
+        };
+        self.doc_locations.entry(alias).or_default().push(location);
+    }
+    /// Records the path of an item, or of a member of the current parent type. Only public
+    /// items are recorded, since links to private ones would break in the public docs.
+    fn add_path(&mut self, alias: String, public: bool, member: bool, ident: &syn::Ident) {
+        let module = match &self.module {
+            Some(module) if public => module.clone(),
+            _ => return,
+        };
+        let item = match (member, &self.parent) {
+            (false, _) => ident.to_string(),
+            (true, Some(parent)) => format!("{}::{}", parent, ident),
+            (true, None) => return,
+        };
+        self.item_paths.push((alias, ItemPath { module, item }));
+    }
+}
+
+fn is_public(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+/// Returns the directory of the crate that a file in its `src` directory belongs to, and the
+/// path of the module the file declares, like `crate::window` for `src/window/mod.rs`.
+fn module_path(path: &Path) -> Option<(PathBuf, String)> {
+    let path = path.canonicalize().ok()?;
+    let components = path.iter().collect::<Vec<_>>();
+    let src = components.iter().rposition(|c| *c == "src")?;
+    let mut module = String::from("crate");
+    for (i, component) in components[src + 1..].iter().enumerate() {
+        let component = Path::new(component);
+        let name = if i + src + 2 == components.len() {
+            match component.file_stem()?.to_str()? {
+                "lib" | "main" | "mod" => continue,
+                stem => stem,
+            }
+        } else {
+            component.to_str()?
+        };
+        module.push_str("::");
+        module.push_str(name);
+    }
+    Some((components[..src].iter().collect(), module))
+}
+
+impl<'ast, 's> syn::visit::Visit<'ast> for DocVisitor<'s> {
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        // only inline modules, the others are files of their own
+        let module = self.module.clone();
+        if let (Some(module), Some(_)) = (&mut self.module, &i.content) {
+            module.push_str("::");
+            module.push_str(&i.ident.to_string());
+        }
+        let len = self.cfgs.len();
+        self.cfgs.extend(cfgs(&i.attrs));
+        syn::visit::visit_item_mod(self, i);
+        self.cfgs.truncate(len);
+        self.module = module;
+    }
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        // impl blocks can't be linked to, so they only get docs
+        self.try_replace_docs(i.span(), &i.attrs);
+        // impls for references, like `impl From<&Foo> for &Bar`, are members of `Bar` too
+        let mut self_ty = &*i.self_ty;
+        while let syn::Type::Reference(syn::TypeReference { elem, .. })
+        | syn::Type::Paren(syn::TypeParen { elem, .. })
+        | syn::Type::Group(syn::TypeGroup { elem, .. }) = self_ty
+        {
+            self_ty = elem;
+        }
+        let parent = match self_ty {
+            syn::Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+        let parent = std::mem::replace(&mut self.parent, parent);
+        let trait_impl = std::mem::replace(&mut self.trait_impl, i.trait_.is_some());
+        let len = self.cfgs.len();
+        self.cfgs.extend(cfgs(&i.attrs));
+        syn::visit::visit_item_impl(self, i);
+        self.cfgs.truncate(len);
+        self.parent = parent;
+        self.trait_impl = trait_impl;
+    }
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        let name = symbol_name(&i.attrs, &i.sig.ident, self.options.exported_symbols);
+        if let Some(alias) = self.try_replace_docs_or(i.span(), &i.attrs, name) {
+            self.signatures.push((alias.clone(), Signature::of(&i.sig)));
+            self.add_path(alias, is_public(&i.vis), false, &i.sig.ident);
+        }
+        syn::visit::visit_item_fn(self, i);
+    }
+    fn visit_item_static(&mut self, i: &'ast syn::ItemStatic) {
+        let name = symbol_name(&i.attrs, &i.ident, self.options.exported_symbols);
+        if let Some(alias) = self.try_replace_docs_or(i.span(), &i.attrs, name) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        syn::visit::visit_item_static(self, i);
+    }
+    fn visit_foreign_item_fn(&mut self, i: &'ast syn::ForeignItemFn) {
+        let name = symbol_name(&i.attrs, &i.sig.ident, false);
+        if let Some(alias) = self.try_replace_docs_or(i.span(), &i.attrs, name) {
+            self.signatures.push((alias.clone(), Signature::of(&i.sig)));
+            self.add_path(alias, is_public(&i.vis), false, &i.sig.ident);
+        }
+        syn::visit::visit_foreign_item_fn(self, i);
+    }
+    fn visit_foreign_item_static(&mut self, i: &'ast syn::ForeignItemStatic) {
+        let name = symbol_name(&i.attrs, &i.ident, false);
+        if let Some(alias) = self.try_replace_docs_or(i.span(), &i.attrs, name) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        syn::visit::visit_foreign_item_static(self, i);
+    }
+    fn visit_trait_item_fn(&mut self, i: &'ast syn::TraitItemFn) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.signatures.push((alias.clone(), Signature::of(&i.sig)));
+            self.add_path(alias, true, true, &i.sig.ident);
+        }
+        syn::visit::visit_trait_item_fn(self, i);
+    }
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.signatures.push((alias.clone(), Signature::of(&i.sig)));
+            let public = self.trait_impl || is_public(&i.vis);
+            self.add_path(alias, public, true, &i.sig.ident);
+        }
+        syn::visit::visit_impl_item_fn(self, i);
+    }
+    fn visit_item_struct(&mut self, i: &'ast syn::ItemStruct) {
+        let parent = self.parent.replace(i.ident.to_string());
+        let inner = match &i.fields {
+            syn::Fields::Unnamed(fields) if self.options.infer_from_inner_type => {
+                pointee_name(&fields.unnamed)
+            }
+            _ => None,
+        };
+        if let Some(alias) = self.try_replace_docs_or(i.span(), &i.attrs, inner) {
+            self.add_path(alias.clone(), is_public(&i.vis), false, &i.ident);
+            // fields without their own alias inherit one from the struct
+            for field in &i.fields {
+                if let (Some(ident), None) = (&field.ident, DocAlias::find(&field.attrs)) {
+                    let alias = format!("{}.{}", alias, ident.unraw());
+                    self.add_location(alias.clone(), field.span(), &field.attrs);
+                    self.add_path(alias, is_public(&field.vis), true, ident);
+                }
+            }
+        }
+        syn::visit::visit_item_struct(self, i);
+        self.parent = parent;
+    }
+    fn visit_field(&mut self, i: &'ast syn::Field) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            if let Some(ident) = &i.ident {
+                self.add_path(alias, is_public(&i.vis), true, ident);
+            }
+        }
+        syn::visit::visit_field(self, i);
+    }
+    fn visit_item_enum(&mut self, i: &'ast syn::ItemEnum) {
+        let parent = self.parent.replace(i.ident.to_string());
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias.clone(), is_public(&i.vis), false, &i.ident);
+            // variants without their own alias are looked up like `FooKind.BAR` for `Bar`,
+            // which matches the constant of the C enum with the common prefix stripped
+            for variant in &i.variants {
+                if DocAlias::find(&variant.attrs).is_none() {
+                    let alias = format!("{}.{}", alias, screaming_snake_case(&variant.ident));
+                    self.add_location(alias.clone(), variant.span(), &variant.attrs);
+                    self.add_path(alias, is_public(&i.vis), true, &variant.ident);
+                }
+            }
+        }
+        syn::visit::visit_item_enum(self, i);
+        self.parent = parent;
+    }
+    fn visit_variant(&mut self, i: &'ast syn::Variant) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, true, true, &i.ident);
+        }
+        syn::visit::visit_variant(self, i);
+    }
+    fn visit_item_type(&mut self, i: &'ast syn::ItemType) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        syn::visit::visit_item_type(self, i);
+    }
+    fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        // items of a trait are as visible as the trait itself
+        let parent = is_public(&i.vis).then(|| i.ident.to_string());
+        let parent = std::mem::replace(&mut self.parent, parent);
+        syn::visit::visit_item_trait(self, i);
+        self.parent = parent;
+    }
+    fn visit_item_const(&mut self, i: &'ast syn::ItemConst) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, is_public(&i.vis), false, &i.ident);
+        }
+        syn::visit::visit_item_const(self, i);
+    }
+    fn visit_impl_item_const(&mut self, i: &'ast syn::ImplItemConst) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            let public = self.trait_impl || is_public(&i.vis);
+            self.add_path(alias, public, true, &i.ident);
+        }
+        syn::visit::visit_impl_item_const(self, i);
+    }
+    fn visit_impl_item_type(&mut self, i: &'ast syn::ImplItemType) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            let public = self.trait_impl || is_public(&i.vis);
+            self.add_path(alias, public, true, &i.ident);
+        }
+        syn::visit::visit_impl_item_type(self, i);
+    }
+    fn visit_trait_item_type(&mut self, i: &'ast syn::TraitItemType) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, true, true, &i.ident);
+        }
+        syn::visit::visit_trait_item_type(self, i);
+    }
+    fn visit_trait_item_const(&mut self, i: &'ast syn::TraitItemConst) {
+        if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+            self.add_path(alias, true, true, &i.ident);
+        }
+        syn::visit::visit_trait_item_const(self, i);
+    }
+    fn visit_item_macro(&mut self, i: &'ast syn::ItemMacro) {
+        // a macro wrapping a C function takes its arguments however it likes
+        if i.mac.path.is_ident("macro_rules") {
+            if let Some(alias) = self.try_replace_docs(i.span(), &i.attrs) {
+                let signature = Signature {
+                    returns: Returns::Other,
+                    params: None,
+                    receiver: false,
+                    variadic: false,
+                    slices: Vec::new(),
+                };
+                self.signatures.push((alias, signature));
+            }
+        }
+        syn::visit::visit_item_macro(self, i);
+    }
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        let is_bitflags = i
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "bitflags");
+        if let Some(Bitflags(structs)) = is_bitflags
+            .then(|| i.parse_body::<Bitflags>().ok())
+            .flatten()
+        {
+            for flags in structs {
+                let alias = self.try_replace_docs(flags.span, &flags.attrs);
+                if let Some(alias) = &alias {
+                    self.add_path(alias.clone(), flags.public, false, &flags.ident);
+                }
+                let parent = self.parent.replace(flags.ident.to_string());
+                // constants without their own alias are looked up like `FooFlags.BAR`
+                for c in flags.consts {
+                    let alias = match (self.try_replace_docs(c.span, &c.attrs), &alias, &c.ident) {
+                        (Some(alias), _, _) => alias,
+                        (None, Some(alias), Some(ident)) => {
+                            let alias = format!("{}.{}", alias, ident.unraw());
+                            self.add_location(alias.clone(), c.span, &c.attrs);
+                            alias
+                        }
+                        _ => continue,
+                    };
+                    if let Some(ident) = &c.ident {
+                        self.add_path(alias, flags.public, true, ident);
+                    }
+                }
+                self.parent = parent;
+            }
+        }
+        syn::visit::visit_macro(self, i);
+    }
+}
+
+/// Collects the macro invocations in item position, with their attributes and arguments.
+struct MacroVisitor(Vec<(Span, Vec<syn::Attribute>, proc_macro2::TokenStream)>);
+
+impl<'ast> syn::visit::Visit<'ast> for MacroVisitor {
+    fn visit_item_macro(&mut self, i: &'ast syn::ItemMacro) {
+        // definitions only mention their own metavariables
+        if i.mac.path.is_ident("macro_rules") {
+            return;
+        }
+        self.0
+            .push((i.span(), i.attrs.clone(), i.mac.tokens.clone()));
+    }
+    fn visit_impl_item_macro(&mut self, i: &'ast syn::ImplItemMacro) {
+        self.0
+            .push((i.span(), i.attrs.clone(), i.mac.tokens.clone()));
+    }
+}
+
+/// Checks if a token stream contains `name` as an identifier or a string literal.
+fn mentions(tokens: proc_macro2::TokenStream, name: &str) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Group(group) => mentions(group.stream(), name),
+        proc_macro2::TokenTree::Ident(ident) => ident == name,
+        proc_macro2::TokenTree::Literal(literal) => {
+            syn::parse2::<syn::LitStr>(proc_macro2::TokenTree::Literal(literal).into())
+                .is_ok_and(|s| s.value() == name)
+        }
+        proc_macro2::TokenTree::Punct(_) => false,
+    })
+}
+
+/// Where a Rust source uses syntax the parser doesn't know, so that the items at the top level
+/// were parsed one at a time, leaving out those that failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialParse {
+    pub path: PathBuf,
+    /// Line of the error, counted from 1.
+    pub line: usize,
+    /// Column of the error, counted from 1.
+    pub column: usize,
+    pub error: String,
+}
+
+/// Parses Rust source. If it uses syntax the parser doesn't know, this falls back to parsing the
+/// items at the top level one at a time, leaving out those that fail, and returns where the
+/// error was. Only fails if the source can't even be split into tokens.
+fn parse_file(
+    source: &str,
+    path: &Path,
+) -> Result<(syn::File, Option<PartialParse>), proc_macro2::LexError> {
+    let error = match syn::parse_file(source) {
+        Ok(file) => return Ok((file, None)),
+        Err(e) => e,
+    };
+    let tokens = source.parse::<proc_macro2::TokenStream>()?;
+    let start = error.span().start();
+    let partial = PartialParse {
+        path: path.to_owned(),
+        line: start.line,
+        column: start.column + 1,
+        error: error.to_string(),
+    };
+    // items end with either a `;` or a block, but a block after `=` can be a struct literal in
+    // the value of a `const` or `static`, which goes on to the `;`
+    let mut items = Vec::new();
+    let mut item = Vec::new();
+    let mut assigned = false;
+    for token in tokens {
+        let (semicolon, block) = match &token {
+            proc_macro2::TokenTree::Punct(p) => {
+                assigned |= p.as_char() == '=';
+                (p.as_char() == ';', false)
+            }
+            proc_macro2::TokenTree::Group(g) => {
+                (false, g.delimiter() == proc_macro2::Delimiter::Brace)
+            }
+            _ => (false, false),
+        };
+        item.push(token);
+        if semicolon || block {
+            match syn::parse2(item.iter().cloned().collect()) {
+                Ok(parsed) => items.push(parsed),
+                Err(_) if block && assigned => continue,
+                Err(_) => {}
+            }
+            item.clear();
+            assigned = false;
+        }
+    }
+    let file = syn::File {
+        shebang: None,
+        frontmatter: None,
+        attrs: Vec::new(),
+        items,
+    };
+    Ok((file, Some(partial)))
+}
+
+pub struct RustFile {
+    pub path: PathBuf,
+    pub source: String,
+    pub doc_locations: BTreeMap<String, Vec<DocLocation>>,
+    /// The existing docs of aliased items, by alias, as Markdown.
+    pub docs: BTreeMap<String, String>,
+    /// Directory of the crate the file belongs to, if it is in a `src` directory.
+    pub crate_dir: Option<PathBuf>,
+    /// Paths of the public aliased items, by alias.
+    pub item_paths: Vec<(String, ItemPath)>,
+    /// Signatures of the aliased functions, by alias.
+    pub signatures: Vec<(String, Signature)>,
+    /// Further aliases of items with several, by the first one, which the docs are stored under.
+    pub alternatives: Vec<(String, Vec<String>)>,
+    /// Where the file, or the expanded code its aliases were found in, couldn't be parsed fully.
+    pub partial_parses: Vec<PartialParse>,
+}
+
+impl RustFile {
+    pub fn parse(path: PathBuf, options: &Options) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = std::fs::File::open(&path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+        Self::from_source(path, source, options)
+    }
+    /// Like `parse`, but with the source already read from `path`.
+    pub fn from_source(
+        path: PathBuf,
+        source: String,
+        options: &Options,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (crate_dir, module) = module_path(&path).unzip();
+        if !has_candidates(&source, options) {
+            return Ok(Self {
+                path,
+                source,
+                doc_locations: BTreeMap::new(),
+                docs: BTreeMap::new(),
+                crate_dir,
+                item_paths: Vec::new(),
+                signatures: Vec::new(),
+                alternatives: Vec::new(),
+                partial_parses: Vec::new(),
+            });
+        }
+        let (ast, partial) = parse_file(&source, &path)?;
+        let (doc_locations, docs, item_paths, signatures, alternatives) = {
+            let mut visitor = DocVisitor::new(&source, module, options);
+            syn::visit::Visit::visit_file(&mut visitor, &ast);
+            (
+                visitor.doc_locations,
+                visitor.docs,
+                visitor.item_paths,
+                visitor.signatures,
+                visitor.alternatives,
+            )
+        };
+        Ok(Self {
+            path,
+            source,
+            doc_locations,
+            docs,
+            crate_dir,
+            item_paths,
+            signatures,
+            alternatives,
+            partial_parses: partial.into_iter().collect(),
+        })
+    }
+    /// Finds aliased items that only exist after macro expansion in `expanded`, like the output
+    /// of `cargo expand`. Their docs go in front of the macro invocation in this file that
+    /// mentions the alias, as an identifier or a string. Aliases in `known` are left alone, since
+    /// they were already found elsewhere. Returns the aliases that were added.
+    pub fn add_expanded(
+        &mut self,
+        expanded: &str,
+        known: &HashSet<String>,
+        options: &Options,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let (expanded_ast, partial) = parse_file(expanded, Path::new("expanded code"))?;
+        self.partial_parses.extend(partial);
+        let mut aliases = {
+            let mut visitor = DocVisitor::new(expanded, None, options);
+            syn::visit::Visit::visit_file(&mut visitor, &expanded_ast);
+            visitor.doc_locations.into_keys().collect::<Vec<_>>()
+        };
+        aliases.retain(|alias| !known.contains(alias) && !self.doc_locations.contains_key(alias));
+        aliases.sort_unstable();
+        // a partial parse of the file itself was already recorded
+        let (ast, _) = parse_file(&self.source, &self.path)?;
+        let mut invocations = MacroVisitor(Vec::new());
+        syn::visit::Visit::visit_file(&mut invocations, &ast);
+        let (doc_locations, docs) = {
+            let mut visitor = DocVisitor::new(&self.source, None, options);
+            for alias in aliases {
+                let invocation = invocations
+                    .0
+                    .iter()
+                    .find(|(_, _, tokens)| mentions(tokens.clone(), &alias));
+                if let Some((span, attrs, _)) = invocation {
+                    visitor.add_location(alias, *span, attrs);
+                }
+            }
+            (visitor.doc_locations, visitor.docs)
+        };
+        let added = doc_locations.keys().cloned().collect();
+        self.doc_locations.extend(doc_locations);
+        for (alias, doc) in docs {
+            self.docs.entry(alias).or_insert(doc);
+        }
+        Ok(added)
+    }
+    /// Replaces the docs of every aliased item that has a non-empty entry in `docs`. Docs of
+    /// aliases in `features` are only included with that cargo feature. If `prune` is given,
+    /// existing docs of aliases missing from `docs` are replaced with it, or removed if it is
+    /// empty. Items whose docs overlap those of an earlier item are left alone and added to
+    /// `overlaps`. Returns `true` if anything was replaced.
+    pub fn replace_docs(
+        &mut self,
+        docs: &HashMap<String, String>,
+        features: &HashMap<String, String>,
+        prune: Option<&str>,
+        overlaps: &mut Vec<Overlap>,
+    ) -> bool {
+        let mut replacements = Vec::new();
+        let mut items = Vec::new();
+        for (ident, locations) in &self.doc_locations {
+            let doc = match docs.get(ident) {
+                Some(doc) if !doc.is_empty() => doc.as_str(),
+                Some(_) => continue,
+                None => match prune {
+                    Some(note) => note,
+                    None => continue,
+                },
+            };
+            for location in locations {
+                let pruned = !docs.contains_key(ident);
+                if pruned && location.existing.is_empty() {
+                    continue;
+                }
+                let item = items.len();
+                items.push((ident, location.line));
+                for range in &location.existing {
+                    replacements.push((Cow::Borrowed(""), removal(&self.source, range), item));
+                }
+                if doc.is_empty() {
+                    continue;
+                }
+                let indent = &location.indent;
+                let feature = features.get(ident).filter(|_| !pruned);
+                let doc = if location.attribute || feature.is_some() {
+                    Cow::Owned(doc_attributes(doc, feature.map(String::as_str)))
+                } else {
+                    Cow::Borrowed(doc)
+                };
+                let mut doc = doc
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let mut line = line.to_owned();
+                        if i > 0 {
+                            line.insert_str(0, indent);
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                // the attribute or item the docs are inserted in front of stays on its own line
+                doc.push('\n');
+                doc.push_str(indent);
+                replacements.push((doc.into(), location.pos..location.pos, item));
+            }
+        }
+        // insertions come before removals starting at the same place, so that they don't overlap
+        replacements.sort_by_key(|(_, range, _)| (range.start, range.end));
+        // an edit overlapping one of another item would garble both, so all the edits of the
+        // later item are left out
+        let mut skipped = HashSet::new();
+        let mut last: Option<(usize, usize)> = None;
+        for (_, range, item) in &replacements {
+            match last {
+                Some((end, other)) if range.start < end && other != *item => {
+                    if skipped.insert(*item) {
+                        let (alias, line) = items[*item];
+                        let (other, other_line) = items[other];
+                        overlaps.push(Overlap {
+                            line,
+                            alias: alias.clone(),
+                            other: other.clone(),
+                            other_line,
+                        });
+                    }
+                }
+                _ if skipped.contains(item) => {}
+                Some((end, _)) if range.end <= end => {}
+                _ => last = Some((range.end, *item)),
+            }
+        }
+        replacements.retain(|(_, _, item)| !skipped.contains(item));
+        let changed = !replacements.is_empty();
+        // as a last resort, an edit overlapping an earlier one of the same item is dropped
+        let mut end = 0;
+        replacements.retain(|(_, range, _)| {
+            let keep = range.start >= end;
+            end = end.max(range.end);
+            keep
+        });
+        // the new source is stitched together in one pass, since editing in place would copy the
+        // rest of a large file for every edit
+        let mut source = String::with_capacity(self.source.len());
+        let mut last = 0;
+        for (doc, range, _) in replacements {
+            source.push_str(&self.source[last..range.start]);
+            source.push_str(&doc);
+            last = range.end;
+        }
+        source.push_str(&self.source[last..]);
+        self.source = source;
+        changed
+    }
+}
+
+/// Makes items with several aliases get the docs of the first alias that has any, by pointing
+/// the alias their docs are stored under at it in `db` if that one has none.
+pub fn resolve_alternatives<'a>(
+    files: impl IntoIterator<Item = &'a RustFile>,
+    db: &mut crate::db::Database,
+) {
+    for (alias, others) in files.into_iter().flat_map(|f| &f.alternatives) {
+        if db.get(alias).is_none() {
+            if let Some(other) = others.iter().find(|other| db.get(other).is_some()) {
+                db.aliases.insert(alias.clone(), other.clone());
+            }
+        }
+    }
+}
+
+/// Returns the signature of the functions with each alias, or `None` for aliases shared by
+/// functions whose signatures differ, since their docs can only be adapted if they agree.
+pub fn shared_signatures<'a>(
+    files: impl IntoIterator<Item = &'a RustFile>,
+) -> HashMap<&'a str, Option<&'a Signature>> {
+    let mut signatures = HashMap::new();
+    for (alias, signature) in files.into_iter().flat_map(|f| &f.signatures) {
+        signatures
+            .entry(alias.as_str())
+            .and_modify(|s: &mut Option<_>| {
+                if *s != Some(signature) {
+                    *s = None;
+                }
+            })
+            .or_insert(Some(signature));
+    }
+    signatures
+}
+
+/// Checks cheaply if a source can have any items that docs are taken for, so that parsing it can
+/// be skipped otherwise: items with `doc(alias)` or `link_name`, items with `no_mangle` or
+/// `export_name` in `reverse` and, with --infer-from-inner-type, structs.
+fn has_candidates(source: &str, options: &Options) -> bool {
+    ["alias", "link_name"].iter().any(|s| source.contains(s))
+        || (options.exported_symbols
+            && ["no_mangle", "export_name"]
+                .iter()
+                .any(|s| source.contains(s)))
+        || (options.infer_from_inner_type && source.contains("struct"))
+}
+
+/// Turns `///` doc comment lines into `#[doc = "..."]` attributes, one per line, or into
+/// `#[cfg_attr(feature = "...", doc = "...")]` if they are only included with `feature`.
+fn doc_attributes(doc: &str, feature: Option<&str>) -> String {
+    doc.lines()
+        .map(|line| {
+            let text = line.strip_prefix("///").unwrap_or(line);
+            match feature {
+                Some(feature) => format!("#[cfg_attr(feature = {:?}, doc = {:?})]", feature, text),
+                None => format!("#[doc = {:?}]", text),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns what to remove to delete the existing docs at `range`: the whole lines if nothing else
+/// is on them, along with any blank lines after them that would be left between the new docs and
+/// the item, or else the docs and the spaces after them.
+fn removal(source: &str, range: &Range<usize>) -> Range<usize> {
+    let mut lines = whole_lines(source, range);
+    if lines == *range {
+        let rest = &source[range.end..];
+        return range.start..range.end + rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+    while let Some(next) = source[lines.end..].find('\n') {
+        if !source[lines.end..lines.end + next].trim().is_empty() {
+            break;
+        }
+        lines.end += next + 1;
+    }
+    lines
+}
+
+/// Widens `range` to the whole lines it is on, including the line break, if nothing else is on
+/// them.
+fn whole_lines(source: &str, range: &Range<usize>) -> Range<usize> {
+    let start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[range.end..]
+        .find('\n')
+        .map_or(source.len(), |i| range.end + i + 1);
+    if source[start..range.start].trim().is_empty() && source[range.end..end].trim().is_empty() {
+        start..end
+    } else {
+        range.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `doc` above the item aliased `c_name` in `source`, like sync does.
+    fn sync(source: &str, doc: &str) -> String {
+        let options = Options::default();
+        let mut file = RustFile::from_source("lib.rs".into(), source.to_owned(), &options).unwrap();
+        let docs = HashMap::from([("c_name".to_owned(), doc.to_owned())]);
+        assert!(file.replace_docs(&docs, &HashMap::new(), None, &mut Vec::new()));
+        file.source
+    }
+
+    #[test]
+    fn generic_fn() {
+        let source = r#"#[doc(alias = "c_name")]
+pub fn new<T: Into<u32>>(value: T) -> Self {}
+"#;
+        let expected = r#"/// Creates one.
+#[doc(alias = "c_name")]
+pub fn new<T: Into<u32>>(value: T) -> Self {}
+"#;
+        assert_eq!(sync(source, "/// Creates one."), expected);
+    }
+
+    #[test]
+    fn const_fn() {
+        let source = r#"impl Foo {
+    /// Old docs.
+    #[doc(alias = "c_name")]
+    pub const fn get(&self) -> u32 {
+        0
+    }
+}
+"#;
+        let expected = r#"impl Foo {
+    /// Gets it.
+    ///
+    /// More.
+    #[doc(alias = "c_name")]
+    pub const fn get(&self) -> u32 {
+        0
+    }
+}
+"#;
+        assert_eq!(sync(source, "/// Gets it.\n///\n/// More."), expected);
+    }
+
+    #[test]
+    fn async_fn() {
+        let source = r#"impl Foo {
+    #[doc(alias = "c_name")]
+    pub async fn read(&self) {}
+}
+"#;
+        let expected = r#"impl Foo {
+    /// Reads it.
+    #[doc(alias = "c_name")]
+    pub async fn read(&self) {}
+}
+"#;
+        assert_eq!(sync(source, "/// Reads it."), expected);
+    }
+
+    #[test]
+    fn trait_impl_fn() {
+        let source = r#"impl std::fmt::Display for Foo {
+    #[doc(alias = "c_name")]
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        Ok(())
+    }
+}
+"#;
+        let expected = r#"impl std::fmt::Display for Foo {
+    /// Formats it.
+    #[doc(alias = "c_name")]
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        Ok(())
+    }
+}
+"#;
+        assert_eq!(sync(source, "/// Formats it."), expected);
+    }
+
+    #[test]
+    fn extern_fn() {
+        let source = r#"extern "C" {
+    #[link_name = "c_name"]
+    pub fn convert(x: i32) -> i32;
+}
+"#;
+        let expected = r#"extern "C" {
+    /// Converts it.
+    #[link_name = "c_name"]
+    pub fn convert(x: i32) -> i32;
+}
+"#;
+        assert_eq!(sync(source, "/// Converts it."), expected);
+    }
+
+    #[test]
+    fn partial_parse() {
+        let source = r#"#[doc(alias = "c_flags")]
+pub const FLAGS: Flags = Flags { bits: 1 };
+
+#[doc(alias = "c_static")]
+pub static STATIC: Wrapper = Wrapper { inner: Flags { bits: 2 } };
+
+impl Foo {
+    pub fn broken(&self) -> u32 { 1 +* }
+}
+
+#[doc(alias = "c_iter")]
+pub fn iter<I: Iterator<Item = u8>>(i: I) {}
+"#;
+        let file =
+            RustFile::from_source("lib.rs".into(), source.to_owned(), &Options::default()).unwrap();
+        assert_eq!(
+            file.doc_locations.keys().collect::<Vec<_>>(),
+            ["c_flags", "c_iter", "c_static"]
+        );
+        let partial = &file.partial_parses[0];
+        assert_eq!((partial.line, partial.column), (8, 40));
+    }
+
+    /// Returns the aliases found in `source` with their existing docs.
+    fn aliases(source: &str, options: &Options) -> BTreeMap<String, String> {
+        let file = RustFile::from_source("lib.rs".into(), source.to_owned(), options).unwrap();
+        file.doc_locations
+            .keys()
+            .map(|alias| {
+                (
+                    alias.clone(),
+                    file.docs.get(alias).cloned().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    const SYMBOLS: &str = r#"extern "C" {
+    pub fn c_name(x: i32) -> i32;
+    #[link_name = "c_linked"]
+    pub fn linked(x: i32) -> i32;
+    #[link_name = "c_static"]
+    pub static STATIC: i32;
+}
+
+/// Exported.
+#[no_mangle]
+pub extern "C" fn c_exported() {}
+
+/// Renamed.
+#[unsafe(export_name = "c_renamed")]
+pub extern "C" fn renamed() {}
+
+/// Other.
+#[unsafe(no_mangle)]
+pub static C_OTHER: i32 = 0;
+"#;
+
+    #[test]
+    fn sync_symbols() {
+        let found = aliases(SYMBOLS, &Options::default());
+        assert_eq!(found.keys().collect::<Vec<_>>(), ["c_linked", "c_static"]);
+    }
+
+    #[test]
+    fn reverse_symbols() {
+        let options = Options {
+            exported_symbols: true,
+            ..Options::default()
+        };
+        let found = aliases(SYMBOLS, &options);
+        assert_eq!(
+            found.into_iter().collect::<Vec<_>>(),
+            [
+                ("C_OTHER".to_owned(), "Other.".to_owned()),
+                ("c_exported".to_owned(), "Exported.".to_owned()),
+                ("c_linked".to_owned(), String::new()),
+                ("c_renamed".to_owned(), "Renamed.".to_owned()),
+                ("c_static".to_owned(), String::new()),
+            ]
+        );
+    }
+}
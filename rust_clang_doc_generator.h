@@ -0,0 +1,54 @@
+/* Functions of rust-clang-doc-generator built with the `capi` feature. Strings are UTF-8 and
+ * NUL-terminated, and those returned are owned by the caller, who frees them with
+ * rcdg_string_free(). Failures return NULL. */
+
+#ifndef RUST_CLANG_DOC_GENERATOR_H
+#define RUST_CLANG_DOC_GENERATOR_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RcdgDatabase RcdgDatabase;
+
+/* Converts a C doc comment in the gtk-doc, HeaderDoc or structured format to the Markdown the
+ * sync command would write for the symbol `name`. `args` are the `n_args` names of its
+ * arguments, if it is a function, and may be NULL. Returns NULL for other formats. */
+char *rcdg_comment_to_markdown(const char *name, const char *comment, const char *const *args,
+                               size_t n_args);
+
+/* Returns the message of the last error of a function that returned NULL on this thread, or NULL
+ * if there was none. It is owned by the library and valid until the next call. */
+const char *rcdg_last_error(void);
+
+/* Loads a database written by the extract command. Returns NULL if it can't be read. */
+RcdgDatabase *rcdg_database_load(const char *path);
+
+/* Extracts the docs of every documented symbol like the extract command, which `args` are the
+ * `n_args` options of, like "--c-srcs=foo.h". Only available if the library is built with the
+ * `libclang` feature. */
+RcdgDatabase *rcdg_database_extract(const char *const *args, size_t n_args);
+
+/* Frees a database returned by rcdg_database_load() or rcdg_database_extract(). */
+void rcdg_database_free(RcdgDatabase *db);
+
+/* Returns the docs of a symbol or alias in `db` as Markdown, or NULL if it has none. */
+char *rcdg_database_markdown(const RcdgDatabase *db, const char *name);
+
+/* Returns the structured docs, location, kind and signature of a symbol or alias in `db` as JSON,
+ * in the format of `extract --format json`, or NULL if it has none. */
+char *rcdg_database_json(const RcdgDatabase *db, const char *name);
+
+/* Returns the names of the symbols in `db` as a JSON array. */
+char *rcdg_database_symbols(const RcdgDatabase *db);
+
+/* Frees a string returned by one of these functions. */
+void rcdg_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,260 @@
+use crate::{
+    doc::{Annotations, CodeBlock, Direction, Doc, Inline, Paragraph, Param},
+    gtkdoc,
+};
+
+/// What a section of the comment documents.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Description,
+    Arguments,
+    Returns,
+    Notes,
+    Examples,
+    Ignored,
+}
+
+/// Returns the section started by a label like `Purpose` or `Arguments:`, if it is one.
+fn section(label: &str) -> Option<Section> {
+    Some(match label.to_ascii_lowercase().as_str() {
+        "purpose" | "description" | "details" | "method" => Section::Description,
+        "arguments" | "parameters" | "params" | "inputs" | "outputs" => Section::Arguments,
+        "return" | "returns" | "return value" | "return values" | "result" => Section::Returns,
+        "note" | "notes" | "remarks" | "further details" | "application notes" => Section::Notes,
+        "example" | "examples" => Section::Examples,
+        "definition" | "author" | "authors" | "references" | "see also" | "history" => {
+            Section::Ignored
+        }
+        _ => return None,
+    })
+}
+
+/// Returns `true` for lines like `=======` that underline a label or separate parts.
+fn is_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.chars().all(|c| c == '=' || c == '-')
+}
+
+/// Parses a comment without any markup that is split into labeled sections, like the ones of
+/// LAPACK and other Fortran-converted or scientific libraries:
+///
+/// ```text
+/// /*  Purpose
+///     =======
+///
+///     DGESV computes the solution to a real system of linear equations.
+///
+///     Arguments
+///     =========
+///
+///     N       (input) INTEGER
+///             The number of linear equations.  N >= 0.
+/// */
+/// ```
+///
+/// Parameters are matched case-insensitively against `args`, the names of the function's
+/// arguments, so `N` documents the argument `n`.
+///
+/// Returns `None` unless the comment has a `Purpose`, `Arguments` or `Parameters` section and
+/// no Doxygen commands, so that other comments are left to libclang.
+pub fn parse(comment: &str, args: &[String]) -> Option<Doc> {
+    let lines = gtkdoc::strip_comment(comment);
+    let doxygen = lines.iter().any(|line| {
+        let line = line.trim_start();
+        (line.starts_with('@') || line.starts_with('\\'))
+            && line[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+    });
+    if doxygen {
+        return None;
+    }
+
+    // split the comment into sections, keeping the lines of each one as they are
+    let mut sections = vec![(Section::Description, Vec::new())];
+    let mut structured = false;
+    let mut lines = lines.iter().copied().peekable();
+    while let Some(line) = lines.next() {
+        let underlined = lines.peek().is_some_and(|next| is_rule(next));
+        let (label, rest) = match line.trim().split_once(':') {
+            Some((label, rest)) if !underlined => (label.trim(), rest.trim()),
+            _ if underlined => (line.trim().trim_end_matches(':'), ""),
+            _ => ("", ""),
+        };
+        // an argument like `result: ...` isn't a section
+        let argument = args.iter().any(|arg| arg.eq_ignore_ascii_case(label));
+        match section(label) {
+            Some(section) if !argument => {
+                structured |=
+                    section == Section::Arguments || label.eq_ignore_ascii_case("purpose");
+                if underlined {
+                    lines.next();
+                }
+                let mut lines = Vec::new();
+                if !rest.is_empty() {
+                    lines.push(rest);
+                }
+                sections.push((section, lines));
+            }
+            _ if is_rule(line) => sections.last_mut().unwrap().1.push(""),
+            _ => sections.last_mut().unwrap().1.push(line),
+        }
+    }
+    if !structured {
+        return None;
+    }
+
+    let mut doc = Doc::default();
+    for (section, lines) in sections {
+        match section {
+            Section::Description => {
+                // banners like `-- LAPACK routine (version 3.1) --` aren't docs
+                let lines = lines
+                    .into_iter()
+                    .filter(|l| !l.trim_start().starts_with("--"))
+                    .collect::<Vec<_>>();
+                doc.description.extend(paragraphs(&lines));
+            }
+            Section::Arguments => doc.params.extend(params(&lines, args)),
+            Section::Returns => doc.returns.description.extend(paragraphs(&lines)),
+            Section::Notes => doc.notes.extend(paragraphs(&lines)),
+            Section::Examples => {
+                if let Some(code) = code_block(&lines) {
+                    doc.examples.push(code);
+                }
+            }
+            Section::Ignored => {}
+        }
+    }
+    Some(doc)
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Parses entries like `N  (input) INTEGER` followed by more deeply indented description
+/// lines, or `n - the number of rows` and `n: the number of rows`.
+fn params(lines: &[&str], args: &[String]) -> Vec<Param> {
+    let base = match lines.iter().find(|l| !l.trim().is_empty()) {
+        Some(first) => indent(first),
+        None => return Vec::new(),
+    };
+    let mut params: Vec<(Param, Vec<&str>)> = Vec::new();
+    for &line in lines {
+        let entry = (indent(line) == base && !line.trim().is_empty())
+            .then(|| entry(line.trim()))
+            .flatten();
+        match entry {
+            Some((name, annotations, rest)) => {
+                // the C arguments are usually lowercase versions of the Fortran ones
+                let name = args
+                    .iter()
+                    .find(|arg| arg.eq_ignore_ascii_case(name))
+                    .map_or(name, String::as_str);
+                let mut lines = Vec::new();
+                if annotations.direction.is_some() && !rest.is_empty() {
+                    // the rest of the line is the type, like `DOUBLE PRECISION array`
+                    lines.extend([rest, ""]);
+                } else if !rest.is_empty() {
+                    lines.push(rest);
+                }
+                let param = Param {
+                    name: name.to_owned(),
+                    annotations,
+                    ..Default::default()
+                };
+                params.push((param, lines));
+            }
+            None => {
+                if let Some((_, lines)) = params.last_mut() {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    params
+        .into_iter()
+        .map(|(mut param, lines)| {
+            param.description = paragraphs(&lines);
+            param
+        })
+        .collect()
+}
+
+/// Splits the first line of a parameter entry into its name, the direction given by
+/// `(input)`, `(output)` or `(input/output)` if any, and the rest of the line.
+fn entry(line: &str) -> Option<(&str, Annotations, &str)> {
+    let end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(line.len());
+    let (name, rest) = line.split_at(end);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let mut annotations = Annotations::default();
+    if let Some(inner) = rest.strip_prefix('(') {
+        let close = inner.find(')')?;
+        annotations.direction = Some(match inner[..close].trim().to_ascii_lowercase().as_str() {
+            "input" | "in" => Direction::In,
+            "output" | "out" | "workspace" | "workspace/output" => Direction::Out,
+            "input/output" | "input or output" | "in/out" | "inout" => Direction::InOut,
+            _ => return None,
+        });
+        return Some((name, annotations, inner[close + 1..].trim()));
+    }
+    // without a direction the name needs a separator or to be on a line of its own or followed
+    // by a column of descriptions, so that prose isn't taken for an entry
+    let rest = match rest.strip_prefix(':').or_else(|| rest.strip_prefix('-')) {
+        Some(rest) => rest,
+        None if rest.is_empty() || line[end..].starts_with("  ") => rest,
+        None => return None,
+    };
+    Some((name, annotations, rest.trim_start_matches('-').trim()))
+}
+
+/// Joins lines into paragraphs at blank lines. Lines starting like list items or conditions,
+/// like `= 0: successful exit` and `< 0: ...`, each start a new paragraph.
+fn paragraphs(lines: &[&str]) -> Vec<Paragraph> {
+    let mut paras = Vec::new();
+    let mut para: Vec<&str> = Vec::new();
+    let flush = |para: &mut Vec<&str>, paras: &mut Vec<Paragraph>| {
+        if !para.is_empty() {
+            paras.push(vec![Inline::Text(para.join(" "))]);
+            para.clear();
+        }
+    };
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            flush(&mut para, &mut paras);
+            continue;
+        }
+        let item = ["= ", "< ", "> ", "<= ", ">= ", "- ", "* ", "o "]
+            .iter()
+            .any(|start| line.starts_with(start));
+        if item {
+            flush(&mut para, &mut paras);
+        }
+        para.push(line);
+    }
+    flush(&mut para, &mut paras);
+    paras
+}
+
+/// Turns the lines of an example section into a code block, without their common indentation.
+fn code_block(lines: &[&str]) -> Option<CodeBlock> {
+    let base = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent(l))
+        .min()?;
+    let code = lines
+        .iter()
+        .map(|l| l.get(base..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(CodeBlock {
+        language: "c".into(),
+        code: code.trim_matches('\n').to_owned(),
+    })
+}
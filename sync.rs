@@ -0,0 +1,1066 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{book, c, db, doc::Doc, html, interactive, json, man, markdown, rust};
+
+#[derive(clap::Args, Debug, Default)]
+#[group(skip)]
+pub struct Options {
+    /// Database written by the extract command to take docs from. Any C sources are only parsed
+    /// for symbols missing from it.
+    #[clap(long)]
+    pub load_db: Option<PathBuf>,
+    /// Remove existing docs of aliases that no longer resolve to any C symbol, for example
+    /// because it was removed or renamed upstream.
+    #[clap(long)]
+    pub prune: bool,
+    /// Replace the docs of aliases that no longer resolve with this note instead of removing
+    /// them. Implies --prune.
+    #[clap(long, value_name = "TEXT")]
+    pub prune_note: Option<String>,
+    /// Also write an mdBook with a page for every symbol found in the C sources or the database
+    /// to this directory, including those without a Rust counterpart.
+    #[clap(long, value_name = "DIR")]
+    pub emit_book: Option<PathBuf>,
+    /// File mapping aliases to the C symbols they stand for, as `alias = symbol` lines, for
+    /// aliases that don't match the C name.
+    #[clap(long, value_name = "FILE")]
+    pub alias_map: Option<PathBuf>,
+    /// Ask which symbol each alias that doesn't resolve stands for, and record the answers in
+    /// the --alias-map file.
+    #[clap(long, requires = "alias_map")]
+    pub interactive: bool,
+    /// Report aliases whose C symbol is declared but has no docs, along with where the alias
+    /// and the declaration are, to find what is missing upstream.
+    #[clap(long)]
+    pub report_undocumented: bool,
+    /// Report documented C symbols that no Rust item has docs taken from, to find API that
+    /// isn't wrapped or annotated yet.
+    #[clap(long)]
+    pub report_unused: bool,
+    /// Only report unused symbols starting with one of these prefixes.
+    #[clap(long, value_name = "PREFIX", requires = "report_unused")]
+    pub unused_prefix: Vec<String>,
+    /// Keep existing docs and add the C docs below them, after a marker line. Later runs only
+    /// replace what is below the marker. Sections of the C docs named like existing ones are
+    /// moved a level down.
+    #[clap(long)]
+    pub merge: bool,
+    /// Run the crate through `cargo expand` to also find aliased items generated by macros.
+    /// Their docs are put on the macro invocation that mentions the alias.
+    #[clap(long, conflicts_with = "expanded")]
+    pub expand: bool,
+    /// Like --expand, but read the expanded crate from this file instead of running
+    /// `cargo expand`.
+    #[clap(long, value_name = "FILE")]
+    pub expanded: Option<PathBuf>,
+    /// Cargo feature that enables the API added in a version of the C library, like
+    /// `3.10=v3_10`. Docs of symbols added after --min-version are written as
+    /// `#[cfg_attr(feature = "...", doc = "...")]` with the feature of the first version in this
+    /// table that has them.
+    #[clap(long, value_name = "VERSION=FEATURE")]
+    pub version_feature: Vec<String>,
+    /// Oldest version of the C library the crate supports. Docs of symbols added up to it are
+    /// written as usual.
+    #[clap(long, value_name = "VERSION", requires = "version_feature")]
+    pub min_version: Option<String>,
+    /// Name of the crate in a directory, like `foo-sys=foo_sys`, so that the other crates given
+    /// can link to its items as `foo_sys::...` with --intra-doc-links.
+    #[clap(long, value_name = "DIR=NAME")]
+    pub crate_name: Vec<String>,
+    /// File to keep hashes of the synced Rust sources and of the docs they were synced with in.
+    /// Later runs don't rewrite sources that haven't changed since, as long as the options and
+    /// the docs found in the C sources, the headers they include and everywhere else haven't
+    /// either. C sources are still parsed for that, and unchanged Rust sources for the items they
+    /// declare, but the latter are left out of the output.
+    #[clap(long, value_name = "FILE")]
+    pub state: Option<PathBuf>,
+    /// Program to rewrite the docs with before they are rendered, for changes only a project
+    /// wants, like renaming terms, dropping sections or adding examples. It gets the docs of
+    /// each crate's aliased items on stdin as a JSON array of objects with the `alias`, the C
+    /// `symbol`, the Rust `path`, `file` and `line` of the item and its `doc`, in the format of
+    /// `extract --format json`, and writes the array back with the docs changed. Items left out
+    /// keep their docs. The program and its arguments are split at whitespace.
+    #[clap(long, value_name = "COMMAND")]
+    pub doc_filter: Option<String>,
+    #[clap(flatten)]
+    pub c: c::Options,
+    #[clap(flatten)]
+    pub markdown: markdown::Options,
+    #[clap(flatten)]
+    pub rust: rust::Options,
+    /// List of Rust sources to parse and insert doc comments into. Sources of several crates,
+    /// like `foo-sys` and `foo`, can be given at once to only look up the C symbols once; each
+    /// crate gets docs adapted to its own items.
+    pub rust_srcs: Vec<PathBuf>,
+}
+
+/// What a sync produced, for the caller to write out and report.
+#[derive(Debug, Default)]
+pub struct SyncOutcome {
+    /// The Rust sources with the new docs, in the order they were found.
+    pub files: Vec<SyncedFile>,
+    pub warnings: Vec<Warning>,
+    /// Every file the docs were made from: the Rust sources, the C sources and the headers they
+    /// include, the database, the alias map and the expanded code, for dependency tracking.
+    pub inputs: BTreeSet<PathBuf>,
+    /// The hashes to keep with `--state`, to save once the files are written.
+    pub state: Option<PendingState>,
+}
+
+/// The state of a sync with `--state`, which is only saved once the synced files are written, so
+/// that files a failed run didn't write aren't taken as unchanged by the next one.
+#[derive(Debug)]
+pub struct PendingState {
+    path: PathBuf,
+    state: State,
+}
+
+impl PendingState {
+    pub fn save(&self) -> std::io::Result<()> {
+        self.state.save(&self.path)
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncedFile {
+    pub path: PathBuf,
+    /// The source as it was before, for backups.
+    pub original: String,
+    pub source: String,
+    /// Set if any docs were replaced.
+    pub changed: bool,
+}
+
+/// Progress of a sync, for tools embedding it to show.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// A Rust source is about to be parsed.
+    ParsingRust(&'a Path),
+    /// A C source is about to be parsed for docs.
+    ParsingC(&'a Path),
+    /// The docs of a symbol were found in a C source, HTML page or man page, parsed with the
+    /// define set `config` if there are several. `merged` is set if they complete docs found in
+    /// an earlier declaration.
+    Found {
+        symbol: &'a str,
+        path: &'a Path,
+        config: Option<&'a str>,
+        merged: bool,
+    },
+    /// An alias was only found after macro expansion, so its docs go in front of the macro
+    /// invocation in the Rust source that mentions it.
+    Expanded { path: &'a Path, alias: &'a str },
+    /// Docs were rendered for an alias from the C symbol it resolves to.
+    Matched { alias: &'a str, symbol: &'a str },
+    /// The docs of an alias were written to an item that only exists with a `cfg`, usually one
+    /// of several variants for different targets.
+    WrittenWithCfg {
+        alias: &'a str,
+        path: &'a Path,
+        line: usize,
+        cfg: &'a str,
+    },
+    /// The docs of a Rust source were replaced, so it is ready to be written.
+    Synced(&'a SyncedFile),
+}
+
+/// The error of a sync stopped by its progress callback.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A flag to stop a sync from another thread, shared by its clones. The sync stops at the next
+/// source or alias once it is set.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Returns `ControlFlow::Break` once the token is cancelled, for use as the progress
+    /// callback of `run_with_progress`, on its own or along with other progress reporting.
+    pub fn check(&self) -> ControlFlow<()> {
+        if self.is_cancelled() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Something a sync found that may need attention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A Rust source couldn't be parsed, so it was left alone.
+    Skipped { path: PathBuf, error: String },
+    /// A Rust source uses syntax the parser doesn't know, so the items that couldn't be parsed
+    /// were left alone.
+    PartialParse(rust::PartialParse),
+    /// A file named by `\example` or `\snippet` in the docs of a C symbol wasn't found.
+    MissingExample { symbol: String, file: String },
+    /// An alias doesn't resolve to any C symbol with docs.
+    Unresolved { alias: String },
+    /// Several Rust items have the same C symbol as alias, so references to it aren't linked.
+    Ambiguous { symbol: String },
+    /// The generated docs of an alias contain something rustdoc would warn about.
+    Docs { alias: String, message: String },
+    /// An alias resolves to a C declaration without docs, found with `report_undocumented`.
+    Undocumented {
+        path: PathBuf,
+        line: usize,
+        alias: String,
+        c_file: PathBuf,
+        c_line: u32,
+    },
+    /// The docs of an item were left alone because they overlap those of another item.
+    Overlap {
+        path: PathBuf,
+        overlap: rust::Overlap,
+    },
+    /// A documented C symbol isn't used by any Rust item, found with `report_unused`.
+    Unused {
+        symbol: String,
+        location: Option<db::Location>,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Skipped { path, error } => {
+                write!(f, "warning: skipping {}: {}", path.display(), error)
+            }
+            Self::PartialParse(partial) => write!(
+                f,
+                "warning: {}:{}:{}: {}, skipping the items that can't be parsed",
+                partial.path.display(),
+                partial.line,
+                partial.column,
+                partial.error
+            ),
+            Self::MissingExample { symbol, file } => write!(
+                f,
+                "warning: example {} in the docs of {} not found",
+                file, symbol
+            ),
+            Self::Unresolved { alias } => {
+                write!(f, "warning: {} doesn't resolve to any C symbol", alias)
+            }
+            Self::Ambiguous { symbol } => write!(
+                f,
+                "warning: several items have {} as alias, so it isn't linked to",
+                symbol
+            ),
+            Self::Docs { alias, message } => write!(f, "warning: docs of {}: {}", alias, message),
+            Self::Undocumented {
+                path,
+                line,
+                alias,
+                c_file,
+                c_line,
+            } => write!(
+                f,
+                "{}:{}: {} has no docs, declared at {}:{}",
+                path.display(),
+                line,
+                alias,
+                c_file.display(),
+                c_line
+            ),
+            Self::Overlap { path, overlap } => write!(
+                f,
+                "{}:{}: docs of {} overlap those of {} on line {}, so they were left alone",
+                path.display(),
+                overlap.line,
+                overlap.alias,
+                overlap.other,
+                overlap.other_line
+            ),
+            Self::Unused {
+                symbol,
+                location: Some(location),
+            } => write!(
+                f,
+                "{}:{}: {} is not used by any Rust item",
+                location.file.display(),
+                location.line,
+                symbol
+            ),
+            Self::Unused {
+                symbol,
+                location: None,
+            } => write!(f, "{} is not used by any Rust item", symbol),
+        }
+    }
+}
+
+/// Copies docs into the Rust sources given in `options`, without writing them. Returns the new
+/// sources along with any warnings, for the caller to decide what to write and what to fail on.
+pub fn run(options: &Options) -> Result<SyncOutcome, Box<dyn std::error::Error>> {
+    run_with_progress(options, |_| ControlFlow::Continue(()))
+}
+
+/// Like `run`, but calls `progress` with every step of the sync. Returning `ControlFlow::Break`
+/// from it stops the sync with a `Cancelled` error before anything else is done.
+pub fn run_with_progress(
+    options: &Options,
+    mut progress: impl FnMut(Event) -> ControlFlow<()>,
+) -> Result<SyncOutcome, Box<dyn std::error::Error>> {
+    let prune = (options.prune || options.prune_note.is_some()).then(|| {
+        options
+            .prune_note
+            .as_deref()
+            .map(|note| {
+                note.lines()
+                    .map(|line| format!("/// {}", line).trim_end().to_owned())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default()
+    });
+    if prune.is_some()
+        && options.load_db.is_none()
+        && !options.c.has_sources()
+        && options.c.html_docs.is_empty()
+        && options.c.man_pages.is_empty()
+    {
+        return Err("--prune needs C sources or a database to look up symbols in".into());
+    }
+    if options.report_undocumented && !options.c.has_sources() {
+        return Err("--report-undocumented needs C sources to find declarations in".into());
+    }
+    let mut version_features = Vec::new();
+    for entry in &options.version_feature {
+        let version = entry.split_once('=').and_then(|(version, feature)| {
+            Some((parse_version(version)?, feature.trim().to_owned()))
+        });
+        match version {
+            Some(version) => version_features.push(version),
+            None => return Err(format!("invalid --version-feature {:?}", entry).into()),
+        }
+    }
+    version_features.sort();
+    let mut crate_names = HashMap::new();
+    for entry in &options.crate_name {
+        let (dir, name) = entry
+            .rsplit_once('=')
+            .ok_or_else(|| format!("invalid --crate-name {:?}", entry))?;
+        crate_names.insert(std::fs::canonicalize(dir)?, name.trim().replace('-', "_"));
+    }
+    let min_version = match &options.min_version {
+        Some(min) => Some(parse_version(min).ok_or("invalid --min-version")?),
+        None => None,
+    };
+    let mut warnings = Vec::new();
+    let mut files = parse_rust_srcs(
+        &options.rust_srcs,
+        &options.rust,
+        &mut warnings,
+        &mut progress,
+    )?;
+    let mut inputs = files
+        .iter()
+        .map(|f| f.path.clone())
+        .collect::<BTreeSet<_>>();
+    inputs.extend(
+        [&options.load_db, &options.alias_map, &options.expanded]
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    let expanded = match &options.expanded {
+        Some(path) => Some(std::fs::read_to_string(path)?),
+        None if options.expand => Some(cargo_expand(&files)?),
+        None => None,
+    };
+    if let Some(expanded) = &expanded {
+        let mut known = files
+            .iter()
+            .flat_map(|f| f.doc_locations.keys().cloned())
+            .collect::<HashSet<_>>();
+        for file in &mut files {
+            let added = file.add_expanded(expanded, &known, &options.rust)?;
+            for alias in &added {
+                let event = Event::Expanded {
+                    path: &file.path,
+                    alias,
+                };
+                if progress(event).is_break() {
+                    return Err(Cancelled.into());
+                }
+            }
+            known.extend(added);
+        }
+    }
+    // every file found the same errors in the expanded code
+    let mut partial_parses = Vec::new();
+    for partial in files.iter().flat_map(|f| &f.partial_parses) {
+        if !partial_parses.contains(partial) {
+            partial_parses.push(partial.clone());
+        }
+    }
+    warnings.extend(partial_parses.into_iter().map(Warning::PartialParse));
+    let mut db = match &options.load_db {
+        Some(path) => db::Database::load(path)?,
+        None => Default::default(),
+    };
+    let alias_map = match &options.alias_map {
+        Some(path) => interactive::load_map(path)?,
+        None => HashMap::new(),
+    };
+    for (alias, symbol) in &alias_map {
+        if !symbol.is_empty() {
+            db.aliases.insert(alias.clone(), symbol.clone());
+        }
+    }
+    let wanted = files
+        .iter()
+        .flat_map(|f| f.doc_locations.keys().cloned())
+        .collect::<HashSet<_>>();
+    let alternatives = files
+        .iter()
+        .flat_map(|f| &f.alternatives)
+        .collect::<Vec<_>>();
+    // the book, the choices offered and the unused symbols need every symbol, which also
+    // covers any copied docs
+    let everything = options.emit_book.is_some() || options.interactive || options.report_unused;
+    let mut names = wanted.clone();
+    names.extend(
+        alternatives
+            .iter()
+            .flat_map(|(_, others)| others.iter().cloned()),
+    );
+    let mut missing_examples = Vec::new();
+    let mut c_progress = |event: c::Event| match event {
+        c::Event::Parsing(path) => progress(Event::ParsingC(path)),
+        c::Event::Found {
+            symbol,
+            path,
+            config,
+            merged,
+        } => progress(Event::Found {
+            symbol,
+            path,
+            config,
+            merged,
+        }),
+        c::Event::MissingExample { symbol, file } => {
+            missing_examples.push(Warning::MissingExample {
+                symbol: symbol.to_owned(),
+                file: file.to_owned(),
+            });
+            ControlFlow::Continue(())
+        }
+    };
+    if everything && options.c.has_sources() {
+        inputs.extend(c::extract(&options.c, None, &mut db, &mut c_progress)?);
+    } else if options.c.has_sources() {
+        inputs.extend(c::extract(
+            &options.c,
+            Some(&names),
+            &mut db,
+            &mut c_progress,
+        )?);
+        let mut missing = db.missing_copies(&names);
+        missing.extend(db.missing_callbacks(&names));
+        if !missing.is_empty() {
+            inputs.extend(c::extract(
+                &options.c,
+                Some(&missing),
+                &mut db,
+                &mut c_progress,
+            )?);
+        }
+    }
+    warnings.extend(missing_examples);
+    // finding docs in HTML docs and man pages can't be stopped
+    let mut found = |symbol: &str, path: &Path| {
+        let _ = progress(Event::Found {
+            symbol,
+            path,
+            config: None,
+            merged: false,
+        });
+    };
+    html::extract(
+        &options.c.html_docs,
+        (!everything).then_some(&names),
+        &mut db,
+        &mut found,
+    )?;
+    man::extract(
+        &options.c.man_pages,
+        (!everything).then_some(&names),
+        &mut db,
+        &mut found,
+    )?;
+    db.expand_copies();
+    db.expand_callbacks();
+    rust::resolve_alternatives(&files, &mut db);
+    if let (true, Some(map)) = (options.interactive, &options.alias_map) {
+        let mut unresolved = wanted
+            .iter()
+            .filter(|alias| db.get(alias).is_none() && !alias_map.contains_key(*alias))
+            .collect::<Vec<_>>();
+        unresolved.sort_unstable();
+        for alias in unresolved {
+            let symbol = match interactive::choose(alias, &db) {
+                Ok(symbol) => symbol,
+                // out of answers, the rest are asked for next time
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            interactive::record(map, alias, symbol.as_deref())?;
+            if let Some(symbol) = symbol {
+                db.aliases.insert(alias.clone(), symbol);
+            }
+        }
+    }
+    let mut unresolved = wanted
+        .iter()
+        .filter(|alias| db.get(alias).is_none())
+        .collect::<Vec<_>>();
+    unresolved.sort_unstable();
+    warnings.extend(unresolved.into_iter().map(|alias| Warning::Unresolved {
+        alias: alias.clone(),
+    }));
+    let mut state = match &options.state {
+        Some(path) => State::load(path)?,
+        None => State::default(),
+    };
+    let hash = match &options.state {
+        Some(_) => inputs_hash(options, &db, &files, expanded.as_deref())?,
+        None => 0,
+    };
+    if state.inputs != hash {
+        state = State {
+            inputs: hash,
+            files: HashMap::new(),
+        };
+    }
+    // sources that haven't changed since the last sync are still parsed for the items they
+    // declare, which other sources link to, but aren't rewritten
+    let unchanged = files
+        .iter()
+        .filter(|f| state.files.get(&f.path) == Some(&db::hash(f.source.as_bytes())))
+        .map(|f| f.path.clone())
+        .collect::<HashSet<_>>();
+    // every crate, like `foo-sys` and `foo`, gets docs rendered for its own items from the
+    // same symbols
+    let mut crates = BTreeMap::<_, Vec<_>>::new();
+    for file in &files {
+        crates.entry(file.crate_dir.clone()).or_default().push(file);
+    }
+    let mut crate_docs = HashMap::new();
+    let mut docs = HashMap::new();
+    let mut features = HashMap::new();
+    let mut ambiguous = BTreeSet::new();
+    for (crate_dir, files) in &crates {
+        // links only work within the crate or to crates with a name, and only if it's clear
+        // which item an alias belongs to
+        let mut paths = HashMap::new();
+        if options.markdown.intra_doc_links {
+            let own = files.iter().flat_map(|f| &f.item_paths).cloned();
+            paths = unique_paths(own, &db);
+            let others = crates
+                .iter()
+                .filter(|(dir, _)| dir != &crate_dir)
+                .filter_map(|(dir, files)| Some((crate_names.get(dir.as_ref()?)?, files)))
+                .flat_map(|(name, files)| {
+                    files
+                        .iter()
+                        .flat_map(|f| &f.item_paths)
+                        .map(|(alias, path)| {
+                            let path = rust::ItemPath {
+                                module: path.module.replacen("crate", name, 1),
+                                item: path.item.clone(),
+                            };
+                            (alias.clone(), path)
+                        })
+                });
+            for (name, path) in unique_paths(others, &db) {
+                paths.entry(name).or_insert(path);
+            }
+        }
+        let paths = paths
+            .into_iter()
+            .filter_map(|(name, path)| match path {
+                Some(path) => Some((name, path)),
+                None => {
+                    ambiguous.insert(name);
+                    None
+                }
+            })
+            .collect::<HashMap<_, _>>();
+        let signatures = rust::shared_signatures(files.iter().copied());
+        let wanted = files
+            .iter()
+            .flat_map(|f| f.doc_locations.keys())
+            .collect::<BTreeSet<_>>();
+        let mut filtered = match &options.doc_filter {
+            Some(command) => {
+                let items = wanted
+                    .iter()
+                    .filter_map(|name| {
+                        let symbol = db.get(name)?;
+                        let location = files.iter().find_map(|f| {
+                            Some((f.path.as_path(), f.doc_locations.get(*name)?.first()?.line))
+                        });
+                        Some(FilterItem {
+                            alias: name,
+                            symbol: db.resolve(name),
+                            path: paths
+                                .get(*name)
+                                .map(|path| format!("{}::{}", path.module, path.item)),
+                            file: location.map(|(file, _)| file),
+                            line: location.map(|(_, line)| line),
+                            doc: &symbol.doc,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                filter_docs(command, &items)?
+            }
+            None => HashMap::new(),
+        };
+        let mut rendered = HashMap::new();
+        for name in wanted {
+            let mut symbol = match db.get(name) {
+                Some(symbol) => symbol,
+                None => continue,
+            };
+            let filtered_symbol;
+            if let Some(doc) = filtered.remove(name) {
+                filtered_symbol = db::Symbol {
+                    doc,
+                    ..symbol.clone()
+                };
+                symbol = &filtered_symbol;
+            }
+            if !docs.contains_key(name) {
+                markdown::copy_assets(symbol, &options.markdown)?;
+            }
+            let doc = markdown::render(
+                db.resolve(name),
+                symbol,
+                &db,
+                &paths,
+                signatures.get(name.as_str()).copied().flatten(),
+                &options.markdown,
+            );
+            warnings.extend(
+                markdown::check(&doc)
+                    .into_iter()
+                    .map(|message| Warning::Docs {
+                        alias: name.clone(),
+                        message,
+                    }),
+            );
+            let feature = symbol.doc.since.as_deref().and_then(|since| {
+                version_feature(since, &version_features, min_version.as_deref())
+            });
+            if let Some(feature) = feature {
+                features.insert(name.clone(), feature.to_owned());
+            }
+            let matched = Event::Matched {
+                alias: name,
+                symbol: db.resolve(name),
+            };
+            if progress(matched).is_break() {
+                return Err(Cancelled.into());
+            }
+            docs.insert(name.clone(), doc.clone());
+            rendered.insert(name.clone(), doc);
+        }
+        crate_docs.insert(crate_dir.clone(), rendered);
+    }
+    warnings.extend(
+        ambiguous
+            .into_iter()
+            .map(|symbol| Warning::Ambiguous { symbol }),
+    );
+    if options.report_undocumented {
+        warnings.extend(report_undocumented(&files, &docs, &db, &options.c)?);
+    }
+    if options.report_unused {
+        warnings.extend(report_unused(&docs, &db, &options.unused_prefix));
+    }
+    if let Some(dir) = &options.emit_book {
+        book::emit(dir, &db, &options.markdown)?;
+    }
+    let mut synced = Vec::new();
+    for mut file in files {
+        if unchanged.contains(&file.path) {
+            continue;
+        }
+        let docs = &crate_docs[&file.crate_dir];
+        // items that only exist for some targets usually come in several variants
+        let mut written = file
+            .doc_locations
+            .iter()
+            .filter(|(alias, _)| docs.contains_key(*alias))
+            .flat_map(|(alias, locations)| locations.iter().map(move |l| (l.line, alias, l)))
+            .filter_map(|(line, alias, l)| Some((line, alias, l.cfg.as_ref()?)))
+            .collect::<Vec<_>>();
+        written.sort_unstable();
+        for (line, alias, cfg) in written {
+            let event = Event::WrittenWithCfg {
+                alias,
+                path: &file.path,
+                line,
+                cfg,
+            };
+            if progress(event).is_break() {
+                return Err(Cancelled.into());
+            }
+        }
+        let original = file.source.clone();
+        let merged;
+        let docs = if options.merge {
+            merged = docs
+                .iter()
+                .map(|(alias, doc)| {
+                    let doc = match file.docs.get(alias) {
+                        Some(existing) => markdown::merge(existing, doc),
+                        None => doc.clone(),
+                    };
+                    (alias.clone(), doc)
+                })
+                .collect();
+            &merged
+        } else {
+            docs
+        };
+        let mut overlaps = Vec::new();
+        let changed = file.replace_docs(docs, &features, prune.as_deref(), &mut overlaps);
+        warnings.extend(overlaps.into_iter().map(|overlap| Warning::Overlap {
+            path: file.path.clone(),
+            overlap,
+        }));
+        let file = SyncedFile {
+            path: file.path,
+            original,
+            source: file.source,
+            changed,
+        };
+        if progress(Event::Synced(&file)).is_break() {
+            return Err(Cancelled.into());
+        }
+        synced.push(file);
+    }
+    let state = options.state.clone().map(|path| {
+        for file in &synced {
+            state
+                .files
+                .insert(file.path.clone(), db::hash(file.source.as_bytes()));
+        }
+        PendingState { path, state }
+    });
+    Ok(SyncOutcome {
+        files: synced,
+        warnings,
+        inputs,
+        state,
+    })
+}
+
+/// Parses the Rust sources matching the globs in `srcs`. Sources that can't be parsed are skipped
+/// with a warning. `progress` is called with every source before it is parsed, like in
+/// `run_with_progress`.
+pub fn parse_rust_srcs(
+    srcs: &[PathBuf],
+    options: &rust::Options,
+    warnings: &mut Vec<Warning>,
+    progress: &mut dyn FnMut(Event) -> ControlFlow<()>,
+) -> Result<Vec<rust::RustFile>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    for src in srcs {
+        for path in glob::glob(src.to_string_lossy().as_ref())? {
+            let path = path?;
+            if !path.is_file() {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path)?;
+            if progress(Event::ParsingRust(&path)).is_break() {
+                return Err(Cancelled.into());
+            }
+            match rust::RustFile::from_source(path.clone(), source, options) {
+                Ok(file) => files.push(file),
+                Err(e) if e.is::<proc_macro2::LexError>() => warnings.push(Warning::Skipped {
+                    path,
+                    error: e.to_string(),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Hashes of the inputs of the last sync and of the Rust sources it wrote, kept with --state.
+#[derive(Debug, Default)]
+struct State {
+    inputs: u64,
+    files: HashMap<PathBuf, u64>,
+}
+
+impl State {
+    /// Reads a state file, made of a line with the hash of the inputs followed by a line with
+    /// the hash and path of every source. A missing file is an empty state.
+    fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut lines = text.lines();
+        let inputs = lines
+            .next()
+            .and_then(|line| u64::from_str_radix(line.trim(), 16).ok())
+            .unwrap_or_default();
+        let files = lines
+            .filter_map(|line| {
+                let (hash, path) = line.split_once(' ')?;
+                Some((PathBuf::from(path), u64::from_str_radix(hash, 16).ok()?))
+            })
+            .collect();
+        Ok(Self { inputs, files })
+    }
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort_unstable();
+        let mut text = format!("{:016x}\n", self.inputs);
+        for (path, hash) in files {
+            text.push_str(&format!("{:016x} {}\n", hash, path.display()));
+        }
+        std::fs::write(path, text)
+    }
+}
+
+/// Hashes what the synced docs depend on besides the Rust source they are written to: the
+/// options, the docs found, wherever they came from, the items of all Rust sources, which docs
+/// link to, and the expanded code.
+fn inputs_hash(
+    options: &Options,
+    db: &db::Database,
+    files: &[rust::RustFile],
+    expanded: Option<&str>,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut data = format!("{:?}", options).into_bytes();
+    data.extend(bincode::serialize(db)?);
+    for file in files {
+        data.extend(format!("{:?}{:?}", file.path, file.item_paths).into_bytes());
+    }
+    data.extend(expanded.unwrap_or_default().as_bytes());
+    Ok(db::hash(&data))
+}
+
+/// Finds every alias that resolves to a C declaration without docs, with the locations of both.
+fn report_undocumented(
+    files: &[rust::RustFile],
+    docs: &HashMap<String, String>,
+    db: &db::Database,
+    options: &c::Options,
+) -> Result<Vec<Warning>, Box<dyn std::error::Error>> {
+    let undocumented = files
+        .iter()
+        .flat_map(|f| f.doc_locations.keys())
+        .filter(|alias| !docs.contains_key(*alias))
+        .map(|alias| db.resolve(alias).to_owned())
+        .collect::<HashSet<_>>();
+    if undocumented.is_empty() {
+        return Ok(Vec::new());
+    }
+    let decls = c::declarations(options, &undocumented)?;
+    let mut found = files
+        .iter()
+        .flat_map(|f| f.doc_locations.iter().map(move |(alias, l)| (f, alias, l)))
+        .filter(|(_, alias, _)| !docs.contains_key(*alias))
+        .flat_map(|(f, alias, locations)| {
+            let decl = decls.get(db.resolve(alias))?;
+            Some(
+                locations
+                    .iter()
+                    .map(move |l| (&f.path, l.line, alias, decl)),
+            )
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    found.sort_unstable_by(|a, b| (a.0, a.1, a.2).cmp(&(b.0, b.1, b.2)));
+    Ok(found
+        .into_iter()
+        .map(|(path, line, alias, decl)| Warning::Undocumented {
+            path: path.clone(),
+            line,
+            alias: alias.clone(),
+            c_file: decl.file.clone(),
+            c_line: decl.line,
+        })
+        .collect())
+}
+
+/// Finds every documented C symbol that none of the aliases resolve to. Fields are left out,
+/// since they are used along with their record.
+fn report_unused(
+    docs: &HashMap<String, String>,
+    db: &db::Database,
+    prefixes: &[String],
+) -> Vec<Warning> {
+    let used = docs
+        .keys()
+        .map(|alias| db.resolve(alias))
+        .collect::<HashSet<_>>();
+    let mut unused = db
+        .symbols
+        .iter()
+        .filter(|(name, _)| !name.contains('.') && !used.contains(name.as_str()))
+        .filter(|(name, _)| prefixes.is_empty() || prefixes.iter().any(|p| name.starts_with(p)))
+        .collect::<Vec<_>>();
+    unused.sort_unstable_by_key(|(name, _)| *name);
+    unused
+        .into_iter()
+        .map(|(name, symbol)| Warning::Unused {
+            symbol: name.clone(),
+            location: symbol.location.clone(),
+        })
+        .collect()
+}
+
+/// Maps the symbols that aliases resolve to to the paths of the items with them, or to `None`
+/// if several items have the same symbol.
+fn unique_paths(
+    item_paths: impl Iterator<Item = (String, rust::ItemPath)>,
+    db: &db::Database,
+) -> HashMap<String, Option<rust::ItemPath>> {
+    let mut paths = HashMap::new();
+    for (alias, path) in item_paths {
+        paths
+            .entry(db.resolve(&alias).to_owned())
+            .and_modify(|p: &mut Option<_>| {
+                if p.as_ref() != Some(&path) {
+                    *p = None;
+                }
+            })
+            .or_insert(Some(path));
+    }
+    paths
+}
+
+/// Parses a version like `3.10` or `GTK 4.2` into its numbers.
+fn parse_version(text: &str) -> Option<Vec<u32>> {
+    let word = text
+        .split_whitespace()
+        .rfind(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+    word.trim_end_matches(|c: char| !c.is_ascii_digit())
+        .split('.')
+        .map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Returns the feature that enables a symbol added in version `since`: the one of the first
+/// version in `table` that isn't older, unless `since` isn't newer than `min`.
+fn version_feature<'a>(
+    since: &str,
+    table: &'a [(Vec<u32>, String)],
+    min: Option<&[u32]>,
+) -> Option<&'a str> {
+    let since = parse_version(since)?;
+    if min.is_some_and(|min| since.as_slice() <= min) {
+        return None;
+    }
+    table
+        .iter()
+        .find(|(version, _)| *version >= since)
+        .map(|(_, feature)| feature.as_str())
+}
+
+/// An aliased item as given to the --doc-filter program.
+#[derive(serde::Serialize)]
+struct FilterItem<'a> {
+    alias: &'a str,
+    symbol: &'a str,
+    path: Option<String>,
+    file: Option<&'a Path>,
+    line: Option<usize>,
+    doc: &'a Doc,
+}
+
+/// What is read back from the --doc-filter program for every item.
+#[derive(serde::Deserialize)]
+struct FilteredItem {
+    alias: String,
+    doc: Doc,
+}
+
+/// Runs the --doc-filter program on `items` and returns the docs it wrote back, by alias.
+fn filter_docs(
+    command: &str,
+    items: &[FilterItem],
+) -> Result<HashMap<String, Doc>, Box<dyn std::error::Error>> {
+    let mut args = command.split_whitespace();
+    let program = args.next().ok_or("--doc-filter is empty")?;
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't run {}: {}", program, e))?;
+    let input = json::to_string(items)?;
+    // written from another thread, since the program may start writing before it has read
+    // everything
+    let mut stdin = child.stdin.take().unwrap();
+    let writer =
+        std::thread::spawn(move || std::io::Write::write_all(&mut stdin, input.as_bytes()));
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("{} failed with {}", command, output.status).into());
+    }
+    writer.join().unwrap()?;
+    let items: Vec<FilteredItem> = json::from_str(&String::from_utf8(output.stdout)?)
+        .map_err(|e| format!("invalid output of {}: {}", command, e))?;
+    Ok(items
+        .into_iter()
+        .map(|item| (item.alias, item.doc))
+        .collect())
+}
+
+/// Runs `cargo expand` in the crate the Rust sources belong to, or else in the current
+/// directory, and returns the expanded crate.
+fn cargo_expand(files: &[rust::RustFile]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut command = std::process::Command::new("cargo");
+    command.arg("expand");
+    if let Some(dir) = files.iter().find_map(|f| f.crate_dir.as_ref()) {
+        command.current_dir(dir);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo expand failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
@@ -0,0 +1,29 @@
+//! Golden-file tests of the docs written by `sync_to_string`. Each directory in `tests/golden`
+//! has a database `db.json`, a Rust source `input.rs` and the source with the docs written into
+//! it, `expected.rs`. Run with `BLESS=1` to write `expected.rs` from the current output.
+
+use std::path::Path;
+
+use rust_clang_doc_generator::{db::Database, json, sync_to_string};
+
+#[test]
+fn golden() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut cases = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    cases.sort();
+    assert!(!cases.is_empty());
+    for case in cases {
+        let read = |name: &str| std::fs::read_to_string(case.join(name)).unwrap();
+        let db: Database = json::from_str(&read("db.json")).unwrap();
+        let output = sync_to_string(&read("input.rs"), &db).unwrap();
+        if bless {
+            std::fs::write(case.join("expected.rs"), &output).unwrap();
+        } else {
+            assert_eq!(output, read("expected.rs"), "{}", case.display());
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/// An object with a name.
+#[doc(alias = "FooBar")]
+pub struct Bar(*mut ffi::FooBar);
+
+impl Bar {
+    /// Sets the name of `bar`. See `foo_bar_get_name()`.
+    ///
+    /// # Parameters
+    ///
+    /// * `bar` — a `FooBar`
+    /// * `name` — the new name, or `NULL` to unset it. May be `NULL`.
+    ///
+    /// # Returns
+    ///
+    /// `TRUE` if the name changed
+    #[doc(alias = "foo_bar_set_name")]
+    pub fn set_name(&self, name: Option<&str>) -> bool {
+        unimplemented!()
+    }
+
+    /// Gets the name of `bar`.
+    ///
+    /// # Parameters
+    ///
+    /// * `bar` — a `FooBar`
+    ///
+    /// # Returns
+    ///
+    /// the name. May be `NULL`. Ownership stays with the function; the value must not be freed.
+    #[doc(alias = "foo_bar_get_name")]
+    pub fn name(&self) -> Option<&str> {
+        unimplemented!()
+    }
+}
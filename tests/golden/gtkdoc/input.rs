@@ -0,0 +1,15 @@
+/// Old docs that get replaced.
+#[doc(alias = "FooBar")]
+pub struct Bar(*mut ffi::FooBar);
+
+impl Bar {
+    #[doc(alias = "foo_bar_set_name")]
+    pub fn set_name(&self, name: Option<&str>) -> bool {
+        unimplemented!()
+    }
+
+    #[doc(alias = "foo_bar_get_name")]
+    pub fn name(&self) -> Option<&str> {
+        unimplemented!()
+    }
+}
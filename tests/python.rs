@@ -0,0 +1,52 @@
+//! Runs the smoke test of the Python bindings in `python/`, with the library built as a cdylib
+//! and a database written from the golden test `gtkdoc`. Skipped if there is no `python3`.
+#![cfg(feature = "capi")]
+
+use std::{path::Path, process::Command};
+
+use rust_clang_doc_generator::{db::Database, json};
+
+#[test]
+fn python_bindings() {
+    if Command::new("python3").arg("--version").output().is_err() {
+        eprintln!("skipped: python3 not found");
+        return;
+    }
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let json = std::fs::read_to_string(root.join("tests/golden/gtkdoc/db.json")).unwrap();
+    let db: Database = json::from_str(&json).unwrap();
+    let db_path = tmp.join("python.db");
+    db.save(&db_path).unwrap();
+    // a target directory of its own, so that the build doesn't wait for the one running this
+    let target = tmp.join("python-target");
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "rustc",
+            "--lib",
+            "--no-default-features",
+            "--features",
+            "capi",
+        ])
+        .args(["--crate-type", "cdylib", "--target-dir"])
+        .arg(&target)
+        .current_dir(root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let name = if cfg!(target_os = "macos") {
+        "librust_clang_doc_generator.dylib"
+    } else if cfg!(windows) {
+        "rust_clang_doc_generator.dll"
+    } else {
+        "librust_clang_doc_generator.so"
+    };
+    let status = Command::new("python3")
+        .args(["-m", "unittest", "-v", "test_rust_clang_doc_generator"])
+        .current_dir(root.join("python"))
+        .env("RCDG_LIBRARY", target.join("debug").join(name))
+        .env("RCDG_TEST_DB", &db_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,76 @@
+//! Tests of `sync` with `--state`, which need libclang to parse the C sources and so only run
+//! with `--ignored`.
+#![cfg(feature = "libclang")]
+
+use std::path::{Path, PathBuf};
+
+use rust_clang_doc_generator::sync;
+
+/// A directory of its own in the temporary directory, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-clang-doc-generator-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Syncs and writes the files like the sync command does with -i. Returns the paths written.
+fn sync(options: &sync::Options) -> Vec<PathBuf> {
+    let outcome = sync::run(options).unwrap();
+    for file in &outcome.files {
+        std::fs::write(&file.path, &file.source).unwrap();
+    }
+    outcome.state.unwrap().save().unwrap();
+    outcome.files.into_iter().map(|f| f.path).collect()
+}
+
+fn write(path: &Path, text: &str) {
+    std::fs::write(path, text).unwrap();
+}
+
+#[test]
+#[ignore = "needs libclang"]
+fn included_header_changed() {
+    let dir = TempDir::new("state");
+    let header = dir.0.join("foo.h");
+    let source = dir.0.join("foo.c");
+    let rust = dir.0.join("lib.rs");
+    write(&header, "/** Opens a foo. */\nint foo_open(void);\n");
+    write(
+        &source,
+        "#include \"foo.h\"\n\nint main(void) { return foo_open(); }\n",
+    );
+    write(&rust, "#[doc(alias = \"foo_open\")]\npub fn open() {}\n");
+    let options = sync::Options {
+        state: Some(dir.0.join("state")),
+        c: rust_clang_doc_generator::c::Options {
+            c_srcs: vec![source],
+            ..Default::default()
+        },
+        rust_srcs: vec![rust.clone()],
+        ..Default::default()
+    };
+    assert_eq!(sync(&options), std::slice::from_ref(&rust));
+    assert!(std::fs::read_to_string(&rust)
+        .unwrap()
+        .starts_with("/// Opens a foo.\n"));
+    assert_eq!(sync(&options), Vec::<PathBuf>::new());
+    write(&header, "/** Opens a new foo. */\nint foo_open(void);\n");
+    assert_eq!(sync(&options), std::slice::from_ref(&rust));
+    assert!(std::fs::read_to_string(&rust)
+        .unwrap()
+        .starts_with("/// Opens a new foo.\n"));
+}